@@ -0,0 +1,322 @@
+use crate::collection::map::{HashMap, Map};
+use std::borrow::Borrow;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::{mem, ptr};
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    prev: *mut Node<K, V>,
+    next: *mut Node<K, V>,
+}
+
+/// the map key, it points at the key inside a node so the key is stored once
+struct KeyRef<K>(*const K);
+
+impl<K: Hash> Hash for KeyRef<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        unsafe { (*self.0).hash(state) }
+    }
+}
+
+impl<K: PartialEq> PartialEq for KeyRef<K> {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { *self.0 == *other.0 }
+    }
+}
+
+impl<K: Eq> Eq for KeyRef<K> {}
+
+/**
+A borrowed key on the lookup side. `KeyRef<K>` can not borrow as `Q` itself without
+overlapping `impl Borrow<T> for T`, so it borrows as this transparent wrapper instead.
+*/
+#[repr(transparent)]
+struct KeyWrapper<Q: ?Sized>(Q);
+
+impl<Q: ?Sized> KeyWrapper<Q> {
+    fn from_ref(key: &Q) -> &Self {
+        unsafe { &*(key as *const Q as *const KeyWrapper<Q>) }
+    }
+}
+
+impl<Q: ?Sized + Hash> Hash for KeyWrapper<Q> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<Q: ?Sized + PartialEq> PartialEq for KeyWrapper<Q> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<Q: ?Sized + Eq> Eq for KeyWrapper<Q> {}
+
+impl<K, Q> Borrow<KeyWrapper<Q>> for KeyRef<K>
+where K: Borrow<Q>, Q: ?Sized,
+{
+    fn borrow(&self) -> &KeyWrapper<Q> {
+        KeyWrapper::from_ref(unsafe { &*self.0 }.borrow())
+    }
+}
+
+/**
+A cache that holds at most `capacity` entries. The entries are linked from the most recently
+used at `head` to the least recently used at `tail`, `put` on a full cache evicts the tail and
+hands it to the eviction listener. Removing an entry with `remove` or `pop_lru` is not an eviction.
+*/
+pub struct LruCache<K, V> {
+    map: HashMap<KeyRef<K>, *mut Node<K, V>>,
+    head: *mut Node<K, V>,
+    tail: *mut Node<K, V>,
+    size: usize,
+    capacity: usize,
+    listener: Option<Box<dyn FnMut(K, V) + Send>>,
+    _marker: PhantomData<(K, V)>,
+}
+
+// the cache owns its nodes, the map only points into them
+unsafe impl<K: Send, V: Send> Send for LruCache<K, V> {}
+
+impl<K, V> Drop for LruCache<K, V> {
+    fn drop(&mut self) {
+        self.free_nodes();
+    }
+}
+
+impl<K, V> LruCache<K, V> {
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// the entry that the next eviction removes
+    pub fn peek_lru(&self) -> Option<(&K, &V)> {
+        let node = unsafe { self.tail.as_ref()? };
+        Some((&node.key, &node.value))
+    }
+
+    /// iterate from the most to the least recently used entry, the order is not changed
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { cur: self.head, remaining: self.len(), _marker: PhantomData }
+    }
+
+    /// drop every entry without calling the eviction listener
+    pub fn clear(&mut self) {
+        self.map.drain().for_each(drop);
+        self.free_nodes();
+    }
+
+    fn free_nodes(&mut self) {
+        let mut cur = self.head;
+        while !cur.is_null() {
+            cur = unsafe { Box::from_raw(cur).next };
+        }
+        self.head = ptr::null_mut();
+        self.tail = ptr::null_mut();
+        self.size = 0;
+    }
+
+    fn detach(&mut self, node: *mut Node<K, V>) {
+        unsafe {
+            let (prev, next) = ((*node).prev, (*node).next);
+            if prev.is_null() {
+                self.head = next;
+            } else {
+                (*prev).next = next;
+            }
+            if next.is_null() {
+                self.tail = prev;
+            } else {
+                (*next).prev = prev;
+            }
+        }
+    }
+
+    fn attach_front(&mut self, node: *mut Node<K, V>) {
+        let h = self.head;
+        unsafe {
+            (*node).prev = ptr::null_mut();
+            (*node).next = h;
+            if h.is_null() {
+                self.tail = node;
+            } else {
+                (*h).prev = node;
+            }
+        }
+        self.head = node;
+    }
+}
+
+impl<K, V> LruCache<K, V>
+where K: Hash + Eq,
+{
+    /// panics if `capacity` is 0
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be greater than 0");
+        LruCache {
+            map: HashMap::with_capacity(capacity),
+            head: ptr::null_mut(),
+            tail: ptr::null_mut(),
+            size: 0,
+            capacity: capacity,
+            listener: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// `listener` receives every entry evicted by `put` or `resize`
+    pub fn with_listener<F>(capacity: usize, listener: F) -> Self
+    where F: FnMut(K, V) + Send + 'static
+    {
+        let mut cache = Self::new(capacity);
+        cache.listener = Some(Box::new(listener));
+        cache
+    }
+
+    /// look up a value and mark it as the most recently used
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        let node = self.touch(key)?;
+        Some(unsafe { &(*node).value })
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        let node = self.touch(key)?;
+        Some(unsafe { &mut (*node).value })
+    }
+
+    /// look up a value without changing the order
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        let node = *self.map.get(KeyWrapper::from_ref(key))?;
+        Some(unsafe { &(*node).value })
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        self.map.contains_key(KeyWrapper::from_ref(key))
+    }
+
+    /// insert or replace an entry and mark it as the most recently used,
+    /// a new key on a full cache evicts the least recently used entry first
+    pub fn put(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(node) = self.touch(&key) {
+            return Some(mem::replace(unsafe { &mut (*node).value }, value));
+        }
+        if self.len() >= self.capacity {
+            self.evict();
+        }
+        let node = Box::into_raw(Box::new(Node { key, value, prev: ptr::null_mut(), next: ptr::null_mut() }));
+        self.attach_front(node);
+        self.map.put(KeyRef(unsafe { &(*node).key }), node);
+        self.size += 1;
+        None
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        let node = self.map.remove(KeyWrapper::from_ref(key))?;
+        self.detach(node);
+        self.size -= 1;
+        Some(unsafe { Box::from_raw(node) }.value)
+    }
+
+    /// remove the least recently used entry, the eviction listener is not called
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let node = self.tail;
+        if node.is_null() {
+            return None;
+        }
+        self.map.remove(KeyWrapper::from_ref(unsafe { &(*node).key }));
+        self.detach(node);
+        self.size -= 1;
+        let node = unsafe { Box::from_raw(node) };
+        Some((node.key, node.value))
+    }
+
+    /// change the capacity, a smaller capacity evicts the least recently used entries
+    pub fn resize(&mut self, capacity: usize) {
+        assert!(capacity > 0, "capacity must be greater than 0");
+        while self.len() > capacity {
+            self.evict();
+        }
+        self.capacity = capacity;
+        self.map.shrink_to_fit();
+    }
+
+    fn touch<Q>(&mut self, key: &Q) -> Option<*mut Node<K, V>>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        let node = *self.map.get(KeyWrapper::from_ref(key))?;
+        if node != self.head {
+            self.detach(node);
+            self.attach_front(node);
+        }
+        Some(node)
+    }
+
+    fn evict(&mut self) {
+        if let Some((key, value)) = self.pop_lru()
+            && let Some(listener) = self.listener.as_mut() {
+            listener(key, value);
+        }
+    }
+}
+
+impl<K, V> Debug for LruCache<K, V>
+where K: Debug, V: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a LruCache<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    cur: *mut Node<K, V>,
+    remaining: usize,
+    _marker: PhantomData<&'a Node<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = unsafe { self.cur.as_ref()? };
+        self.cur = node.next;
+        self.remaining -= 1;
+        Some((&node.key, &node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
@@ -0,0 +1,3 @@
+mod lru_cache;
+
+pub use lru_cache::LruCache;
@@ -6,9 +6,9 @@ use crate::collection::map::{Entry, TreeHashMap as HashMap};
 use std::hash::Hash;
 
 pub struct ImmutableGraph<N> {
-    nodeMap: HashMap<N, NodeIndex<N>>,
-    edgeMap: HashMap<NodeIndex<N>, LinkedList<NodeIndex<N>>>,
-    rootMap: HashMap<NodeIndex<N>, bool>
+    node_map: HashMap<N, NodeIndex<N>>,
+    edge_map: HashMap<NodeIndex<N>, LinkedList<NodeIndex<N>>>,
+    root_map: HashMap<NodeIndex<N>, bool>
 }
 
 #[derive(PartialEq, Eq, Hash)]
@@ -19,10 +19,7 @@ struct NodeIndex<N> {
 
 impl<N> Clone for NodeIndex<N> {
     fn clone(&self) -> Self {
-        NodeIndex {
-            index: self.index,
-            ptr: self.ptr,
-        }
+        *self
     }
 }
 
@@ -31,9 +28,9 @@ impl<N> Copy for NodeIndex<N> {}
 
 pub struct Builder<N> {
     index: u32,
-    nodeMap: HashMap<N, NodeIndex<N>>,
-    edgeMap: HashMap<NodeIndex<N>, LinkedList<NodeIndex<N>>>,
-    rootMap: HashMap<NodeIndex<N>, bool>
+    node_map: HashMap<N, NodeIndex<N>>,
+    edge_map: HashMap<NodeIndex<N>, LinkedList<NodeIndex<N>>>,
+    root_map: HashMap<NodeIndex<N>, bool>
 }
 
 impl <N> Builder<N>
@@ -42,34 +39,34 @@ where
 {
     fn new() -> Self {
         Builder { index: 0,
-            nodeMap: HashMap::new(),
-            edgeMap: HashMap::new(),
-            rootMap: HashMap::new()
+            node_map: HashMap::new(),
+            edge_map: HashMap::new(),
+            root_map: HashMap::new()
         }
     }
 
     fn add_node(&mut self, value: N) -> NodeIndex<N>
     {
-        match self.nodeMap.entry(value) {
-            Entry::Occupied(x) => { *x.value() }
+        match self.node_map.entry(value) {
+            Entry::Occupied(x) => { *x.get() }
             Entry::Vacant(entry) => {
                 let rs = entry.put_if_absent(|_| {
                     NodeIndex { index: self.index, ptr: std::ptr::null() }
                 });
-                let k_ref = rs.key();
-                let v_ref = rs.value();
+                let k_ref: *const N = rs.key();
+                let v_ref = rs.into_mut();
                 v_ref.ptr = k_ref;
                 self.index += 1;
 
-                // self.edgeMap.put(new_index, LinkedList::new());
-                self.rootMap.put(v_ref.clone(), true);
-                return v_ref.clone()
+                // self.edge_map.put(new_index, LinkedList::new());
+                self.root_map.put(*v_ref, true);
+                return *v_ref
             }
         }
     }
 
     fn add_edge(&mut self, src: NodeIndex<N>, dst: NodeIndex<N>) -> &mut Builder<N> {
-        let map = &mut self.edgeMap;
+        let map = &mut self.edge_map;
         let rs = map.get_mut(&src);
         if let Some(x) = rs {
             x.add(dst)
@@ -78,20 +75,20 @@ where
             list.add(dst);
             map.put(src, list);
         }
-        self.rootMap.remove(&dst);
+        self.root_map.remove(&dst);
         return self;
     }
 
     fn build(self) -> ImmutableGraph<N> {
-        // let mut node_list: Vec<*const N> = Vec::<*const N>::with_capacity(self.nodeMap.len() as usize);
-        // unsafe { node_list.set_len(self.nodeMap.len() as usize) };
-        // for (k,v) in &self.nodeMap {
+        // let mut node_list: Vec<*const N> = Vec::<*const N>::with_capacity(self.node_map.len() as usize);
+        // unsafe { node_list.set_len(self.node_map.len() as usize) };
+        // for (k,v) in &self.node_map {
         //     node_list[v.index as usize] = k as *const N;
         // }
         ImmutableGraph {
-            nodeMap: self.nodeMap,
-            edgeMap: self.edgeMap,
-            rootMap: self.rootMap
+            node_map: self.node_map,
+            edge_map: self.edge_map,
+            root_map: self.root_map
         }
     }
 }
@@ -104,11 +101,65 @@ where N: Eq + Hash,
     }
 
     pub fn contains_node(&self, node: &N) -> bool {
-        return self.nodeMap.contains_key(node);
+        return self.node_map.contains_key(node);
     }
 
     pub fn size(&self) -> usize {
-        self.nodeMap.size()
+        self.node_map.size()
+    }
+}
+
+/// the serialized form, the nodes in the order they were added and the edges as pairs of node positions
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GraphData<N> {
+    nodes: Vec<N>,
+    edges: Vec<(u32, u32)>,
+}
+
+#[cfg(feature = "serde")]
+impl<N> serde::Serialize for ImmutableGraph<N>
+where N: serde::Serialize + Eq + Hash,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut nodes: Vec<Option<&N>> = (0..self.node_map.size()).map(|_| None).collect();
+        for (node, index) in self.node_map.iter() {
+            nodes[index.index as usize] = Some(node);
+        }
+        let mut edges: Vec<(u32, u32)> = self.edge_map.iter()
+            .flat_map(|(src, list)| list.iter().map(|dst| (src.index, dst.index)))
+            .collect();
+        // the edge map has no order of its own
+        edges.sort_by_key(|edge| edge.0);
+        let data = GraphData { nodes: nodes.into_iter().flatten().collect(), edges: edges };
+        data.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, N> serde::Deserialize<'de> for ImmutableGraph<N>
+where N: serde::Deserialize<'de> + Eq + Hash,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let data = GraphData::<N>::deserialize(deserializer)?;
+        let mut builder = Builder::new();
+        let mut indexes = Vec::with_capacity(data.nodes.len());
+        for node in data.nodes {
+            let index = builder.add_node(node);
+            if index.index as usize != indexes.len() {
+                return Err(D::Error::custom(format!("duplicate graph node at position {}", indexes.len())));
+            }
+            indexes.push(index);
+        }
+        for (src, dst) in data.edges {
+            match (indexes.get(src as usize), indexes.get(dst as usize)) {
+                (Some(src), Some(dst)) => builder.add_edge(*src, *dst),
+                _ => return Err(D::Error::custom(format!("graph edge ({src}, {dst}) refers to a missing node"))),
+            };
+        }
+        return Ok(builder.build());
     }
 }
 
@@ -130,6 +181,6 @@ fn test1() {
 
     let graph = builder.build();
     assert_eq!(graph.size(), 4);
-    assert_eq!(graph.contains_node(&"a"), true);
-    assert_eq!(graph.contains_node(&"aaa"), false);
+    assert!(graph.contains_node(&"a"));
+    assert!(!graph.contains_node(&"aaa"));
 }
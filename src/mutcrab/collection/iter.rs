@@ -0,0 +1,138 @@
+/*!
+Iterator adapters shared by the maps, they project the `(key, value)` items of a map iterator
+*/
+
+pub struct Keys<I>(pub(crate) I);
+
+pub struct Values<I>(pub(crate) I);
+
+pub struct ValuesMut<I>(pub(crate) I);
+
+pub struct IntoKeys<I>(pub(crate) I);
+
+pub struct IntoValues<I>(pub(crate) I);
+
+impl<'a, K: 'a, V: 'a, I> Iterator for Keys<I>
+where I: Iterator<Item = (&'a K, &'a V)>
+{
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, K: 'a, V: 'a, I> DoubleEndedIterator for Keys<I>
+where I: DoubleEndedIterator<Item = (&'a K, &'a V)>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<'a, K: 'a, V: 'a, I> ExactSizeIterator for Keys<I>
+where I: ExactSizeIterator<Item = (&'a K, &'a V)> {}
+
+impl<'a, K: 'a, V: 'a, I> Iterator for Values<I>
+where I: Iterator<Item = (&'a K, &'a V)>
+{
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, K: 'a, V: 'a, I> DoubleEndedIterator for Values<I>
+where I: DoubleEndedIterator<Item = (&'a K, &'a V)>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<'a, K: 'a, V: 'a, I> ExactSizeIterator for Values<I>
+where I: ExactSizeIterator<Item = (&'a K, &'a V)> {}
+
+impl<'a, K: 'a, V: 'a, I> Iterator for ValuesMut<I>
+where I: Iterator<Item = (&'a K, &'a mut V)>
+{
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, K: 'a, V: 'a, I> DoubleEndedIterator for ValuesMut<I>
+where I: DoubleEndedIterator<Item = (&'a K, &'a mut V)>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<'a, K: 'a, V: 'a, I> ExactSizeIterator for ValuesMut<I>
+where I: ExactSizeIterator<Item = (&'a K, &'a mut V)> {}
+
+impl<K, V, I> Iterator for IntoKeys<I>
+where I: Iterator<Item = (K, V)>
+{
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V, I> DoubleEndedIterator for IntoKeys<I>
+where I: DoubleEndedIterator<Item = (K, V)>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<K, V, I> ExactSizeIterator for IntoKeys<I>
+where I: ExactSizeIterator<Item = (K, V)> {}
+
+impl<K, V, I> Iterator for IntoValues<I>
+where I: Iterator<Item = (K, V)>
+{
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V, I> DoubleEndedIterator for IntoValues<I>
+where I: DoubleEndedIterator<Item = (K, V)>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<K, V, I> ExactSizeIterator for IntoValues<I>
+where I: ExactSizeIterator<Item = (K, V)> {}
@@ -56,7 +56,7 @@ impl<T> LinkedBlockingQueue<T> {
             let _guard = self.put_lock.lock().unwrap();
             let queue = &self.queue;
             // 检查队列是否满，若满则等待
-            while self.len() == self.capacity {
+            if self.len() == self.capacity {
                 return Some(data);           // 队列满了
             }
             queue.push(data); // 执行队列操作，推送数据
@@ -97,7 +97,7 @@ impl<T> LinkedBlockingQueue<T> {
         let value;
         {
             let mut guard = self.take_lock.lock().unwrap();
-            while self.len() == 0 {
+            while self.is_empty() {
                 guard = self.not_empty.wait(guard).unwrap();
             }
             value = self.queue.pop().unwrap();
@@ -123,7 +123,7 @@ impl<T> LinkedBlockingQueue<T> {
     }
 
     pub fn poll(&self) -> Option<T> {
-        if self.len() == 0 {
+        if self.is_empty() {
             return None;
         }
         let value:Option<T>;
@@ -131,10 +131,7 @@ impl<T> LinkedBlockingQueue<T> {
         {
             let _guard = self.take_lock.lock().unwrap();
             let queue = &self.queue;
-            value = queue.pop();
-            if value.is_none() {
-                return None;
-            }
+            value = Some(queue.pop()?);
             c = self.count.fetch_sub(1, Ordering::AcqRel);
             if c > 1 {
                 self.not_empty.notify_one();
@@ -152,7 +149,7 @@ impl<T> LinkedBlockingQueue<T> {
         {
             let mut guard = self.take_lock.lock().unwrap();
             let mut remaining = timeout;
-            while self.len() == 0 {
+            while self.is_empty() {
                 let begin = Instant::now();
                 (guard, _) = self.not_empty.wait_timeout(guard, timeout).unwrap();
                 let elapsed = begin.elapsed();
@@ -180,12 +177,52 @@ impl<T> LinkedBlockingQueue<T> {
     pub fn len(&self) -> u32 {
         self.count.load(Ordering::Acquire)
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// a snapshot of the elements from head to tail, both locks keep producers and consumers out meanwhile
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for LinkedBlockingQueue<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let _put_guard = self.put_lock.lock().unwrap();
+        let _take_guard = self.take_lock.lock().unwrap();
+        serializer.collect_seq(self.iter())
+    }
+}
+
+/// the elements go into an unbounded queue, the capacity is not part of the snapshot
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for LinkedBlockingQueue<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct QueueVisitor<T>(PhantomData<fn() -> T>);
+
+        impl<'de, T: serde::Deserialize<'de>> serde::de::Visitor<'de> for QueueVisitor<T> {
+            type Value = LinkedBlockingQueue<T>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("a sequence")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut access: A) -> Result<LinkedBlockingQueue<T>, A::Error> {
+                let queue = LinkedBlockingQueue::new();
+                while let Some(value) = access.next_element()? {
+                    queue.push(value);
+                }
+                return Ok(queue);
+            }
+        }
+
+        deserializer.deserialize_seq(QueueVisitor(PhantomData))
+    }
 }
 
 // iterator
 impl<'a, T> IntoIterator for &'a LinkedBlockingQueue<T>
 {
-    type Item = &'a mut T;
+    type Item = &'a T;
     type IntoIter = Iter<'a, T>;
 
     fn into_iter(self) -> Iter<'a, T> {
@@ -14,6 +14,10 @@ pub struct LinkedList<T> {
     _marker: PhantomData<T>,
 }
 
+// the list owns its nodes and hands out `&mut T` only through `&mut self`
+unsafe impl<T: Send> Send for LinkedList<T> {}
+unsafe impl<T: Sync> Sync for LinkedList<T> {}
+
 impl <T> Drop for LinkedList<T> {
     fn drop(&mut self) {
         self.clear();
@@ -200,6 +204,38 @@ impl<'a, T> IntoIterator for &'a LinkedList<T>
     }
 }
 
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for LinkedList<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for LinkedList<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ListVisitor<T>(PhantomData<fn() -> T>);
+
+        impl<'de, T: serde::Deserialize<'de>> serde::de::Visitor<'de> for ListVisitor<T> {
+            type Value = LinkedList<T>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("a sequence")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut access: A) -> Result<LinkedList<T>, A::Error> {
+                let mut list = LinkedList::new();
+                while let Some(value) = access.next_element()? {
+                    list.add_last(value);
+                }
+                return Ok(list);
+            }
+        }
+
+        deserializer.deserialize_seq(ListVisitor(PhantomData))
+    }
+}
+
 pub struct Iter<'a, T> {
     cur: *mut Node<T>,
     _marker: &'a PhantomData<T>,
@@ -211,13 +247,13 @@ impl<'a, T> Iterator for Iter<'a, T>
 
     fn next(&mut self) -> Option<Self::Item> {
         let node = self.cur;
-        while !node.is_null() {
-            // Safely access the current node and update the pointer to the next node
-            let node_ref = unsafe { &*node };
-            self.cur = node_ref.next;
-            return Some(&node_ref.value);
+        if node.is_null() {
+            return None;
         }
-        None
+        // Safely access the current node and update the pointer to the next node
+        let node_ref = unsafe { &*node };
+        self.cur = node_ref.next;
+        Some(&node_ref.value)
     }
 }
 
@@ -67,7 +67,7 @@ impl<T> LockFreeQueue<T> {
         Some(value)
     }
 
-    pub fn iter(&self) -> Iter<T> {
+    pub fn iter(&self) -> Iter<'_, T> {
         let head = self.head.load(Ordering::Acquire);
         let next = unsafe { (*head).next.load(Ordering::Acquire) };
         Iter {
@@ -86,7 +86,7 @@ impl<T> Drop for LockFreeQueue<T> {
 
 impl<'a, T> IntoIterator for &'a LockFreeQueue<T>
 {
-    type Item = &'a mut T;
+    type Item = &'a T;
     type IntoIter = Iter<'a, T>;
 
     fn into_iter(self) -> Iter<'a, T> {
@@ -106,19 +106,19 @@ pub struct Iter<'a, T> {
 
 impl<'a, T> Iterator for Iter<'a, T>
 {
-    type Item = &'a mut T;
+    type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
         let node = self.cur;
-        while !node.is_null() {
-            // Safely access the current node and update the pointer to the next node
+        if node.is_null() {
+            return None;
+        }
+        // Safely access the current node and update the pointer to the next node
         unsafe {
             self.cur = (*node).next.load(Ordering::Acquire);
-            return Some((*node).value.assume_init_mut());
+            Some((*node).value.assume_init_ref())
         }
     }
-        None
-    }
 }
 
 #[cfg(test)]
@@ -230,7 +230,7 @@ mod tests {
     this is UB
     add #[test] verify
     */
-    #[warn(dead_code)]
+    #[allow(dead_code)]
     fn mut_readwrite_test() {
         let queue0 = Arc::new(LockFreeQueue::<i32>::new());
         let queue = Arc::clone(&queue0);
@@ -302,7 +302,7 @@ mod tests {
         let v2 = c2.join().unwrap();
 
         // 验证所有数据是否被正确消费
-        let mut all_data = vec![v1, v2].concat();
+        let mut all_data = [v1, v2].concat();
         all_data.sort();
         assert_eq!(all_data, (0..3000).collect::<Vec<_>>());
     }
@@ -1,28 +1,284 @@
+use crate::collection::map::map::{try_filled_vec, TryReserveError};
 use std::marker::PhantomData;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ptr::{self, NonNull};
 
+/**
+Hands out the memory of the nodes of a map. Every node the map creates goes through `alloc` and
+every node it removes goes back through `free` of the same allocator, so `size` is the number
+of entries. The map frees all its nodes before the allocator is dropped.
+
+# Safety
+`alloc` must return a pointer to the moved in `obj` that stays valid and in place until it is
+passed to `free`, and `size` must count the nodes that were allocated and not freed yet.
+*/
+pub unsafe trait NodeAllocator<T> {
+    fn alloc(&mut self, obj: T) -> NonNull<T>;
+
+    /// # Safety
+    /// `ptr` must come from `alloc` of this allocator and must not be freed already.
+    unsafe fn free(&mut self, ptr: NonNull<T>) -> T;
+
+    fn size(&self) -> usize;
+
+    /// make sure the next `additional` calls to `alloc` find memory, the default allocates on demand
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        let _ = additional;
+        Ok(())
+    }
+
+    /// give back the memory no node uses, the default keeps none
+    fn shrink_to_fit(&mut self) {}
+}
+
+/// one `Box` per node from the global allocator
 #[derive(Debug)]
-pub struct Allocator<T> {
-    pub size: usize,
-    _marker: PhantomData<T>,
+pub struct BoxAllocator<T> {
+    size: usize,
+    // only counts the nodes, it never holds a `T`
+    _marker: PhantomData<fn() -> T>,
 }
 
-impl<T> Allocator<T> {
+impl<T> BoxAllocator<T> {
     pub fn new() -> Self {
-        Allocator {
-            size: 0,
+        Self::with_size(0)
+    }
+
+    /// an allocator that already counts `size` nodes, they were moved in from another one
+    pub(crate) fn with_size(size: usize) -> Self {
+        BoxAllocator {
+            size: size,
             _marker: PhantomData,
         }
     }
+}
+
+impl<T> Default for BoxAllocator<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<T> NodeAllocator<T> for BoxAllocator<T> {
+    fn alloc(&mut self, obj: T) -> NonNull<T> {
+        self.size += 1;
+        NonNull::from(Box::leak(Box::new(obj)))
+    }
+
+    unsafe fn free(&mut self, ptr: NonNull<T>) -> T {
+        self.size -= 1;
+        *unsafe { Box::from_raw(ptr.as_ptr()) }
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+}
+
+const MIN_CHUNK_LEN: usize = 16;
+const MAX_CHUNK_LEN: usize = 1024;
+
+/// a slot holds a node while it is allocated and the next free slot once it is freed
+#[repr(C)]
+union SlabSlot<T> {
+    value: ManuallyDrop<T>,
+    next: *mut SlabSlot<T>,
+}
+
+/**
+Hands out the nodes from chunks of slots and keeps the freed slots in a free list, so a map that
+inserts and removes all the time reuses the same memory instead of calling the global allocator
+for every node. The chunks double in length up to `MAX_CHUNK_LEN` slots, a chunk whose slots are
+all free is given back by `shrink_to_fit`.
+
+A chunk is only kept as the raw pointer it got when it was allocated, every slot pointer is
+derived from it, so handing out a slot never reborrows a chunk that earlier nodes point into.
+*/
+pub struct SlabAllocator<T> {
+    chunks: Vec<NonNull<[MaybeUninit<SlabSlot<T>>]>>,
+    free: *mut SlabSlot<T>,
+    // slots at the end of the last chunk that were never handed out
+    unused: usize,
+    slots: usize,
+    size: usize,
+}
+
+// the allocator owns the nodes it hands out
+unsafe impl<T: Send> Send for SlabAllocator<T> {}
+unsafe impl<T: Sync> Sync for SlabAllocator<T> {}
+
+impl<T> SlabAllocator<T> {
+    pub fn new() -> Self {
+        SlabAllocator {
+            chunks: Vec::new(),
+            free: ptr::null_mut(),
+            unused: 0,
+            slots: 0,
+            size: 0,
+        }
+    }
+
+    /// number of slots in all chunks, the allocated and the free ones
+    pub fn slots(&self) -> usize {
+        self.slots
+    }
+
+    pub fn chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// the index into `chunks` of the chunk that holds `slot`, `order` lists the chunks by address
+    fn chunk_of(&self, order: &[usize], slot: *mut SlabSlot<T>) -> usize {
+        let start = |i: usize| self.chunks[i].cast::<SlabSlot<T>>().as_ptr() as usize;
+        let pos = order.partition_point(|&i| start(i) <= slot as usize);
+        order[pos - 1]
+    }
+
+    /// drop the chunks in which no slot is allocated, the free slots of the other chunks stay
+    fn release_free_chunks(&mut self) {
+        if self.size == 0 {
+            for chunk in self.chunks.drain(..) {
+                drop(unsafe { Box::from_raw(chunk.as_ptr()) });
+            }
+            self.free = ptr::null_mut();
+            self.unused = 0;
+            self.slots = 0;
+            return;
+        }
+        let mut order: Vec<usize> = (0..self.chunks.len()).collect();
+        order.sort_unstable_by_key(|&i| self.chunks[i].cast::<SlabSlot<T>>().as_ptr() as usize);
+        // the unused slots are the end of the last chunk
+        let mut free = vec![0; self.chunks.len()];
+        *free.last_mut().unwrap() = self.unused;
+        let mut slot = self.free;
+        while !slot.is_null() {
+            free[self.chunk_of(&order, slot)] += 1;
+            slot = unsafe { (*slot).next };
+        }
+        let released: Vec<bool> = self.chunks.iter().zip(&free).map(|(chunk, n)| chunk.len() == *n).collect();
+        if !released.contains(&true) {
+            return;
+        }
+        // keep the free slots of the chunks that stay
+        let mut slot = self.free;
+        self.free = ptr::null_mut();
+        while !slot.is_null() {
+            let next = unsafe { (*slot).next };
+            if !released[self.chunk_of(&order, slot)] {
+                unsafe { ptr::addr_of_mut!((*slot).next).write(self.free) };
+                self.free = slot;
+            }
+            slot = next;
+        }
+        if *released.last().unwrap() {
+            self.unused = 0;
+        }
+        let mut index = 0;
+        self.chunks.retain(|chunk| {
+            let keep = !released[index];
+            index += 1;
+            if !keep {
+                self.slots -= chunk.len();
+                drop(unsafe { Box::from_raw(chunk.as_ptr()) });
+            }
+            keep
+        });
+    }
+
+    fn next_slot(&mut self) -> *mut SlabSlot<T> {
+        if !self.free.is_null() {
+            let slot = self.free;
+            self.free = unsafe { (*slot).next };
+            return slot;
+        }
+        if self.unused == 0 {
+            self.grow(0).unwrap_or_else(|e| e.raise());
+        }
+        self.take_unused()
+    }
+
+    fn take_unused(&mut self) -> *mut SlabSlot<T> {
+        let chunk = *self.chunks.last().unwrap();
+        let index = chunk.len() - self.unused;
+        self.unused -= 1;
+        unsafe { chunk.cast::<SlabSlot<T>>().as_ptr().add(index) }
+    }
+
+    /// add a chunk of at least `min_len` slots, the unused slots of the last chunk go to the free list
+    fn grow(&mut self, min_len: usize) -> Result<(), TryReserveError> {
+        let len = self.chunks.last().map_or(MIN_CHUNK_LEN, |chunk| (chunk.len() * 2).min(MAX_CHUNK_LEN)).max(min_len);
+        let chunk = try_filled_vec(len, MaybeUninit::uninit)?;
+        self.chunks.try_reserve(1).map_err(|_| TryReserveError::for_array::<Box<[SlabSlot<T>]>>(self.chunks.len() + 1))?;
+        while self.unused > 0 {
+            let slot = self.take_unused();
+            unsafe { ptr::addr_of_mut!((*slot).next).write(self.free) };
+            self.free = slot;
+        }
+        self.chunks.push(NonNull::from(Box::leak(chunk.into_boxed_slice())));
+        self.unused = len;
+        self.slots += len;
+        return Ok(());
+    }
+}
 
-    pub fn alloc(&mut self, obj: T) -> Box<T> {
+impl<T> Default for SlabAllocator<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for SlabAllocator<T> {
+    fn drop(&mut self) {
+        for chunk in self.chunks.drain(..) {
+            drop(unsafe { Box::from_raw(chunk.as_ptr()) });
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for SlabAllocator<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SlabAllocator").field("size", &self.size).field("slots", &self.slots).finish()
+    }
+}
+
+unsafe impl<T> NodeAllocator<T> for SlabAllocator<T> {
+    fn alloc(&mut self, obj: T) -> NonNull<T> {
+        let slot = self.next_slot();
         self.size += 1;
-        Box::new(obj)
+        // the union is `repr(C)`, so the value starts at the slot
+        let ptr = slot as *mut T;
+        unsafe {
+            ptr.write(obj);
+            NonNull::new_unchecked(ptr)
+        }
     }
 
-    pub fn free(&mut self, obj: Box<T>) -> T {
+    unsafe fn free(&mut self, ptr: NonNull<T>) -> T {
         self.size -= 1;
-        let old = *obj;
-        // drop(obj);
-        return old;
+        let slot = ptr.as_ptr() as *mut SlabSlot<T>;
+        unsafe {
+            let obj = ptr.as_ptr().read();
+            ptr::addr_of_mut!((*slot).next).write(self.free);
+            self.free = slot;
+            obj
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    /// a new chunk is added if the free and unused slots are not enough
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        let available = self.slots - self.size;
+        if additional > available {
+            self.grow(additional - available)?;
+        }
+        return Ok(());
+    }
+
+    /// drop every chunk that has no allocated slot
+    fn shrink_to_fit(&mut self) {
+        self.release_free_chunks()
     }
 }
@@ -0,0 +1,292 @@
+use crate::collection::iter::{IntoKeys, IntoValues, Keys, Values, ValuesMut};
+use crate::collection::map::map::{make_hash, make_hasher, TryReserveError};
+use crate::collection::map::raw_flat_hashmap::{Drain, ExtractIf, FlatTable, IntoIter, Iter, IterMut};
+use crate::collection::map::{Entry, Map};
+use std::borrow::Borrow;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hash, RandomState};
+use std::ops::Index;
+
+/**
+A hash map that stores its entries inline in one open addressing table instead of a node per entry.
+It keeps 7/8 of the slots in use at most, so there is no load factor to choose.
+*/
+pub struct HashMap<K, V, S = RandomState>(FlatTable<K, V>, S);
+
+impl<K, V> HashMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self(FlatTable::new(), Default::default())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_hasher(capacity, Default::default())
+    }
+
+    pub fn of(key: K, value: V) -> HashMap<K, V>
+    where
+        K: Hash + Eq,
+    {
+        let mut map = Self::with_capacity(1);
+        map.put(key, value);
+        map
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S> {
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        self.0.iter_mut()
+    }
+
+    pub fn keys(&self) -> Keys<Iter<'_, K, V>> {
+        Keys(self.0.iter())
+    }
+
+    pub fn values(&self) -> Values<Iter<'_, K, V>> {
+        Values(self.0.iter())
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<IterMut<'_, K, V>> {
+        ValuesMut(self.0.iter_mut())
+    }
+
+    pub fn into_keys(self) -> IntoKeys<IntoIter<K, V>> {
+        IntoKeys(self.0.into_iter())
+    }
+
+    pub fn into_values(self) -> IntoValues<IntoIter<K, V>> {
+        IntoValues(self.0.into_iter())
+    }
+
+    /// remove every entry, the slots keep their capacity
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        self.0.drain()
+    }
+
+    /// keep only the entries for which `f` returns true, the others are removed in place
+    pub fn retain<F>(&mut self, f: F)
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        self.0.retain(f)
+    }
+
+    /// lazily remove and yield the entries for which `pred` returns true
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, F>
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        self.0.extract_if(pred)
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    S: BuildHasher,
+{
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self(FlatTable::new(), hash_builder)
+    }
+
+    pub fn with_capacity_hasher(capacity: usize, hash_builder: S) -> Self {
+        Self(FlatTable::with_capacity(capacity), hash_builder)
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S>
+where K: Hash + Eq, S: BuildHasher,
+{
+    /// both `Map` and `std::iter::Extend` provide `extend`, this keeps `map.extend(..)` unambiguous
+    pub fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        Map::extend(self, iter)
+    }
+}
+
+impl<K, V, S> Map<K, V> for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    type Raw = FlatTable<K, V>;
+
+    #[inline]
+    fn size(&self) -> usize {
+        self.0.size()
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.size() == 0
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        let hash = make_hash(&self.1, key);
+        self.0.get(hash, key)
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        let hash = make_hash(&self.1, key);
+        self.0.get_mut(hash, key)
+    }
+
+    fn put(&mut self, key: K, value: V) -> Option<V> {
+        let hash = make_hash(&self.1, &key);
+        self.0.put(hash, key, value, make_hasher(&self.1))
+    }
+
+    fn try_put(&mut self, key: K, value: V) -> Result<Option<V>, TryReserveError> {
+        let hash = make_hash(&self.1, &key);
+        self.0.try_put(hash, key, value, make_hasher(&self.1))
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        let hash = make_hash(&self.1, key);
+        self.0.remove(hash, key)
+    }
+
+    fn foreach<F: FnMut(&K, &mut V)>(&mut self, f: F) {
+        self.0.foreach(f)
+    }
+
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional, make_hasher(&self.1))
+    }
+
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.0.try_reserve(additional, make_hasher(&self.1))
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit(make_hasher(&self.1))
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        self.0.retain(f)
+    }
+
+    fn entry(&mut self, key: K) -> Entry<'_, K, V, Self::Raw> {
+        let hash = make_hash(&self.1, &key);
+        self.0.entry(hash, key, make_hasher(&self.1))
+    }
+}
+
+impl<K, V, S> Default for HashMap<K, V, S>
+where S: Default,
+{
+    fn default() -> Self {
+        Self(FlatTable::new(), Default::default())
+    }
+}
+
+impl<K, V, S> Clone for HashMap<K, V, S>
+where K: Clone, V: Clone, S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), self.1.clone())
+    }
+}
+
+impl<K, V, S> Debug for HashMap<K, V, S>
+where K: Debug, V: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, S> PartialEq for HashMap<K, V, S>
+where K: Hash + Eq, V: PartialEq, S: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K, V, S> Eq for HashMap<K, V, S>
+where K: Hash + Eq, V: Eq, S: BuildHasher,
+{}
+
+impl<K, V, S> FromIterator<(K, V)> for HashMap<K, V, S>
+where K: Hash + Eq, S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::with_hasher(Default::default());
+        Map::extend(&mut map, iter);
+        map
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for HashMap<K, V, S>
+where K: Hash + Eq, S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        Map::extend(self, iter)
+    }
+}
+
+impl<K, Q, V, S> Index<&Q> for HashMap<K, V, S>
+where K: Borrow<Q> + Hash + Eq, Q: ?Sized + Hash + Eq, S: BuildHasher,
+{
+    type Output = V;
+
+    /// panics if the key is not in the map
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a HashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.0.iter()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a mut HashMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        self.0.iter_mut()
+    }
+}
+
+impl<K, V, S> IntoIterator for HashMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> IntoIter<K, V> {
+        self.0.into_iter()
+    }
+}
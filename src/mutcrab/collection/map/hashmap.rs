@@ -1,10 +1,19 @@
-use crate::collection::map::list_bucket::ListBucket;
-use crate::collection::map::map::{make_hash, make_hasher};
-use crate::collection::map::raw_hashmap::{HashTable, Iter};
+use crate::collection::iter::{IntoKeys, IntoValues, Keys, Values, ValuesMut};
+use crate::collection::map::map::{make_hash, make_hasher, MapStats, TryReserveError};
+#[cfg(feature = "serde")]
+use crate::collection::map::map::MapVisitor;
+use crate::collection::map::raw_hashmap::{Drain, ExtractIf, HashTable, IntoIter, Iter, IterMut, DEFAULT_INITIAL_CAPACITY, DEFAULT_LOAD_FACTOR};
+use crate::collection::map::allocator::{NodeAllocator, SlabAllocator};
+use crate::collection::map::list_bucket::EntryNode;
 use crate::collection::map::{Entry, Map};
+use std::borrow::Borrow;
+use std::fmt::{self, Debug, Formatter};
 use std::hash::{BuildHasher, Hash, RandomState};
+use std::ops::Index;
 
-pub struct HashMap<K, V, S = RandomState>(HashTable<K, V>, S);
+/// the entry nodes come from `A`, a slab of recycled slots unless another allocator is given
+pub struct HashMap<K, V, S = RandomState, A = SlabAllocator<EntryNode<K, V>>>(HashTable<K, V, A>, S)
+where A: NodeAllocator<EntryNode<K, V>>;
 
 impl<K, V> HashMap<K, V, RandomState> {
     pub fn new() -> Self {
@@ -27,10 +36,57 @@ impl<K, V> HashMap<K, V, RandomState> {
         map.put(key, value);
         map
     }
+}
 
-    pub fn iter(&self) -> Iter<K, V> {
+impl<K, V, S, A> HashMap<K, V, S, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    pub fn iter(&self) -> Iter<'_, K, V> {
         self.0.iter()
     }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        self.0.iter_mut()
+    }
+
+    pub fn keys(&self) -> Keys<Iter<'_, K, V>> {
+        Keys(self.0.iter())
+    }
+
+    pub fn values(&self) -> Values<Iter<'_, K, V>> {
+        Values(self.0.iter())
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<IterMut<'_, K, V>> {
+        ValuesMut(self.0.iter_mut())
+    }
+
+    pub fn into_keys(self) -> IntoKeys<IntoIter<K, V, A>> {
+        IntoKeys(self.0.into_iter())
+    }
+
+    pub fn into_values(self) -> IntoValues<IntoIter<K, V, A>> {
+        IntoValues(self.0.into_iter())
+    }
+
+    /// remove every entry, the bucket table keeps its capacity
+    pub fn drain(&mut self) -> Drain<'_, K, V, A> {
+        self.0.drain()
+    }
+
+    /// keep only the entries for which `f` returns true, the others are unlinked in place
+    pub fn retain<F>(&mut self, f: F)
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        self.0.retain(f)
+    }
+
+    /// lazily remove and yield the entries for which `pred` returns true
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, F, A>
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        self.0.extract_if(pred)
+    }
 }
 
 impl<K, V, S> HashMap<K, V, S>
@@ -53,12 +109,63 @@ where
     }
 }
 
-impl<K, V, S> Map<K, V> for HashMap<K, V, S>
+impl<K, V, S, A> HashMap<K, V, S, A>
+where
+    S: BuildHasher,
+    A: NodeAllocator<EntryNode<K, V>>,
+{
+    pub fn with_hasher_in(hash_builder: S, allocator: A) -> Self {
+        Self::with_capacity_hasher_in(DEFAULT_INITIAL_CAPACITY, hash_builder, allocator)
+    }
+
+    pub fn with_capacity_hasher_in(capacity: usize, hash_builder: S, allocator: A) -> Self {
+        Self(HashTable::with_allocator(capacity, DEFAULT_LOAD_FACTOR, allocator), hash_builder)
+    }
+
+    pub fn allocator(&self) -> &A {
+        self.0.allocator()
+    }
+
+    /// how the entries are spread over the buckets, see `MapStats`
+    pub fn stats(&self) -> MapStats {
+        self.0.stats()
+    }
+}
+
+impl<K, V, S, A> HashMap<K, V, S, A>
+where K: Hash + Eq, S: BuildHasher, A: NodeAllocator<EntryNode<K, V>>,
+{
+    /// both `Map` and `std::iter::Extend` provide `extend`, this keeps `map.extend(..)` unambiguous
+    pub fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        Map::extend(self, iter)
+    }
+
+    /**
+    In incremental mode a growing map keeps its old buckets next to the new ones and moves a few of
+    them on every `put`, `entry` and `remove`, so no single call has to rehash the whole map.
+    Switching it off finishes a running resize.
+    */
+    pub fn set_incremental_rehash(&mut self, incremental: bool) {
+        self.0.set_incremental(incremental, make_hasher(&self.1))
+    }
+
+    pub fn is_incremental_rehash(&self) -> bool {
+        self.0.is_incremental()
+    }
+
+    /// true while an incremental resize is running
+    pub fn is_rehashing(&self) -> bool {
+        self.0.is_rehashing()
+    }
+}
+
+impl<K, V, S, A> Map<K, V> for HashMap<K, V, S, A>
 where
     K: Hash + Eq,
     S: BuildHasher,
+    A: NodeAllocator<EntryNode<K, V>>,
 {
-    type Raw = ListBucket<K, V>;
+    type Raw = HashTable<K, V, A>;
 
     #[inline]
     fn size(&self) -> usize {
@@ -70,7 +177,9 @@ where
         self.size() == 0
     }
 
-    fn get(&self, key: &K) -> Option<&V> {
+    fn get<Q>(&self, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
         if self.is_empty() {
             return None;
         }
@@ -78,7 +187,9 @@ where
         self.0.get(hash, key)
     }
 
-    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
         if self.is_empty() {
             return None;
         }
@@ -91,32 +202,176 @@ where
         self.0.put(hash, key, value, make_hasher(&self.1))
     }
 
-    fn remove(&mut self, key: &K) -> Option<V> {
+    fn try_put(&mut self, key: K, value: V) -> Result<Option<V>, TryReserveError> {
+        let hash = make_hash(&self.1, &key);
+        self.0.try_put(hash, key, value, make_hasher(&self.1))
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
         if self.is_empty() {
             return None;
         }
         let hash = make_hash(&self.1, key);
-        self.0.remove(hash, key)
+        self.0.remove(hash, key, make_hasher(&self.1))
     }
 
     fn foreach<F: FnMut(&K, &mut V)>(&mut self, f: F) {
         self.0.foreach(f)
     }
 
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional, make_hasher(&self.1))
+    }
+
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.0.try_reserve(additional, make_hasher(&self.1))
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit(make_hasher(&self.1))
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        self.0.retain(f)
+    }
+
     fn entry(&mut self, key: K) -> Entry<'_, K, V, Self::Raw> {
         let hash = make_hash(&self.1, &key);
         self.0.entry(hash, key, make_hasher(&self.1))
     }
 }
 
-impl<'a, K, V> IntoIterator for &'a HashMap<K, V>
+impl<K, V, S, A> Default for HashMap<K, V, S, A>
+where S: Default, A: NodeAllocator<EntryNode<K, V>> + Default,
+{
+    fn default() -> Self {
+        Self(HashTable::default(), Default::default())
+    }
+}
+
+impl<K, V, S, A> Clone for HashMap<K, V, S, A>
+where K: Clone, V: Clone, S: Clone, A: NodeAllocator<EntryNode<K, V>> + Default,
+{
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), self.1.clone())
+    }
+}
+
+impl<K, V, S, A> Debug for HashMap<K, V, S, A>
+where K: Debug, V: Debug, A: NodeAllocator<EntryNode<K, V>>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, S, A> PartialEq for HashMap<K, V, S, A>
+where K: Hash + Eq, V: PartialEq, S: BuildHasher, A: NodeAllocator<EntryNode<K, V>>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K, V, S, A> Eq for HashMap<K, V, S, A>
+where K: Hash + Eq, V: Eq, S: BuildHasher, A: NodeAllocator<EntryNode<K, V>>,
+{}
+
+impl<K, V, S, A> FromIterator<(K, V)> for HashMap<K, V, S, A>
+where K: Hash + Eq, S: BuildHasher + Default, A: NodeAllocator<EntryNode<K, V>> + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::with_hasher_in(Default::default(), Default::default());
+        Map::extend(&mut map, iter);
+        map
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, S, A> serde::Serialize for HashMap<K, V, S, A>
+where K: serde::Serialize, V: serde::Serialize, A: NodeAllocator<EntryNode<K, V>>,
+{
+    fn serialize<T: serde::Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V, S, A> serde::Deserialize<'de> for HashMap<K, V, S, A>
 where
-    K: Eq + Hash,
+    K: serde::Deserialize<'de> + Hash + Eq,
+    V: serde::Deserialize<'de>,
+    S: BuildHasher + Default,
+    A: NodeAllocator<EntryNode<K, V>> + Default,
 {
-    type Item = (&'a K, &'a mut V);
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(MapVisitor::new())
+    }
+}
+
+impl<K, V, S, A> Extend<(K, V)> for HashMap<K, V, S, A>
+where K: Hash + Eq, S: BuildHasher, A: NodeAllocator<EntryNode<K, V>>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        Map::extend(self, iter)
+    }
+}
+
+impl<K, Q, V, S, A> Index<&Q> for HashMap<K, V, S, A>
+where K: Borrow<Q> + Hash + Eq, Q: ?Sized + Hash + Eq, S: BuildHasher, A: NodeAllocator<EntryNode<K, V>>,
+{
+    type Output = V;
+
+    /// panics if the key is not in the map
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<'a, K, V, S, A> IntoIterator for &'a HashMap<K, V, S, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    type Item = (&'a K, &'a V);
     type IntoIter = Iter<'a, K, V>;
 
     fn into_iter(self) -> Iter<'a, K, V> {
         self.0.iter()
     }
 }
+
+impl<'a, K, V, S, A> IntoIterator for &'a mut HashMap<K, V, S, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        self.0.iter_mut()
+    }
+}
+
+impl<K, V, S, A> IntoIterator for HashMap<K, V, S, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, A>;
+
+    fn into_iter(self) -> IntoIter<K, V, A> {
+        self.0.into_iter()
+    }
+}
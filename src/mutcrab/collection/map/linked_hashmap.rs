@@ -0,0 +1,370 @@
+use crate::collection::iter::{IntoKeys, IntoValues, Keys, Values, ValuesMut};
+use crate::collection::map::map::{make_hash, TryReserveError};
+use crate::collection::map::allocator::{NodeAllocator, SlabAllocator};
+use crate::collection::map::raw_linked_hashmap::{Drain, IntoIter, Iter, IterMut, LinkedHashTable, LinkedNode};
+use crate::collection::map::{Entry, Map};
+use std::borrow::Borrow;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hash, RandomState};
+use std::ops::Index;
+
+/**
+A hash map that also links its entries from the oldest to the newest, like Java's `LinkedHashMap`.
+By default it iterates in insertion order and putting an existing key keeps its place.
+An access ordered map, see `with_access_order`, moves an entry to the back on
+`get_refresh`, `get_mut`, `put` and `entry`, so `front` is the least recently used entry.
+Only a `&mut` call is an access, the shared `get` leaves the order alone.
+The entry nodes come from `A`, a slab of recycled slots unless another allocator is given.
+*/
+pub struct LinkedHashMap<K, V, S = RandomState, A = SlabAllocator<LinkedNode<K, V>>>(LinkedHashTable<K, V, A>, S)
+where A: NodeAllocator<LinkedNode<K, V>>;
+
+impl<K, V> LinkedHashMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self(LinkedHashTable::new(), Default::default())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_hasher(capacity, Default::default())
+    }
+
+    /// an empty map that iterates from the least to the most recently used entry
+    pub fn with_access_order() -> Self {
+        Self::with_capacity_factor_order(16, 0.75, true)
+    }
+
+    pub fn with_capacity_factor_order(capacity: usize, factor: f32, access_order: bool) -> Self {
+        Self::with_capacity_factor_order_hasher(capacity, factor, access_order, Default::default())
+    }
+}
+
+impl<K, V, S, A> LinkedHashMap<K, V, S, A>
+where A: NodeAllocator<LinkedNode<K, V>>,
+{
+    pub fn is_access_order(&self) -> bool {
+        self.0.is_access_order()
+    }
+
+    /// iterate from the oldest to the newest entry
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        self.0.iter_mut()
+    }
+
+    pub fn keys(&self) -> Keys<Iter<'_, K, V>> {
+        Keys(self.0.iter())
+    }
+
+    pub fn values(&self) -> Values<Iter<'_, K, V>> {
+        Values(self.0.iter())
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<IterMut<'_, K, V>> {
+        ValuesMut(self.0.iter_mut())
+    }
+
+    pub fn into_keys(self) -> IntoKeys<IntoIter<K, V, A>> {
+        IntoKeys(self.0.into_iter())
+    }
+
+    pub fn into_values(self) -> IntoValues<IntoIter<K, V, A>> {
+        IntoValues(self.0.into_iter())
+    }
+
+    /// the oldest entry, in access order the least recently used one
+    pub fn front(&self) -> Option<(&K, &V)> {
+        self.0.front()
+    }
+
+    /// the newest entry, in access order the most recently used one
+    pub fn back(&self) -> Option<(&K, &V)> {
+        self.0.back()
+    }
+
+    pub fn pop_front(&mut self) -> Option<(K, V)> {
+        self.0.pop_front()
+    }
+
+    pub fn pop_back(&mut self) -> Option<(K, V)> {
+        self.0.pop_back()
+    }
+
+    /// remove every entry in order, the bucket table keeps its capacity
+    pub fn drain(&mut self) -> Drain<'_, K, V, A> {
+        self.0.drain()
+    }
+
+    /// keep only the entries for which `f` returns true, the order of the others is kept
+    pub fn retain<F>(&mut self, f: F)
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        self.0.retain(f)
+    }
+}
+
+impl<K, V, S> LinkedHashMap<K, V, S>
+where
+    S: BuildHasher,
+{
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self(LinkedHashTable::new(), hash_builder)
+    }
+
+    pub fn with_capacity_hasher(capacity: usize, hash_builder: S) -> Self {
+        Self(LinkedHashTable::with_capacity(capacity), hash_builder)
+    }
+
+    pub fn with_capacity_factor_order_hasher(capacity: usize, factor: f32, access_order: bool, hash_builder: S) -> Self {
+        Self(
+            LinkedHashTable::with_capacity_factor_order(capacity, factor, access_order),
+            hash_builder
+        )
+    }
+}
+
+impl<K, V, S, A> LinkedHashMap<K, V, S, A>
+where
+    S: BuildHasher,
+    A: NodeAllocator<LinkedNode<K, V>>,
+{
+    pub fn with_hasher_in(hash_builder: S, allocator: A) -> Self {
+        Self::with_capacity_factor_order_hasher_in(16, 0.75, false, hash_builder, allocator)
+    }
+
+    pub fn with_capacity_factor_order_hasher_in(capacity: usize, factor: f32, access_order: bool, hash_builder: S, allocator: A) -> Self {
+        Self(
+            LinkedHashTable::with_allocator(capacity, factor, access_order, allocator),
+            hash_builder
+        )
+    }
+
+    pub fn allocator(&self) -> &A {
+        self.0.allocator()
+    }
+}
+
+impl<K, V, S, A> LinkedHashMap<K, V, S, A>
+where K: Hash + Eq, S: BuildHasher, A: NodeAllocator<LinkedNode<K, V>>,
+{
+    /// both `Map` and `std::iter::Extend` provide `extend`, this keeps `map.extend(..)` unambiguous
+    pub fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        Map::extend(self, iter)
+    }
+
+    /// look up a value, an access ordered map moves the entry to the back like `get_mut` does
+    pub fn get_refresh<Q>(&mut self, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        let hash = make_hash(&self.1, key);
+        self.0.get_refresh(hash, key)
+    }
+}
+
+impl<K, V, S, A> Map<K, V> for LinkedHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: NodeAllocator<LinkedNode<K, V>>,
+{
+    type Raw = LinkedHashTable<K, V, A>;
+
+    #[inline]
+    fn size(&self) -> usize {
+        self.0.size()
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.size() == 0
+    }
+
+    /// not an access, see `get_refresh`
+    fn get<Q>(&self, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        let hash = make_hash(&self.1, key);
+        self.0.get(hash, key)
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        let hash = make_hash(&self.1, key);
+        self.0.get_mut(hash, key)
+    }
+
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        self.get(key).is_some()
+    }
+
+    fn put(&mut self, key: K, value: V) -> Option<V> {
+        let hash = make_hash(&self.1, &key);
+        self.0.put(hash, key, value)
+    }
+
+    fn try_put(&mut self, key: K, value: V) -> Result<Option<V>, TryReserveError> {
+        let hash = make_hash(&self.1, &key);
+        self.0.try_put(hash, key, value)
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        let hash = make_hash(&self.1, key);
+        self.0.remove(hash, key)
+    }
+
+    fn foreach<F: FnMut(&K, &mut V)>(&mut self, f: F) {
+        self.0.foreach(f)
+    }
+
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional)
+    }
+
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.0.try_reserve(additional)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit()
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        self.0.retain(f)
+    }
+
+    fn entry(&mut self, key: K) -> Entry<'_, K, V, Self::Raw> {
+        let hash = make_hash(&self.1, &key);
+        self.0.entry(hash, key)
+    }
+}
+
+impl<K, V, S, A> Default for LinkedHashMap<K, V, S, A>
+where S: Default, A: NodeAllocator<LinkedNode<K, V>> + Default,
+{
+    fn default() -> Self {
+        Self(LinkedHashTable::default(), Default::default())
+    }
+}
+
+impl<K, V, S, A> Clone for LinkedHashMap<K, V, S, A>
+where K: Clone, V: Clone, S: Clone, A: NodeAllocator<LinkedNode<K, V>> + Default,
+{
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), self.1.clone())
+    }
+}
+
+impl<K, V, S, A> Debug for LinkedHashMap<K, V, S, A>
+where K: Debug, V: Debug, A: NodeAllocator<LinkedNode<K, V>>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.0.iter()).finish()
+    }
+}
+
+/// maps are equal when they hold the same entries, the order is not compared
+impl<K, V, S, A> PartialEq for LinkedHashMap<K, V, S, A>
+where K: Hash + Eq, V: PartialEq, S: BuildHasher, A: NodeAllocator<LinkedNode<K, V>>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K, V, S, A> Eq for LinkedHashMap<K, V, S, A>
+where K: Hash + Eq, V: Eq, S: BuildHasher, A: NodeAllocator<LinkedNode<K, V>>,
+{}
+
+impl<K, V, S, A> FromIterator<(K, V)> for LinkedHashMap<K, V, S, A>
+where K: Hash + Eq, S: BuildHasher + Default, A: NodeAllocator<LinkedNode<K, V>> + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::with_hasher_in(Default::default(), Default::default());
+        Map::extend(&mut map, iter);
+        map
+    }
+}
+
+impl<K, V, S, A> Extend<(K, V)> for LinkedHashMap<K, V, S, A>
+where K: Hash + Eq, S: BuildHasher, A: NodeAllocator<LinkedNode<K, V>>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        Map::extend(self, iter)
+    }
+}
+
+impl<K, Q, V, S, A> Index<&Q> for LinkedHashMap<K, V, S, A>
+where K: Borrow<Q> + Hash + Eq, Q: ?Sized + Hash + Eq, S: BuildHasher, A: NodeAllocator<LinkedNode<K, V>>,
+{
+    type Output = V;
+
+    /// panics if the key is not in the map, like `get` it leaves the order alone
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<'a, K, V, S, A> IntoIterator for &'a LinkedHashMap<K, V, S, A>
+where A: NodeAllocator<LinkedNode<K, V>>,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.0.iter()
+    }
+}
+
+impl<'a, K, V, S, A> IntoIterator for &'a mut LinkedHashMap<K, V, S, A>
+where A: NodeAllocator<LinkedNode<K, V>>,
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        self.0.iter_mut()
+    }
+}
+
+impl<K, V, S, A> IntoIterator for LinkedHashMap<K, V, S, A>
+where A: NodeAllocator<LinkedNode<K, V>>,
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, A>;
+
+    fn into_iter(self) -> IntoIter<K, V, A> {
+        self.0.into_iter()
+    }
+}
@@ -1,16 +1,15 @@
-use crate::collection::map::map::{OccupiedEntry, VacantEntry};
-use crate::collection::map::tree_bucket::TreeBucket;
-use crate::collection::map::{Entry, RawTable};
+use crate::collection::map::tree_bucket::{KeyOrder, TreeBucket};
+use std::borrow::Borrow;
 use std::ptr::NonNull;
 use std::{mem, ptr};
 use crate::collection::map::map_bucket::Bucket;
-use crate::collection::map::allocator::Allocator;
+use crate::collection::map::allocator::NodeAllocator;
 
 /**
 The maximum length of the linked list is 8. If the length exceeds this,
 the `convert_to_tree` function will be triggered to convert it to RedBlackTree
 */
-const MAX_LIST_LENGTH: u32 = 8;
+pub(crate) const MAX_LIST_LENGTH: u32 = 8;
 
 #[derive(Debug)]
 pub struct EntryNode<K, V> {
@@ -19,19 +18,17 @@ pub struct EntryNode<K, V> {
     pub(crate) next: *mut EntryNode<K, V>,
 }
 
+/// the nodes belong to the allocator of the table, dropping a bucket does not free them
 #[derive(Debug)]
 pub struct ListBucket<K, V> {
     root: *mut EntryNode<K, V>,
 }
 
-impl<K, V> Drop for ListBucket<K, V> {
-    fn drop(&mut self) {
-        let mut ptr = self.root;
-        while !ptr.is_null() {
-            ptr = unsafe { Box::from_raw(ptr).next }; // free ptr
-        }
-    }
-}
+// the bucket owns its chain, the hash maps are Send/Sync through it
+unsafe impl<K: Send, V: Send> Send for EntryNode<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for EntryNode<K, V> {}
+unsafe impl<K: Send, V: Send> Send for ListBucket<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for ListBucket<K, V> {}
 
 impl<K, V> ListBucket<K, V> {
     pub fn new() -> Self {
@@ -50,20 +47,28 @@ impl<K, V> ListBucket<K, V> {
         count
     }
 
-    pub fn merge(&mut self, other: ListBucket<K, V>) {
-        let mut ptr = other.root;
+    pub fn merge(&mut self, mut other: ListBucket<K, V>) {
+        let mut ptr = other.take();
         while !ptr.is_null() {
             let node = unsafe { &mut *ptr };
             ptr = node.next;
             node.next = self.root;
-            self.root = ptr;
+            self.root = node;
         }
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.root.is_null()
+    }
+
     pub unsafe fn from_raw(head: *mut EntryNode<K, V>) -> Self {
         Self { root: head }
     }
 
+    pub fn head(&self) -> *mut EntryNode<K, V> {
+        self.root
+    }
+
     pub fn take(&mut self) -> *mut EntryNode<K, V> {
         let ptr = self.root;
         self.root = ptr::null_mut();
@@ -75,8 +80,8 @@ impl<K, V> ListBucket<K, V> {
         self.root = ptr;
     }
 
-    pub fn convert_to_tree(&mut self, hasher: impl Fn(&K) -> u64) -> TreeBucket<K, V> {
-        let mut tree_bucket = TreeBucket::<K, V>::new();
+    pub fn convert_to_tree(&mut self, hasher: impl Fn(&K) -> u64, order: Option<KeyOrder<K>>) -> TreeBucket<K, V> {
+        let mut tree_bucket = TreeBucket::<K, V>::with_order(order);
         let mut ptr: *mut EntryNode<K, V> = mem::replace(&mut self.root, std::ptr::null_mut());
         while !ptr.is_null() {
             let node = unsafe { &mut *ptr };
@@ -100,7 +105,7 @@ impl<K, V> ListBucket<K, V> {
         }
     }
 
-    pub fn transfer<F>(&mut self, old_cap: usize, index: usize, new_tab:&mut Vec<Bucket<K, V>>, hasher: F)
+    pub fn transfer<F>(&mut self, old_cap: usize, index: usize, new_tab: &mut [Bucket<K, V>], hasher: F)
     where F: Fn(&K) -> u64
     {
         let new_cap = new_tab.len();
@@ -152,35 +157,64 @@ impl<K, V> ListBucket<K, V> {
     }
 }
 
-impl<K, V> ListBucket<K, V>
-where
-    K: Eq,
-{
-    pub fn entry<'a>(&'a mut self, hash: u64, key: K, allocator: &'a mut Allocator<EntryNode<K, V>>) -> Entry<'a, K, V, ListBucket<K, V>> {
+impl<K, V> ListBucket<K, V> {
+    pub fn find_node<Q>(&self, key: &Q) -> *mut EntryNode<K, V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
         let mut ptr = self.root;
         while !ptr.is_null() {
-            let node = unsafe { &mut (*ptr) };
-            if key == node.key {
-                return Entry::Occupied(OccupiedEntry::new(&node.key, &mut node.value));
+            let node = unsafe { &*ptr };
+            if key == node.key.borrow() {
+                return ptr;
             }
             ptr = node.next;
         }
-        Entry::Vacant(VacantEntry::new(key, hash, self, allocator))
+        return ptr::null_mut();
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let node = unsafe { self.find_node(key).as_ref()? };
+        return Some(&node.value);
     }
 
-    pub fn get(&self, key: &K) -> Option<&mut V> {
-        let mut node = self.root;
-        while !node.is_null() {
-            let ptr = unsafe { &mut (*node) };
-            if *key == ptr.key {
-                return Some(&mut ptr.value);
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let node = unsafe { self.find_node(key).as_mut()? };
+        return Some(&mut node.value);
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let mut ptr = self.root;
+        let mut last: *mut EntryNode<K, V> = std::ptr::null_mut();
+        while !ptr.is_null() {
+            let node = unsafe { &mut (*ptr) };
+            if node.key.borrow() == key {
+                unsafe {
+                    if last.is_null() {
+                        self.root = node.next;
+                    } else {
+                        (*last).next = node.next;
+                    }
+                    return Some(allocator.free(NonNull::from(node)).value);
+                }
             }
-            node = ptr.next;
+            last = ptr;
+            ptr = node.next;
         }
         return None;
     }
+}
 
-    pub fn write(&mut self, key: K, value: V, allocator: &mut Allocator<EntryNode<K, V>>) -> (bool, Option<V>) {
+impl<K, V> ListBucket<K, V>
+where
+    K: Eq,
+{
+    pub fn write(&mut self, key: K, value: V, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> (bool, Option<V>) {
         let mut ptr: *mut EntryNode<K, V> = self.root;
         let mut count: u32 = 0;
         while !ptr.is_null() {
@@ -196,39 +230,102 @@ where
         self.add_node(key, value, allocator);
         return (count + 1 > MAX_LIST_LENGTH, None);
     }
+}
 
-    pub fn remove(&mut self, key: &K, allocator: &mut Allocator<EntryNode<K, V>>) -> Option<V> {
+impl <K, V> ListBucket<K, V> {
+    /// copy every node into a new list through `allocator`, the order is kept
+    pub fn clone_with(&self, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> ListBucket<K, V>
+    where K: Clone, V: Clone,
+    {
+        let mut list = ListBucket::new();
+        let mut link: *mut *mut EntryNode<K, V> = &mut list.root;
         let mut ptr = self.root;
-        let mut last: *mut EntryNode<K, V> = std::ptr::null_mut();
         while !ptr.is_null() {
-            let node = unsafe { &mut (*ptr) };
-            if node.key == *key {
-                unsafe {
-                    if last.is_null() {
-                        self.root = node.next;
-                    } else {
-                        (*last).next = node.next;
-                    }
-                    return Some(allocator.free(Box::from_raw(node)).value);
+            let node = unsafe { &*ptr };
+            let new_node = unsafe { allocator.alloc(EntryNode {
+                key: node.key.clone(),
+                value: node.value.clone(),
+                next: ptr::null_mut(),
+            }).as_mut() };
+            unsafe { *link = new_node };
+            link = &mut new_node.next;
+            ptr = node.next;
+        }
+        return list;
+    }
+
+    /// unlink the node whose key is stored at `key`, it is found by address so no `Eq` is needed
+    pub fn unlink(&mut self, key: *const K, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> Option<(K, V)> {
+        let mut link: *mut *mut EntryNode<K, V> = &mut self.root;
+        unsafe {
+            while !(*link).is_null() {
+                let node = *link;
+                if ptr::eq(&(*node).key, key) {
+                    *link = (*node).next;
+                    let node = allocator.free(NonNull::new_unchecked(node));
+                    return Some((node.key, node.value));
                 }
+                link = &mut (*node).next;
             }
-            last = ptr;
-            ptr = node.next;
         }
         return None;
     }
-}
 
-impl <K, V> RawTable<K, V> for ListBucket<K, V> {
-    fn add_node(&mut self, key: K, value: V, allocator: &mut Allocator<EntryNode<K, V>>) -> (&K, &mut V) {
-        let heap_node = allocator.alloc(EntryNode {
+    pub fn add_node(&mut self, key: K, value: V, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> (&K, &mut V) {
+        let new_node = unsafe { &mut *self.push_new(key, value, allocator) };
+        return (&new_node.key, &mut new_node.value);
+    }
+
+    /// allocate a node at the head of the chain and return it
+    pub fn push_new(&mut self, key: K, value: V, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> *mut EntryNode<K, V> {
+        let new_node = allocator.alloc(EntryNode {
             key: key,
             value: value,
             next: self.root,
-        });
-        let new_node = Box::leak(heap_node);
+        }).as_ptr();
         self.root = new_node;
-        return (&new_node.key, &mut new_node.value);
+        return new_node;
+    }
+}
+
+/**
+Unlinks the nodes accepted by a predicate in place and frees them through the allocator.
+`link` points at the slot that holds the next node to test, the bucket root or a `next` field.
+*/
+pub struct ExtractCursor<K, V> {
+    link: *mut *mut EntryNode<K, V>,
+}
+
+impl<K, V> ExtractCursor<K, V> {
+    pub fn new(list: &mut ListBucket<K, V>) -> Self {
+        ExtractCursor { link: &mut list.root }
+    }
+
+    pub fn empty() -> Self {
+        ExtractCursor { link: ptr::null_mut() }
+    }
+
+    pub fn next<F>(&mut self, f: &mut F, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> Option<(K, V)>
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        if self.link.is_null() {
+            return None;
+        }
+        unsafe {
+            loop {
+                let ptr = *self.link;
+                if ptr.is_null() {
+                    return None;
+                }
+                let node = &mut *ptr;
+                if f(&node.key, &mut node.value) {
+                    *self.link = node.next;
+                    let node = allocator.free(NonNull::new_unchecked(ptr));
+                    return Some((node.key, node.value));
+                }
+                self.link = &mut node.next;
+            }
+        }
     }
 }
 
@@ -1,7 +1,8 @@
+use std::alloc::Layout;
+use std::borrow::Borrow;
+use std::fmt::{self, Display, Formatter};
 use std::hash::{BuildHasher, Hash};
 use std::marker::PhantomData;
-use crate::collection::map::list_bucket::EntryNode;
-use crate::collection::map::allocator::Allocator;
 
 pub trait Map<K, V>
 {
@@ -13,11 +14,15 @@ pub trait Map<K, V>
         self.size()
     }
 
-    fn get(&self, key: &K) -> Option<&V>;
+    fn get<Q>(&self, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq;
 
-    fn get_mut(&mut self, key: &K) -> Option<&mut V>;
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq;
 
-    fn contains_key(&self, key: &K) -> bool {
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
         self.get(key).is_some()
     }
 
@@ -33,90 +38,189 @@ pub trait Map<K, V>
         return self.put(key, value)
     }
 
-    fn remove(&mut self, key: &K) -> Option<V>;
+    /// like `put`, on error the map keeps its entries and `key` and `value` are dropped
+    fn try_put(&mut self, key: K, value: V) -> Result<Option<V>, TryReserveError>;
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq;
 
     fn foreach<F: FnMut(&K, &mut V)>(&mut self, f: F);
+
+    /// remove every entry, the allocated buckets are kept
+    fn clear(&mut self);
+
+    /// number of entries the map holds before it has to grow
+    fn capacity(&self) -> usize;
+
+    /// make room for at least `additional` more entries
+    fn reserve(&mut self, additional: usize);
+
+    /// like `reserve`, but a capacity overflow or a failed allocation is returned instead of aborting
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError>;
+
+    /// give back the buckets that the current entries do not need
+    fn shrink_to_fit(&mut self);
+
+    fn retain<F>(&mut self, f: F)
+    where F: FnMut(&K, &mut V) -> bool;
+
+    fn extend<I>(&mut self, iter: I)
+    where I: IntoIterator<Item = (K, V)>,
+    {
+        let iter = iter.into_iter();
+        // keys may repeat, so only half of the hint is reserved once the map holds entries
+        let additional = if self.is_empty() { iter.size_hint().0 } else { iter.size_hint().0.div_ceil(2) };
+        self.reserve(additional);
+        for (key, value) in iter {
+            self.put(key, value);
+        }
+    }
 }
 
 pub enum Entry<'a, K, V, M>
 where M: RawTable<K, V>
 {
-    Occupied(OccupiedEntry<'a, K, V>),
+    Occupied(OccupiedEntry<'a, K, V, M>),
     Vacant(VacantEntry<'a, K, V, M>),
 }
 
 impl<'a, K, V, M> Entry<'a, K, V, M>
 where
-    K: Eq + Hash,
+    K: 'a,
     M: RawTable<K, V>
 {
     pub fn or_insert(self, default: V) -> &'a mut V {
         match self {
-            Entry::Occupied(x) => x._value,
-            Entry::Vacant(x) => x.or_insert(default),
+            Entry::Occupied(x) => x.into_mut(),
+            Entry::Vacant(x) => x.insert(default),
         }
     }
 
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where F: FnOnce() -> V
+    {
+        match self {
+            Entry::Occupied(x) => x.into_mut(),
+            Entry::Vacant(x) => x.insert(default()),
+        }
+    }
+
+    pub fn or_insert_with_key<F>(self, default: F) -> &'a mut V
+    where F: FnOnce(&K) -> V
+    {
+        match self {
+            Entry::Occupied(x) => x.into_mut(),
+            Entry::Vacant(x) => {
+                let value = default(&x.key);
+                x.insert(value)
+            }
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where V: Default
+    {
+        self.or_insert_with(Default::default)
+    }
+
     pub fn take_insert(self, default: V) -> Option<V> {
         match self {
-            Entry::Occupied(mut x) => Some(x.take_insert(default)),
+            Entry::Occupied(mut x) => Some(x.insert(default)),
             Entry::Vacant(x) => x.take_insert(default),
         }
     }
 
-    pub fn key(&'a self) -> &'a K {
+    pub fn key(&self) -> &K {
         match *self {
             Entry::Occupied(ref x) => x.key(),
             Entry::Vacant(ref x) => x.key(),
         }
     }
 
-    pub fn and_modify<F>(self, f: F) -> OccupiedEntry<'a, K, V>
-    where F: FnOnce(&mut V),
-          V: Default
+    /// modify the value of an occupied entry, a vacant entry is returned untouched
+    pub fn and_modify<F>(self, f: F) -> Self
+    where F: FnOnce(&mut V)
     {
         match self {
-            Entry::Occupied(x) => x.and_modify(f),
-            Entry::Vacant(x) => x.and_modify(f),
+            Entry::Occupied(mut x) => {
+                f(x.get_mut());
+                Entry::Occupied(x)
+            }
+            Entry::Vacant(x) => Entry::Vacant(x),
         }
     }
 }
 
-pub struct OccupiedEntry<'a, K, V> {
-    _key: &'a K,
-    _value: &'a mut V,
+/**
+An entry that is present in the table. The key and value point into the node, the table is
+kept borrowed so `remove` can unlink the node through the table's allocator.
+*/
+pub struct OccupiedEntry<'a, K, V, M: RawTable<K, V>> {
+    key: *const K,
+    value: *mut V,
+    hash: u64,
+    base: &'a mut M,
 }
 
-impl <'a, K, V> OccupiedEntry<'a, K, V> {
-    pub fn new(key:&'a K, value: &'a mut V) -> OccupiedEntry<'a, K, V> {
+impl <'a, K, V, M: RawTable<K, V>> OccupiedEntry<'a, K, V, M> {
+    /// `key` and `value` must point into a node of `base` stored under `hash`
+    pub(crate) unsafe fn new(key: *const K, value: *mut V, hash: u64, base: &'a mut M) -> OccupiedEntry<'a, K, V, M> {
         OccupiedEntry {
-            _key: key,
-            _value: value,
+            key: key,
+            value: value,
+            hash: hash,
+            base: base,
         }
     }
 
-    pub fn key(&self) -> &'a K {
-        self._key
+    pub fn key(&self) -> &K {
+        unsafe { &*self.key }
+    }
+
+    pub fn get(&self) -> &V {
+        unsafe { &*self.value }
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { &mut *self.value }
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        unsafe { &mut *self.value }
     }
 
     pub fn value(self) -> &'a mut V {
-        self._value
+        self.into_mut()
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
     }
 
     pub fn take_insert(&mut self, value: V) -> V {
-        let old = std::mem::replace(self._value, value);
-        return old;
+        self.insert(value)
     }
 
-    pub fn and_modify<F>(self, f: F) -> Self
-    where F: FnOnce(&mut V) {
-        f(self._value);
-        OccupiedEntry::new(self._key, self._value)
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        unsafe { self.base.remove_entry(self.hash, self.key) }
     }
 }
 
+/**
+The table behind an `Entry`. A `VacantEntry` inserts a key that is known to be absent and an
+`OccupiedEntry` unlinks the node it points to. Hash maps look up the bucket by `hash`,
+ordered maps ignore it.
+*/
 pub trait RawTable<K, V> {
-    fn add_node(&mut self, key: K, value: V, allocator: &mut Allocator<EntryNode<K, V>>) -> (&K ,&mut V);
+    fn add_node(&mut self, hash: u64, key: K, value: V) -> (&K ,&mut V);
+
+    /// # Safety
+    /// `key` must point to the key of a node stored in this table under `hash`.
+    unsafe fn remove_entry(&mut self, hash: u64, key: *const K) -> (K, V);
 }
 
 pub struct VacantEntry<'a, K, V, M: RawTable<K, V>>
@@ -124,56 +228,98 @@ pub struct VacantEntry<'a, K, V, M: RawTable<K, V>>
     pub key: K,
     pub hash: u64,
     pub base: &'a mut M,
-    pub allocator: &'a mut Allocator<EntryNode<K, V>>,
     pub _marker: PhantomData<V>,
 }
 
 impl <'a, K, V, M: RawTable<K, V>> VacantEntry<'a, K, V, M>
-where K: Eq + 'a
+where K: 'a
 {
-    pub fn new(key: K, hash:u64, tab: &'a mut M, allocator: &'a mut Allocator<EntryNode<K, V>>) -> VacantEntry<'a, K, V, M> {
-        VacantEntry { key: key, hash: hash, base: tab, allocator: allocator,_marker: PhantomData }
+    pub fn new(key: K, hash:u64, tab: &'a mut M) -> VacantEntry<'a, K, V, M> {
+        VacantEntry { key: key, hash: hash, base: tab, _marker: PhantomData }
     }
 
     pub fn key(&self) -> &K {
         &self.key
     }
 
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        return self.base.add_node(self.hash, self.key, value).1;
+    }
+
     pub fn or_insert(self, value: V) -> &'a mut V {
-        return self.base.add_node(self.key, value, self.allocator).1;
+        self.insert(value)
     }
 
     // take_and_insert
     pub fn take_insert(self, value: V) -> Option<V> {
-        self.base.add_node(self.key, value, self.allocator);
+        self.base.add_node(self.hash, self.key, value);
         return None
     }
 
-    pub fn and_modify<F>(self, f: F) -> OccupiedEntry<'a, K, V>
+    pub fn put_if_absent<F>(self, f: F) -> OccupiedEntry<'a, K, V, M>
     where
-        F: FnOnce(&mut V),
-        V: Default
+        F: FnOnce(&K) -> V,
     {
-        let mut value:V = Default::default();
-        f(&mut value);
+        let value = f(&self.key);
+        let (key, value) = self.base.add_node(self.hash, self.key, value);
+        let (key, value) = (key as *const K, value as *mut V);
+        unsafe { OccupiedEntry::new(key, value, self.hash, self.base) }
+    }
+}
+
+/// returned by `try_reserve` and `try_put` when the map cannot grow
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TryReserveError {
+    /// the capacity does not fit in `usize`
+    CapacityOverflow,
+    /// the global allocator could not allocate `layout`
+    AllocError { layout: Layout },
+}
 
-        let (kptr,vptr) = self.base.add_node(self.key, value, self.allocator);
-        OccupiedEntry::new(kptr, vptr)
+impl TryReserveError {
+    /// the error of `Vec::try_reserve` for an array of `len` elements
+    pub(crate) fn for_array<T>(len: usize) -> TryReserveError {
+        match Layout::array::<T>(len) {
+            Ok(layout) => TryReserveError::AllocError { layout: layout },
+            Err(_) => TryReserveError::CapacityOverflow,
+        }
     }
 
-    pub fn put_if_absent<F>(self, f: F) -> OccupiedEntry<'a, K, V>
-    where
-        F: FnOnce(&K) -> V,
-    {
-        let value = f(&self.key);
-        let (kptr,vptr) = self.base.add_node(self.key, value, self.allocator);
-        OccupiedEntry::new(kptr, vptr)
+    /// the infallible functions fail the same way `Vec` does
+    pub(crate) fn raise(self) -> ! {
+        match self {
+            TryReserveError::CapacityOverflow => panic!("capacity overflow"),
+            TryReserveError::AllocError { layout } => std::alloc::handle_alloc_error(layout),
+        }
     }
 }
 
+impl Display for TryReserveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TryReserveError::CapacityOverflow => f.write_str("capacity overflow"),
+            TryReserveError::AllocError { layout } => write!(f, "memory allocation of {} bytes failed", layout.size()),
+        }
+    }
+}
+
+impl std::error::Error for TryReserveError {}
+
+/// a vector of `len` elements made by `f`, the allocation failure is returned instead of aborting
+pub(crate) fn try_filled_vec<T>(len: usize, f: impl FnMut() -> T) -> Result<Vec<T>, TryReserveError> {
+    let mut vec = Vec::new();
+    vec.try_reserve_exact(len).map_err(|_| TryReserveError::for_array::<T>(len))?;
+    vec.resize_with(len, f);
+    return Ok(vec);
+}
+
 #[cfg_attr(feature = "inline-more", inline)]
-pub fn make_hash<K, S>(hash_builder: &S, key: &K) -> u64
-where K: Hash,
+pub fn make_hash<Q, S>(hash_builder: &S, key: &Q) -> u64
+where Q: ?Sized + Hash,
       S: BuildHasher,
 {
     hash_builder.hash_one(key)
@@ -186,3 +332,93 @@ where K: Hash,
 {
     move | k:&K | ->u64 { make_hash(hash_builder, k) }
 }
+
+/**
+The bucket distribution of a `HashMap` or `TreeHashMap`, made by their `stats`. A good hash spreads
+the entries so that most chains are 0, 1 or 2 long. Long chains or many tree buckets point at a
+weak `Hash` implementation or at keys chosen to collide.
+*/
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MapStats {
+    pub entries: usize,
+    /// the buckets of both tables while an incremental resize is running
+    pub buckets: usize,
+    pub empty_buckets: usize,
+    /// `chain_lengths[n]` is the number of list buckets with `n` entries
+    pub chain_lengths: Vec<usize>,
+    /// the longest list, the same-hash lists inside tree buckets included
+    pub longest_chain: usize,
+    pub tree_buckets: usize,
+    /// the number of distinct hashes over all tree buckets
+    pub tree_hashes: usize,
+    /// entries per bucket
+    pub load_factor: f64,
+}
+
+impl MapStats {
+    pub(crate) fn new(entries: usize, buckets: usize) -> MapStats {
+        MapStats {
+            entries: entries,
+            buckets: buckets,
+            load_factor: if buckets == 0 { 0.0 } else { entries as f64 / buckets as f64 },
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn add_chain(&mut self, len: usize) {
+        if self.chain_lengths.len() <= len {
+            self.chain_lengths.resize(len + 1, 0);
+        }
+        self.chain_lengths[len] += 1;
+        if len == 0 {
+            self.empty_buckets += 1;
+        }
+        self.longest_chain = self.longest_chain.max(len);
+    }
+
+    pub(crate) fn add_tree(&mut self, chains: impl Iterator<Item = usize>) {
+        self.tree_buckets += 1;
+        for len in chains {
+            self.tree_hashes += 1;
+            self.longest_chain = self.longest_chain.max(len);
+        }
+    }
+}
+
+/// the length hint of a serialized collection comes from the input, so at most this much is reserved up front
+#[cfg(feature = "serde")]
+const MAX_PREALLOCATION: usize = 1 << 12;
+
+/// fills a map from a serialized map, `HashMap` and `TreeHashMap` deserialize through it
+#[cfg(feature = "serde")]
+pub(crate) struct MapVisitor<K, V, M> {
+    _marker: PhantomData<fn() -> M>,
+    _entry: PhantomData<fn() -> (K, V)>,
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, M> MapVisitor<K, V, M> {
+    pub(crate) fn new() -> Self {
+        MapVisitor { _marker: PhantomData, _entry: PhantomData }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V, M> serde::de::Visitor<'de> for MapVisitor<K, V, M>
+where K: serde::Deserialize<'de>, V: serde::Deserialize<'de>, M: Map<K, V> + Default,
+{
+    type Value = M;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a map")
+    }
+
+    fn visit_map<T: serde::de::MapAccess<'de>>(self, mut access: T) -> Result<M, T::Error> {
+        let mut map = M::default();
+        map.reserve(access.size_hint().unwrap_or(0).min(MAX_PREALLOCATION));
+        while let Some((key, value)) = access.next_entry()? {
+            map.put(key, value);
+        }
+        return Ok(map);
+    }
+}
@@ -1,8 +1,9 @@
+use std::borrow::Borrow;
 use std::ptr::NonNull;
-use crate::collection::map::Entry;
-use crate::collection::map::list_bucket::{EntryNode, ListBucket, ListBucketIter};
-use crate::collection::map::tree_bucket::{TreeBucket, TreeBucketIter};
-use crate::collection::map::allocator::Allocator;
+use crate::collection::map::list_bucket::{EntryNode, ExtractCursor, ListBucket, ListBucketIter, MAX_LIST_LENGTH};
+use crate::collection::map::tree_bucket::{KeyOrder, TreeBucket, TreeBucketIter, TreeExtractCursor};
+use crate::collection::map::allocator::NodeAllocator;
+use crate::collection::map::map::MapStats;
 
 #[derive(Debug)]
 pub struct Bucket<K, V> {
@@ -34,26 +35,115 @@ impl<K, V> Bucket<K, V> {
         }
     }
 
+    /// copy the bucket without rehashing, a tree bucket keeps the hashes of its tree nodes
+    pub fn clone_with(&self, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> Bucket<K, V>
+    where K: Clone, V: Clone,
+    {
+        match &self.bucket {
+            BucketEnum::List(list) => Bucket::with_list(list.clone_with(allocator)),
+            BucketEnum::Tree(tree) => Bucket::with_tree(tree.clone_with(allocator)),
+        }
+    }
+
+    pub fn add_to(&self, stats: &mut MapStats) {
+        match &self.bucket {
+            BucketEnum::List(list) => stats.add_chain(list.count()),
+            BucketEnum::Tree(tree) => stats.add_tree(tree.chain_lengths()),
+        }
+    }
+
     pub fn take(self) -> BucketEnum<K, V> {
        self.bucket
     }
 
-    pub fn remove(&mut self, hash: u64, key: &K, allocator: &mut Allocator<EntryNode<K, V>>) -> Option<V>
-    where
-        K: Eq,
+    /// move every entry out as one chain, a tree bucket is replaced by an empty list
+    pub fn take_list(&mut self) -> *mut EntryNode<K, V> {
+        match &mut self.bucket {
+            BucketEnum::List(list) => list.take(),
+            BucketEnum::Tree(tree) => {
+                let head = tree.untreeify().take();
+                self.bucket = BucketEnum::List(ListBucket::new());
+                head
+            }
+        }
+    }
+
+    pub fn remove<Q>(&mut self, hash: u64, key: &Q, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
     {
         match &mut self.bucket {
             BucketEnum::List(list) => {
                 list.remove(key, allocator)
             }
             BucketEnum::Tree(tree) => {
-                tree.tree.remove_if(&hash, |x| {true});
-                todo!();
+                let value = tree.remove(hash, key, allocator);
+                if value.is_some() {
+                    self.shrink_tree();
+                }
+                value
+            }
+        }
+    }
+
+    /// like `remove`, a tree bucket with ordered keys searches its tree by key
+    pub fn remove_ordered<Q>(&mut self, hash: u64, key: &Q, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
+        match &mut self.bucket {
+            BucketEnum::List(list) => list.remove(key, allocator),
+            BucketEnum::Tree(tree) => {
+                let value = tree.remove_ordered(hash, key, allocator);
+                if value.is_some() {
+                    self.shrink_tree();
+                }
+                value
+            }
+        }
+    }
+
+    pub fn unlink(&mut self, hash: u64, key: *const K, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> Option<(K, V)> {
+        match &mut self.bucket {
+            BucketEnum::List(list) => list.unlink(key, allocator),
+            BucketEnum::Tree(tree) => {
+                let entry = tree.unlink(hash, key, allocator);
+                if entry.is_some() {
+                    self.shrink_tree();
+                }
+                entry
             }
         }
     }
 
-    pub fn write(&mut self, hash: u64, key: K, value: V, allocator: &mut Allocator<EntryNode<K, V>>, hasher: impl Fn(&K) -> u64) -> Option<V>
+    /// convert a tree bucket that has shrunk to a few entries back to a list
+    pub fn shrink_tree(&mut self) {
+        if let BucketEnum::Tree(tree) = &mut self.bucket
+            && tree.should_untreeify()
+        {
+            let list = tree.untreeify();
+            self.bucket = BucketEnum::List(list);
+        }
+    }
+
+    pub fn extract_cursor(&mut self) -> BucketCursor<K, V> {
+        match &mut self.bucket {
+            BucketEnum::List(list) => BucketCursor::List(ExtractCursor::new(list)),
+            BucketEnum::Tree(tree) => BucketCursor::Tree(TreeExtractCursor::new(tree)),
+        }
+    }
+
+    /// the cursor must come from `extract_cursor` of this bucket, and the bucket is not modified in between
+    pub fn extract_next<F>(&mut self, cursor: &mut BucketCursor<K, V>, f: &mut F, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> Option<(K, V)>
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        match (&mut self.bucket, cursor) {
+            (_, BucketCursor::List(cursor)) => cursor.next(f, allocator),
+            (BucketEnum::Tree(tree), BucketCursor::Tree(cursor)) => cursor.next(tree, f, allocator),
+            (BucketEnum::List(_), BucketCursor::Tree(_)) => unreachable!("tree cursor on a list bucket"),
+        }
+    }
+
+    /// a list that gets too long becomes a tree bucket, which orders the keys by `order` if given
+    pub fn write(&mut self, hash: u64, key: K, value: V, allocator: &mut impl NodeAllocator<EntryNode<K, V>>, hasher: impl Fn(&K) -> u64, order: Option<KeyOrder<K>>) -> Option<V>
     where
         K: Eq,
     {
@@ -61,8 +151,8 @@ impl<K, V> Bucket<K, V> {
             BucketEnum::Tree(tree) => tree.write(hash, key, value, allocator),
             BucketEnum::List(list) => {
                 let (is_gt8, option) = list.write(key, value, allocator);
-                if is_gt8 == true {
-                    let tree_bucket: TreeBucket<K, V> = list.convert_to_tree(hasher);
+                if is_gt8 {
+                    let tree_bucket: TreeBucket<K, V> = list.convert_to_tree(hasher, order);
                     self.bucket = BucketEnum::Tree(tree_bucket);
                 }
                 option
@@ -81,18 +171,50 @@ impl<K, V> Bucket<K, V> {
         }
     }
 
-    pub fn entry<'a>(&'a mut self, hash: u64, key: K, allocator: &'a mut Allocator<EntryNode<K, V>>) -> Entry<'a, K, V, ListBucket<K, V>>
+    pub fn find_node<Q>(&self, hash: u64, key: &Q) -> *mut EntryNode<K, V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        match &self.bucket {
+            BucketEnum::List(list) => list.find_node(key),
+            BucketEnum::Tree(tree) => tree.find_node(hash, key),
+        }
+    }
+
+    /// like `find_node`, a tree bucket with ordered keys searches its tree by the `Ord` key
+    pub fn find_ordered<Q>(&self, hash: u64, key: &Q) -> *mut EntryNode<K, V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
+        match &self.bucket {
+            BucketEnum::List(list) => list.find_node(key),
+            BucketEnum::Tree(tree) => tree.find_ordered(hash, key),
+        }
+    }
+
+    /// like `find_node`, a tree bucket with ordered keys searches its tree by key
+    pub fn find_key(&self, hash: u64, key: &K) -> *mut EntryNode<K, V>
     where K: Eq,
     {
+        match &self.bucket {
+            BucketEnum::List(list) => list.find_node(key),
+            BucketEnum::Tree(tree) => tree.find_key(hash, key),
+        }
+    }
+
+    pub fn set_key_order(&mut self, order: Option<KeyOrder<K>>) {
+        if let BucketEnum::Tree(tree) = &mut self.bucket {
+            tree.set_order(order);
+        }
+    }
+
+    pub fn add_node(&mut self, hash: u64, key: K, value: V, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> (&K, &mut V) {
         match &mut self.bucket {
-            BucketEnum::List(list) => list.entry(hash, key, allocator),
-            BucketEnum::Tree(tree) => tree.entry(hash, key, allocator),
+            BucketEnum::List(list) => list.add_node(key, value, allocator),
+            BucketEnum::Tree(tree) => tree.add_node(hash, key, value, allocator),
         }
     }
 
-    pub fn get(&self, hash: u64, key: &K) -> Option<&mut V>
-    where
-        K: Eq,
+    pub fn get<Q>(&self, hash: u64, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
     {
         return match &self.bucket {
             BucketEnum::List(list) => list.get(key),
@@ -100,25 +222,78 @@ impl<K, V> Bucket<K, V> {
         };
     }
 
+    pub fn get_mut<Q>(&mut self, hash: u64, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        return match &mut self.bucket {
+            BucketEnum::List(list) => list.get_mut(key),
+            BucketEnum::Tree(tree) => tree.get_mut(hash, key),
+        };
+    }
+
     pub fn foreach<F: FnMut(&K, &mut V)>(&mut self, mut f: F) {
         match &mut self.bucket {
             BucketEnum::List(list) => {
                 list.foreach(&mut f);
             }
             BucketEnum::Tree(tree) => {
-                for (_, v) in &tree.tree {
-                    v.foreach(&mut f);
-                }
+                tree.foreach(&mut f);
             }
         }
     }
 
-    pub fn transfer<F>(tab: &mut Vec<Bucket<K, V>>, new_tab: &mut Vec<Bucket<K, V>>, hasher: F)
+    /// move every node of `tab` into `new_tab` of any size, chains that got too long become trees
+    pub fn rehash<F>(tab: &mut [Bucket<K, V>], new_tab: &mut [Bucket<K, V>], hasher: F, order: Option<KeyOrder<K>>)
+    where F: Fn(&K) -> u64
+    {
+        let mask = new_tab.len() - 1;
+        for bucket in tab.iter_mut() {
+            let mut ptr = bucket.take_list();
+            while !ptr.is_null() {
+                let node = unsafe { &mut *ptr };
+                ptr = node.next;
+                let hash = hasher(&node.key);
+                new_tab[hash as usize & mask].push(hash, node);
+            }
+        }
+        for bucket in new_tab.iter_mut() {
+            bucket.treeify_if_long(&hasher, order);
+        }
+    }
+
+    /// move the nodes of this bucket into `new_tab`, whose buckets may already hold entries
+    pub fn move_into<F>(&mut self, new_tab: &mut [Bucket<K, V>], hasher: F, order: Option<KeyOrder<K>>)
+    where F: Fn(&K) -> u64
+    {
+        let mask = new_tab.len() - 1;
+        let mut ptr = self.take_list();
+        while !ptr.is_null() {
+            let node = unsafe { &mut *ptr };
+            ptr = node.next;
+            let hash = hasher(&node.key);
+            let bucket = &mut new_tab[hash as usize & mask];
+            bucket.push(hash, node);
+            bucket.treeify_if_long(&hasher, order);
+        }
+    }
+
+    /// convert a list bucket that was filled by `push` to a tree once it is longer than `MAX_LIST_LENGTH`
+    pub fn treeify_if_long<F>(&mut self, hasher: F, order: Option<KeyOrder<K>>)
+    where F: Fn(&K) -> u64
+    {
+        if let BucketEnum::List(list) = &mut self.bucket
+            && list.count() > MAX_LIST_LENGTH as usize
+        {
+            self.bucket = BucketEnum::Tree(list.convert_to_tree(hasher, order));
+        }
+    }
+
+    pub fn transfer<F>(tab: &mut [Bucket<K, V>], new_tab: &mut [Bucket<K, V>], hasher: F)
     where F: Fn(&K) -> u64
     {
         let old_cap = tab.len();
-        for i in 0..old_cap {
-            match &mut tab[i].bucket {
+        for (i, bucket) in tab.iter_mut().enumerate() {
+            match &mut bucket.bucket {
                 BucketEnum::List(bucket) =>  { bucket.transfer(old_cap, i, new_tab, &hasher) },
                 BucketEnum::Tree(tree) => tree.split_transfer(old_cap, i, new_tab),
             }
@@ -133,6 +308,17 @@ impl<K, V> Bucket<K, V> {
     }
 }
 
+pub enum BucketCursor<K, V> {
+    List(ExtractCursor<K, V>),
+    Tree(TreeExtractCursor<K, V>),
+}
+
+impl<K, V> BucketCursor<K, V> {
+    pub fn empty() -> Self {
+        Self::List(ExtractCursor::empty())
+    }
+}
+
 pub enum BucketIter<'a, K, V> {
     List(ListBucketIter<K, V>),
     Tree(TreeBucketIter<'a, K, V>),
@@ -158,20 +344,49 @@ impl<'a, K, V> Iterator for BucketIter<'a, K, V> {
 #[cfg(test)]
 mod test {
     use crate::collection::map::map_bucket::{Bucket, BucketEnum};
-    use crate::collection::map::allocator::Allocator;
+    use crate::collection::map::allocator::{NodeAllocator, SlabAllocator};
 
     #[test]
     fn test_convert_to_tree() {
-        let mut allocator = Allocator::new();
+        let mut allocator = SlabAllocator::new();
         let mut bucket:Bucket<i32, &str> = Bucket::new_list();
-        let hasher = |x:&i32 | -> u64 { 1 };
+        let hasher = |_:&i32 | -> u64 { 1 };
         for i in 0..8 {
-            bucket.write(hasher(&i), i, "a", &mut allocator, hasher);
+            bucket.write(hasher(&i), i, "a", &mut allocator, hasher, None);
             assert!(matches!(&bucket.bucket, BucketEnum::List(_)));
-            assert_eq!(allocator.size, i as usize + 1);
+            assert_eq!(allocator.size(), i as usize + 1);
         }
-        bucket.write(hasher(&8), 8, "a", &mut allocator, hasher);
-        assert_eq!(allocator.size, 9);
+        bucket.write(hasher(&8), 8, "a", &mut allocator, hasher, None);
+        assert_eq!(allocator.size(), 9);
         assert!(matches!(&bucket.bucket, BucketEnum::Tree(_)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_remove_untreeify() {
+        let mut allocator = SlabAllocator::new();
+        let mut bucket:Bucket<i32, i32> = Bucket::new_list();
+        let hasher = |x:&i32 | -> u64 { *x as u64 / 4 };
+        for i in 0..12 {
+            bucket.write(hasher(&i), i, i * 10, &mut allocator, hasher, None);
+        }
+        assert!(matches!(&bucket.bucket, BucketEnum::Tree(_)));
+
+        assert_eq!(bucket.remove(hasher(&100), &100, &mut allocator), None);
+        // drop the whole `hash = 0` list from the tree
+        for i in 0..4 {
+            assert_eq!(bucket.remove(hasher(&i), &i, &mut allocator), Some(i * 10));
+            assert_eq!(bucket.get(hasher(&i), &i), None);
+        }
+        assert_eq!(allocator.size(), 8);
+        assert!(matches!(&bucket.bucket, BucketEnum::Tree(_)));
+        assert_eq!(bucket.remove(hasher(&4), &4, &mut allocator), Some(40));
+        assert!(matches!(&bucket.bucket, BucketEnum::Tree(_)));
+        assert_eq!(bucket.remove(hasher(&5), &5, &mut allocator), Some(50));
+        assert!(matches!(&bucket.bucket, BucketEnum::List(_)));
+
+        assert_eq!(allocator.size(), 6);
+        for i in 6..12 {
+            assert_eq!(bucket.get(hasher(&i), &i), Some(&(i * 10)));
+        }
+    }
+}
@@ -2,8 +2,13 @@ mod hashmap;
 mod map;
 mod raw_hashmap;
 mod raw_rbtree_hashmap;
+mod raw_flat_hashmap;
+mod flat_hashmap;
 mod tree_bucket;
 mod rbtree_hashmap;
+mod tree_map;
+mod linked_hashmap;
+mod raw_linked_hashmap;
 mod list_bucket;
 mod map_bucket;
 mod allocator;
@@ -11,5 +16,18 @@ mod allocator;
 pub use map::Map;
 pub use map::Entry;
 pub use map::RawTable;
+pub use map::TryReserveError;
+pub use map::MapStats;
 pub use hashmap::HashMap;
 pub use rbtree_hashmap::HashMap as TreeHashMap;
+pub use flat_hashmap::HashMap as FlatHashMap;
+pub use tree_map::TreeMap;
+pub use linked_hashmap::LinkedHashMap;
+pub use raw_linked_hashmap::LinkedNode;
+pub use allocator::{BoxAllocator, NodeAllocator, SlabAllocator};
+pub use list_bucket::EntryNode;
+pub(crate) use map::{make_hash, make_hasher};
+pub(crate) use map_bucket::Bucket;
+pub(crate) use tree_bucket::KeyOrder;
+pub(crate) use raw_hashmap::{IntoIter as HashMapIntoIter, Iter as HashMapIter};
+pub(crate) use raw_rbtree_hashmap::{IntoIter as TreeHashMapIntoIter, Iter as TreeHashMapIter};
@@ -0,0 +1,659 @@
+use crate::collection::map::map::{try_filled_vec, Entry, OccupiedEntry, RawTable, TryReserveError, VacantEntry};
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::mem::{self, MaybeUninit};
+use std::ptr;
+
+const DEFAULT_INITIAL_CAPACITY: usize = 16;
+
+/// control bytes are matched a group at a time inside a `u64`
+const GROUP_WIDTH: usize = 8;
+
+const EMPTY: u8 = 0xFF;
+const DELETED: u8 = 0x80;
+
+const LO_BITS: u64 = 0x0101_0101_0101_0101;
+const HI_BITS: u64 = 0x8080_8080_8080_8080;
+
+/// the top 7 bits of the hash, stored in the control byte of a full slot
+fn h2(hash: u64) -> u8 {
+    (hash >> 57) as u8
+}
+
+fn is_full(ctrl: u8) -> bool {
+    ctrl & 0x80 == 0
+}
+
+/// a table holds entries in 7/8 of its slots, so a probe always ends at an empty slot
+fn bucket_capacity(buckets: usize) -> usize {
+    buckets - buckets / 8
+}
+
+/// `None` if the number of buckets does not fit in `usize`
+fn buckets_for(capacity: usize) -> Option<usize> {
+    let buckets = capacity.checked_mul(8)?.div_ceil(7).checked_next_power_of_two()?;
+    Some(buckets.max(GROUP_WIDTH))
+}
+
+#[derive(Clone, Copy)]
+struct Group(u64);
+
+impl Group {
+    fn load(ctrl: &[u8], pos: usize) -> Group {
+        let bytes: [u8; GROUP_WIDTH] = ctrl[pos..pos + GROUP_WIDTH].try_into().unwrap();
+        Group(u64::from_le_bytes(bytes))
+    }
+
+    /// the bytes equal to `byte`, a false positive is possible after a real match but only on a full slot
+    fn match_byte(self, byte: u8) -> BitMask {
+        let x = self.0 ^ (LO_BITS * byte as u64);
+        BitMask(x.wrapping_sub(LO_BITS) & !x & HI_BITS)
+    }
+
+    fn match_empty(self) -> BitMask {
+        BitMask(self.0 & (self.0 << 1) & HI_BITS)
+    }
+
+    fn match_empty_or_deleted(self) -> BitMask {
+        BitMask(self.0 & HI_BITS)
+    }
+}
+
+/// the high bit of every matching byte, iterates the byte offsets in the group
+struct BitMask(u64);
+
+impl BitMask {
+    fn any(&self) -> bool {
+        self.0 != 0
+    }
+
+    fn leading_zeros(&self) -> usize {
+        self.0.leading_zeros() as usize / 8
+    }
+
+    fn trailing_zeros(&self) -> usize {
+        self.0.trailing_zeros() as usize / 8
+    }
+}
+
+impl Iterator for BitMask {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let bit = self.trailing_zeros();
+        self.0 &= self.0 - 1;
+        Some(bit)
+    }
+}
+
+/// triangular probing over groups, it visits every group of a power of two table
+struct ProbeSeq {
+    pos: usize,
+    stride: usize,
+}
+
+impl ProbeSeq {
+    fn move_next(&mut self, mask: usize) {
+        self.stride += GROUP_WIDTH;
+        self.pos = (self.pos + self.stride) & mask;
+    }
+}
+
+struct Slot<K, V> {
+    key: K,
+    value: V,
+}
+
+/**
+An open addressing table in the SwissTable layout. The entries are stored inline in `slots` and
+every slot has a control byte that is `EMPTY`, `DELETED` or the top 7 bits of the hash, so a probe
+compares 8 control bytes at once and only reads the keys whose byte matches. There is no node
+allocation per entry.
+*/
+pub struct FlatTable<K, V> {
+    // `buckets + GROUP_WIDTH` bytes, the last group repeats the first so a group read never wraps
+    ctrl: Box<[u8]>,
+    slots: Box<[MaybeUninit<Slot<K, V>>]>,
+    items: usize,
+    // slots that can still turn from EMPTY to full before the table has to grow
+    growth_left: usize,
+    init_capacity: usize,
+}
+
+impl<K, V> Drop for FlatTable<K, V> {
+    fn drop(&mut self) {
+        if mem::needs_drop::<Slot<K, V>>() {
+            for index in 0..self.slots.len() {
+                if is_full(self.ctrl[index]) {
+                    unsafe { self.slots[index].assume_init_drop() };
+                }
+            }
+        }
+    }
+}
+
+impl<K, V> Default for FlatTable<K, V>
+{
+    fn default() -> Self {
+        FlatTable::new()
+    }
+}
+
+impl<K, V> Clone for FlatTable<K, V>
+where K: Clone, V: Clone,
+{
+    fn clone(&self) -> Self {
+        let mut table = FlatTable::try_allocate(self.slots.len(), self.init_capacity).unwrap_or_else(|e| e.raise());
+        for index in self.full_indexes() {
+            let slot = unsafe { self.slots[index].assume_init_ref() };
+            table.slots[index].write(Slot { key: slot.key.clone(), value: slot.value.clone() });
+            // mark the slot only once it is written, a panicking clone leaves a table that drops cleanly
+            table.set_ctrl(index, self.ctrl[index]);
+            table.items += 1;
+        }
+        table.ctrl.copy_from_slice(&self.ctrl);
+        table.growth_left = self.growth_left;
+        table
+    }
+}
+
+impl<K, V> FlatTable<K, V>
+{
+    pub fn new() -> FlatTable<K, V> {
+        Self::with_capacity(DEFAULT_INITIAL_CAPACITY)
+    }
+
+    /// the slots are allocated on the first insert
+    pub fn with_capacity(init_cap: usize) -> FlatTable<K, V> {
+        FlatTable {
+            ctrl: Box::new([]),
+            slots: Box::new([]),
+            items: 0,
+            growth_left: 0,
+            init_capacity: init_cap,
+        }
+    }
+
+    fn try_allocate(buckets: usize, init_capacity: usize) -> Result<FlatTable<K, V>, TryReserveError> {
+        if buckets == 0 {
+            return Ok(Self::with_capacity(init_capacity));
+        }
+        let ctrl_len = buckets.checked_add(GROUP_WIDTH).ok_or(TryReserveError::CapacityOverflow)?;
+        Ok(FlatTable {
+            ctrl: try_filled_vec(ctrl_len, || EMPTY)?.into_boxed_slice(),
+            slots: try_filled_vec(buckets, MaybeUninit::uninit)?.into_boxed_slice(),
+            items: 0,
+            growth_left: bucket_capacity(buckets),
+            init_capacity: init_capacity,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.items
+    }
+
+    // iterator
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { raw: RawIter::new(&self.ctrl, self.slots.as_ptr() as *mut _, self.items) }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        let slots = self.slots.as_mut_ptr();
+        IterMut { raw: RawIter::new(&self.ctrl, slots, self.items), _marker: PhantomData }
+    }
+
+    /// number of entries the table holds before it grows, 0 until the slots are allocated
+    pub fn capacity(&self) -> usize {
+        self.items + self.growth_left
+    }
+
+    pub fn reserve(&mut self, additional: usize, hasher: impl Fn(&K) -> u64) {
+        if let Err(e) = self.try_reserve(additional, hasher) {
+            e.raise();
+        }
+    }
+
+    /// make room for `additional` more entries, no entry is changed on error
+    pub fn try_reserve(&mut self, additional: usize, hasher: impl Fn(&K) -> u64) -> Result<(), TryReserveError> {
+        if !self.slots.is_empty() && additional <= self.growth_left {
+            return Ok(());
+        }
+        let needed = self.items.checked_add(additional).ok_or(TryReserveError::CapacityOverflow)?;
+        if self.slots.is_empty() {
+            let buckets = buckets_for(needed.max(self.init_capacity)).ok_or(TryReserveError::CapacityOverflow)?;
+            return self.try_resize(buckets, hasher);
+        }
+        let full_capacity = bucket_capacity(self.slots.len());
+        if needed <= full_capacity / 2 {
+            // mostly tombstones, rebuild at the same size
+            return self.try_resize(self.slots.len(), hasher);
+        }
+        let buckets = buckets_for(needed.max(full_capacity + 1)).ok_or(TryReserveError::CapacityOverflow)?;
+        return self.try_resize(buckets, hasher);
+    }
+
+    /// rebuild the table at the smallest size that holds the current entries, a failed allocation keeps the table
+    pub fn shrink_to_fit(&mut self, hasher: impl Fn(&K) -> u64) {
+        if self.items == 0 {
+            *self = Self::with_capacity(self.init_capacity);
+            return;
+        }
+        let buckets = buckets_for(self.items).expect("the entries fit in the table");
+        if buckets < self.slots.len() {
+            let _ = self.try_resize(buckets, hasher);
+        }
+    }
+
+    /// remove every entry, the slots keep their capacity
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
+
+    /// the new table is allocated before any entry moves
+    fn try_resize(&mut self, buckets: usize, hasher: impl Fn(&K) -> u64) -> Result<(), TryReserveError> {
+        let mut table = FlatTable::try_allocate(buckets, self.init_capacity)?;
+        for index in 0..self.slots.len() {
+            if !is_full(self.ctrl[index]) {
+                continue;
+            }
+            // hash before moving, a panicking hasher leaves every entry in exactly one table
+            let hash = hasher(unsafe { &self.slots[index].assume_init_ref().key });
+            self.set_ctrl(index, EMPTY);
+            self.items -= 1;
+            let slot = unsafe { self.slots[index].assume_init_read() };
+            let new_index = table.find_insert_slot(hash);
+            table.insert_at(new_index, hash, slot);
+        }
+        *self = table;
+        return Ok(());
+    }
+
+    fn bucket_mask(&self) -> usize {
+        self.slots.len() - 1
+    }
+
+    fn set_ctrl(&mut self, index: usize, ctrl: u8) {
+        let mirror = (index.wrapping_sub(GROUP_WIDTH) & self.bucket_mask()) + GROUP_WIDTH;
+        self.ctrl[index] = ctrl;
+        self.ctrl[mirror] = ctrl;
+    }
+
+    fn full_indexes(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.slots.len()).filter(|index| is_full(self.ctrl[*index]))
+    }
+
+    fn find(&self, hash: u64, mut eq: impl FnMut(&K) -> bool) -> Option<usize> {
+        if self.slots.is_empty() {
+            return None;
+        }
+        let mask = self.bucket_mask();
+        let h2 = h2(hash);
+        let mut probe = ProbeSeq { pos: hash as usize & mask, stride: 0 };
+        loop {
+            let group = Group::load(&self.ctrl, probe.pos);
+            for bit in group.match_byte(h2) {
+                let index = (probe.pos + bit) & mask;
+                if eq(unsafe { &self.slots[index].assume_init_ref().key }) {
+                    return Some(index);
+                }
+            }
+            if group.match_empty().any() {
+                return None;
+            }
+            probe.move_next(mask);
+        }
+    }
+
+    /// the first empty or deleted slot on the probe sequence of `hash`
+    fn find_insert_slot(&self, hash: u64) -> usize {
+        let mask = self.bucket_mask();
+        let mut probe = ProbeSeq { pos: hash as usize & mask, stride: 0 };
+        loop {
+            let group = Group::load(&self.ctrl, probe.pos);
+            if let Some(bit) = group.match_empty_or_deleted().next() {
+                return (probe.pos + bit) & mask;
+            }
+            probe.move_next(mask);
+        }
+    }
+
+    fn insert_at(&mut self, index: usize, hash: u64, slot: Slot<K, V>) -> &mut Slot<K, V> {
+        if self.ctrl[index] == EMPTY {
+            self.growth_left -= 1;
+        }
+        self.set_ctrl(index, h2(hash));
+        self.items += 1;
+        self.slots[index].write(slot)
+    }
+
+    /// an emptied slot only becomes a tombstone if a probe may have passed it without stopping
+    fn erase(&mut self, index: usize) -> (K, V) {
+        let before = index.wrapping_sub(GROUP_WIDTH) & self.bucket_mask();
+        let empty_before = Group::load(&self.ctrl, before).match_empty();
+        let empty_after = Group::load(&self.ctrl, index).match_empty();
+        let ctrl = if empty_before.leading_zeros() + empty_after.trailing_zeros() >= GROUP_WIDTH {
+            DELETED
+        } else {
+            self.growth_left += 1;
+            EMPTY
+        };
+        self.set_ctrl(index, ctrl);
+        self.items -= 1;
+        let slot = unsafe { self.slots[index].assume_init_read() };
+        (slot.key, slot.value)
+    }
+
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        Drain { table: self, index: 0 }
+    }
+
+    pub fn retain<F>(&mut self, mut f: F)
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        self.extract_if(|k, v| !f(k, v)).for_each(drop);
+    }
+
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, F>
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        ExtractIf { table: self, index: 0, pred: pred }
+    }
+
+    pub fn foreach<F: FnMut(&K, &mut V)>(&mut self, mut f: F) {
+        for (key, value) in self.iter_mut() {
+            f(key, value);
+        }
+    }
+
+    /// take the next entry at or after `index` out of the table, used by `Drain` and `IntoIter`
+    fn take_next(&mut self, index: &mut usize) -> Option<(K, V)> {
+        while *index < self.slots.len() {
+            let i = *index;
+            *index += 1;
+            if is_full(self.ctrl[i]) {
+                self.set_ctrl(i, EMPTY);
+                self.items -= 1;
+                let slot = unsafe { self.slots[i].assume_init_read() };
+                return Some((slot.key, slot.value));
+            }
+        }
+        None
+    }
+}
+
+impl<K, V> FlatTable<K, V>
+where K: Eq,
+{
+    pub fn get<Q>(&self, hash: u64, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let index = self.find(hash, |k| k.borrow() == key)?;
+        Some(unsafe { &self.slots[index].assume_init_ref().value })
+    }
+
+    pub fn get_mut<Q>(&mut self, hash: u64, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let index = self.find(hash, |k| k.borrow() == key)?;
+        Some(unsafe { &mut self.slots[index].assume_init_mut().value })
+    }
+
+    pub fn put(&mut self, hash: u64, key: K, value: V, hasher: impl Fn(&K) -> u64) -> Option<V> {
+        self.try_put(hash, key, value, hasher).unwrap_or_else(|e| e.raise())
+    }
+
+    /// on error no entry is changed and `key` and `value` are dropped
+    pub fn try_put(&mut self, hash: u64, key: K, value: V, hasher: impl Fn(&K) -> u64) -> Result<Option<V>, TryReserveError> {
+        if let Some(index) = self.find(hash, |k| *k == key) {
+            let slot = unsafe { self.slots[index].assume_init_mut() };
+            return Ok(Some(mem::replace(&mut slot.value, value)));
+        }
+        self.try_reserve(1, hasher)?;
+        let index = self.find_insert_slot(hash);
+        self.insert_at(index, hash, Slot { key: key, value: value });
+        Ok(None)
+    }
+
+    pub fn remove<Q>(&mut self, hash: u64, key: &Q) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let index = self.find(hash, |k| k.borrow() == key)?;
+        Some(self.erase(index).1)
+    }
+
+    pub fn entry(&mut self, hash: u64, key: K, hasher: impl Fn(&K) -> u64) -> Entry<'_, K, V, FlatTable<K, V>> {
+        if let Some(index) = self.find(hash, |k| *k == key) {
+            let slot: *mut Slot<K, V> = self.slots[index].as_mut_ptr();
+            return Entry::Occupied(unsafe { OccupiedEntry::new(&(*slot).key, &mut (*slot).value, hash, self) });
+        }
+        // only a vacant entry may add a slot, an occupied one leaves the table as it is
+        self.reserve(1, hasher);
+        Entry::Vacant(VacantEntry::new(key, hash, self))
+    }
+}
+
+impl<K, V> RawTable<K, V> for FlatTable<K, V> {
+    /// the caller has reserved room for one more entry
+    fn add_node(&mut self, hash: u64, key: K, value: V) -> (&K, &mut V) {
+        let index = self.find_insert_slot(hash);
+        let slot = self.insert_at(index, hash, Slot { key: key, value: value });
+        return (&slot.key, &mut slot.value);
+    }
+
+    unsafe fn remove_entry(&mut self, hash: u64, key: *const K) -> (K, V) {
+        let index = self.find(hash, |k| ptr::eq(k, key)).expect("entry is not in the table");
+        return self.erase(index);
+    }
+}
+
+// iterator
+impl<'a, K, V> IntoIterator for &'a FlatTable<K, V>
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut FlatTable<K, V>
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        self.iter_mut()
+    }
+}
+
+impl<K, V> IntoIterator for FlatTable<K, V>
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> IntoIter<K, V> {
+        IntoIter { table: self, index: 0 }
+    }
+}
+
+pub struct IntoIter<K, V> {
+    table: FlatTable<K, V>,
+    index: usize,
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.table.take_next(&mut self.index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.table.size(), Some(self.table.size()))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+/// removes the remaining entries when dropped
+pub struct Drain<'a, K, V> {
+    table: &'a mut FlatTable<K, V>,
+    index: usize,
+}
+
+impl<K, V> Iterator for Drain<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.table.take_next(&mut self.index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.table.size(), Some(self.table.size()))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Drain<'_, K, V> {}
+
+impl<K, V> Drop for Drain<'_, K, V> {
+    fn drop(&mut self) {
+        while self.table.take_next(&mut self.index).is_some() {}
+        // the tombstones are gone too, every slot is free again
+        self.table.ctrl.fill(EMPTY);
+        self.table.growth_left = if self.table.slots.is_empty() { 0 } else { bucket_capacity(self.table.slots.len()) };
+    }
+}
+
+/// lazily removes the entries accepted by `pred`, the rest stay in the map once it is dropped
+pub struct ExtractIf<'a, K, V, F> {
+    table: &'a mut FlatTable<K, V>,
+    index: usize,
+    pred: F,
+}
+
+impl<K, V, F> Iterator for ExtractIf<'_, K, V, F>
+where F: FnMut(&K, &mut V) -> bool
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.table.slots.len() {
+            let index = self.index;
+            self.index += 1;
+            if !is_full(self.table.ctrl[index]) {
+                continue;
+            }
+            let slot = unsafe { self.table.slots[index].assume_init_mut() };
+            if (self.pred)(&slot.key, &mut slot.value) {
+                return Some(self.table.erase(index));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.table.size()))
+    }
+}
+
+/// walks the slots in order and yields the full ones, shared by `Iter` and `IterMut`
+struct RawIter<'a, K, V> {
+    ctrl: &'a [u8],
+    slots: *mut MaybeUninit<Slot<K, V>>,
+    index: usize,
+    remaining: usize,
+    _marker: PhantomData<&'a Slot<K, V>>,
+}
+
+impl<'a, K, V> RawIter<'a, K, V> {
+    fn new(ctrl: &'a [u8], slots: *mut MaybeUninit<Slot<K, V>>, size: usize) -> Self {
+        RawIter { ctrl: ctrl, slots: slots, index: 0, remaining: size, _marker: PhantomData }
+    }
+}
+
+impl<K, V> Iterator for RawIter<'_, K, V>
+{
+    type Item = *mut Slot<K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        loop {
+            let index = self.index;
+            self.index += 1;
+            if is_full(self.ctrl[index]) {
+                self.remaining -= 1;
+                return Some(unsafe { (*self.slots.add(index)).as_mut_ptr() });
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    raw: RawIter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V>
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let slot = unsafe { &*self.raw.next()? };
+        Some((&slot.key, &slot.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+pub struct IterMut<'a, K, V> {
+    raw: RawIter<'a, K, V>,
+    _marker: PhantomData<&'a mut V>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V>
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let slot = unsafe { &mut *self.raw.next()? };
+        Some((&slot.key, &mut slot.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+#[cfg(test)]
+mod test {
+    use crate::collection::map::raw_flat_hashmap::{Group, DELETED, EMPTY};
+
+    #[test]
+    fn test_group_match() {
+        let ctrl = [0x12, EMPTY, 0x13, DELETED, 0x12, 0x00, EMPTY, 0x7F];
+        let group = Group::load(&ctrl, 0);
+        assert_eq!(group.match_byte(0x12).collect::<Vec<_>>(), vec![0, 4]);
+        assert_eq!(group.match_byte(0x7F).collect::<Vec<_>>(), vec![7]);
+        assert_eq!(group.match_byte(0x00).collect::<Vec<_>>(), vec![5]);
+        assert_eq!(group.match_empty().collect::<Vec<_>>(), vec![1, 6]);
+        assert_eq!(group.match_empty_or_deleted().collect::<Vec<_>>(), vec![1, 3, 6]);
+        assert_eq!(group.match_empty().leading_zeros(), 1);
+        assert_eq!(group.match_empty().trailing_zeros(), 1);
+        assert!(!Group::load(&[0; 8], 0).match_empty().any());
+    }
+}
@@ -1,29 +1,71 @@
 use crate::base::numbers::next_power_of_two;
-use crate::collection::map::list_bucket::{EntryNode, ListBucket, ListBucketIter};
-use crate::collection::map::map::Entry;
-use crate::collection::map::allocator::Allocator;
+use crate::collection::map::list_bucket::{EntryNode, ExtractCursor, ListBucket, ListBucketIter};
+use crate::collection::map::map::{try_filled_vec, Entry, MapStats, OccupiedEntry, RawTable, TryReserveError, VacantEntry};
+use crate::collection::map::allocator::{NodeAllocator, SlabAllocator};
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::{self, NonNull};
 
-const DEFAULT_LOAD_FACTOR:f32 = 0.75;
+pub(crate) const DEFAULT_INITIAL_CAPACITY: usize = 16;
+pub(crate) const DEFAULT_LOAD_FACTOR:f32 = 0.75;
+/// number of old buckets an incremental resize moves on every insert of a new key and every `remove`
+const REHASH_STEP: usize = 4;
 
-pub struct HashTable<K, V>
+pub struct HashTable<K, V, A = SlabAllocator<EntryNode<K, V>>>
+where A: NodeAllocator<EntryNode<K, V>>,
 {
     tab: Vec<ListBucket<K, V>>,
+    // the buckets an incremental resize still moves into `tab`, empty when no resize is running
+    old: Vec<ListBucket<K, V>>,
+    // the buckets of `old` below this index are moved already
+    rehash_index: usize,
+    incremental: bool,
     threshold: usize,
     load_factor:f32,
-    allocator: Allocator<EntryNode<K, V>>
+    allocator: A,
 }
 
-impl<K, V> Default for HashTable<K, V>
+impl<K, V, A> Drop for HashTable<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    fn drop(&mut self) {
+        // the buckets do not own their nodes, give them back to the allocator
+        self.clear();
+    }
+}
+
+impl<K, V, A> Default for HashTable<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>> + Default,
 {
     fn default() -> Self {
-        HashTable::new()
+        HashTable::with_allocator(DEFAULT_INITIAL_CAPACITY, DEFAULT_LOAD_FACTOR, A::default())
+    }
+}
+
+impl<K, V, A> Clone for HashTable<K, V, A>
+where K: Clone, V: Clone, A: NodeAllocator<EntryNode<K, V>> + Default,
+{
+    fn clone(&self) -> Self {
+        let mut allocator = A::default();
+        let tab = self.tab.iter().map(|list| list.clone_with(&mut allocator)).collect();
+        let old = self.old.iter().map(|list| list.clone_with(&mut allocator)).collect();
+        HashTable {
+            tab: tab,
+            old: old,
+            rehash_index: self.rehash_index,
+            incremental: self.incremental,
+            threshold: self.threshold,
+            load_factor: self.load_factor,
+            allocator: allocator,
+        }
     }
 }
 
 impl<K, V> HashTable<K, V>
 {
     pub fn new() -> HashTable<K, V> {
-        Self::with_capacity(16)
+        Self::with_capacity(DEFAULT_INITIAL_CAPACITY)
     }
 
     pub fn with_capacity(init_cap: usize) -> HashTable<K, V> {
@@ -31,36 +73,188 @@ impl<K, V> HashTable<K, V>
     }
 
     pub fn with_capacity_factor(init_cap: usize, load_factor: f32) -> HashTable<K, V> {
+        HashTable::with_allocator(init_cap, load_factor, SlabAllocator::new())
+    }
+}
+
+impl<K, V, A> HashTable<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    pub fn with_allocator(init_cap: usize, load_factor: f32, allocator: A) -> HashTable<K, V, A> {
         let capacity = next_power_of_two(init_cap);
         HashTable {
             tab: Vec::new(),
-            allocator: Allocator::new(),
+            old: Vec::new(),
+            rehash_index: 0,
+            incremental: false,
+            allocator: allocator,
             threshold: capacity,
             load_factor: load_factor,
         }
     }
 
+    pub fn allocator(&self) -> &A {
+        &self.allocator
+    }
+
     // iterator
     pub fn iter(&self) -> Iter<'_, K, V> {
-        Iter { tab: &self.tab, index: 0 , cur: ListBucketIter::empty() }
+        Iter { raw: RawIter::new(&self.old, &self.tab, self.size()) }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut { raw: RawIter::new(&self.old, &self.tab, self.size()), _marker: PhantomData }
+    }
+
+    pub fn is_incremental(&self) -> bool {
+        self.incremental
+    }
+
+    /// switching it off finishes a running resize
+    pub fn set_incremental(&mut self, incremental: bool, hasher: impl Fn(&K) -> u64) {
+        if !incremental {
+            self.finish_rehash(hasher);
+        }
+        self.incremental = incremental;
+    }
+
+    /// true while an incremental resize keeps the old buckets next to the new ones
+    pub fn is_rehashing(&self) -> bool {
+        !self.old.is_empty()
+    }
+
+    /// number of entries the table holds before it grows, 0 until the bucket table is allocated
+    pub fn capacity(&self) -> usize {
+        if self.tab.is_empty() { 0 } else { self.threshold }
+    }
+
+    /**
+    Makes room for one more entry in the bucket table. In incremental mode a full table starts a resize that only
+    allocates the new buckets, every following call moves `REHASH_STEP` old buckets over.
+    */
+    fn try_reserve_one(&mut self, hasher: impl Fn(&K) -> u64) -> Result<(), TryReserveError> {
+        if self.is_rehashing() {
+            self.rehash_step(REHASH_STEP, &hasher);
+        }
+        if self.incremental && !self.tab.is_empty() && self.size() + 1 > self.threshold {
+            // the last resize is not done yet when the table fills up this fast
+            self.finish_rehash(&hasher);
+            let capacity = self.tab.len().checked_mul(2).ok_or(TryReserveError::CapacityOverflow)?;
+            let new_tab = try_filled_vec(capacity, ListBucket::new)?;
+            self.old = mem::replace(&mut self.tab, new_tab);
+            self.rehash_index = 0;
+            self.threshold = (capacity as f32 * self.load_factor) as usize;
+            return Ok(());
+        }
+        self.try_grow(1, hasher)
+    }
+
+    /// move up to `count` buckets of `old` into `tab`
+    fn rehash_step(&mut self, count: usize, hasher: impl Fn(&K) -> u64) {
+        let mask = self.tab.len() - 1;
+        let end = self.rehash_index.saturating_add(count).min(self.old.len());
+        for bucket in &mut self.old[self.rehash_index..end] {
+            let mut node = bucket.take();
+            while !node.is_null() {
+                let ptr = unsafe { &mut (*node) };
+                node = ptr.next;
+                self.tab[hasher(&ptr.key) as usize & mask].push(ptr);
+            }
+        }
+        self.rehash_index = end;
+        if end == self.old.len() {
+            self.old = Vec::new();
+            self.rehash_index = 0;
+        }
+    }
+
+    fn finish_rehash(&mut self, hasher: impl Fn(&K) -> u64) {
+        if self.is_rehashing() {
+            self.rehash_step(usize::MAX, hasher);
+        }
     }
 
     // hasher: impl Fn(&K) -> u64
     pub fn reserve(&mut self, additional: usize, hasher: impl Fn(&K) -> u64) {
-        if !self.tab.is_empty() && self.size() + additional <= self.threshold {
+        if let Err(e) = self.try_reserve(additional, hasher) {
+            e.raise();
+        }
+    }
+
+    /// make room for `additional` more entries in the allocator and the bucket table, no entry is changed on error
+    pub fn try_reserve(&mut self, additional: usize, hasher: impl Fn(&K) -> u64) -> Result<(), TryReserveError> {
+        self.size().checked_add(additional).ok_or(TryReserveError::CapacityOverflow)?;
+        self.allocator.try_reserve(additional)?;
+        return self.try_grow(additional, hasher);
+    }
+
+    /// make room for `additional` more entries in the bucket table only, the nodes are reserved by the caller
+    fn try_grow(&mut self, additional: usize, hasher: impl Fn(&K) -> u64) -> Result<(), TryReserveError> {
+        let size = self.size().checked_add(additional).ok_or(TryReserveError::CapacityOverflow)?;
+        if !self.tab.is_empty() && size <= self.threshold {
+            return Ok(());
+        }
+        let old_capacity = self.tab.len();
+        let capacity = if old_capacity > 0 {
+            old_capacity.checked_mul(2).ok_or(TryReserveError::CapacityOverflow)?
+        } else {
+            self.threshold
+        };
+        let capacity = capacity.max(self.buckets_for(size).ok_or(TryReserveError::CapacityOverflow)?);
+        let new_tab = try_filled_vec(capacity, ListBucket::new)?;
+        self.resize(new_tab, hasher);
+        return Ok(());
+    }
+
+    /// rebuild the bucket table at the smallest power of two that holds the current entries
+    /// and give the unused node memory back to the allocator
+    pub fn shrink_to_fit(&mut self, hasher: impl Fn(&K) -> u64) {
+        self.finish_rehash(&hasher);
+        self.allocator.shrink_to_fit();
+        if self.size() == 0 {
+            self.tab = Vec::new();
+            self.threshold = DEFAULT_INITIAL_CAPACITY;
             return;
         }
-        // resize
-        let old_capacity = if self.tab.is_empty() {0} else {self.tab.len()};
-        let capacity = if old_capacity > 0 {old_capacity * 2} else {self.threshold};
+        let capacity = self.buckets_for(self.size()).expect("the entries fit in the table");
+        if capacity < self.tab.len() {
+            // a failed allocation keeps the current buckets
+            if let Ok(new_tab) = try_filled_vec(capacity, ListBucket::new) {
+                self.resize(new_tab, hasher);
+            }
+        }
+    }
 
-        let mut new_tab: Vec<ListBucket<K, V>> = Vec::with_capacity(capacity);
-        for _ in 0..capacity {
-            new_tab.push(ListBucket::new());
+    pub fn stats(&self) -> MapStats {
+        let mut stats = MapStats::new(self.size(), self.old.len() + self.tab.len());
+        for bucket in self.old.iter().chain(self.tab.iter()) {
+            stats.add_chain(bucket.count());
         }
+        return stats;
+    }
+
+    /// remove every entry, the bucket table keeps its capacity and the node memory goes back to the allocator
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+        // nothing is left to move
+        self.old = Vec::new();
+        self.rehash_index = 0;
+        self.allocator.shrink_to_fit();
+    }
 
-        for i in 0..old_capacity {
-            let mut node = self.tab[i].take();
+    /// `None` if the number of buckets does not fit in `usize`
+    fn buckets_for(&self, size: usize) -> Option<usize> {
+        // the cast saturates, and `usize::MAX` has no next power of two
+        ((size as f64 / self.load_factor as f64).ceil() as usize).checked_next_power_of_two()
+    }
+
+    /// move every entry into the empty buckets of `new_tab`
+    fn resize(&mut self, mut new_tab: Vec<ListBucket<K, V>>, hasher: impl Fn(&K) -> u64) {
+        self.finish_rehash(&hasher);
+        let capacity = new_tab.len();
+
+        for bucket in self.tab.iter_mut() {
+            let mut node = bucket.take();
             while !node.is_null() {
                 let ptr = unsafe { &mut (*node) };
                 node = ptr.next;
@@ -76,65 +270,149 @@ impl<K, V> HashTable<K, V>
     }
 
     pub fn size(&self) -> usize {
-        self.allocator.size
+        self.allocator.size()
+    }
+
+    pub fn drain(&mut self) -> Drain<'_, K, V, A> {
+        Drain { table: self, cursor: DrainCursor::new() }
+    }
+
+    pub fn retain<F>(&mut self, mut f: F)
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        self.extract_if(|k, v| !f(k, v)).for_each(drop);
+    }
+
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, F, A>
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        ExtractIf { table: self, index: 0, cursor: ExtractCursor::empty(), pred: pred }
     }
 
     pub fn foreach<F: FnMut(&K, &mut V)>(&mut self, mut f: F) {
-        if self.tab.is_empty() {
-            return;
+        for bucket in self.old.iter_mut().chain(self.tab.iter_mut()) {
+            bucket.foreach(&mut f);
         }
-        for i in 0..self.tab.len() {
-            self.tab[i].foreach(&mut f);
+    }
+
+    /// the buckets of `old` come first, the drain and extract cursors walk both tables by one index
+    fn bucket_mut(&mut self, index: usize) -> Option<&mut ListBucket<K, V>> {
+        if index < self.old.len() {
+            return Some(&mut self.old[index]);
         }
+        self.tab.get_mut(index - self.old.len())
+    }
+
+    fn old_index(&self, hash: u64) -> usize {
+        hash as usize & (self.old.len() - 1)
     }
 }
 
-impl<K, V> HashTable<K, V>
-where K: Eq,
+impl<K, V, A> HashTable<K, V, A>
+where K: Eq, A: NodeAllocator<EntryNode<K, V>>,
 {
-    pub fn get(&self, hash: u64, key: &K) -> Option<&V> {
-        Some(self.get_node(hash, key)?)
+    /// while a resize is running the key may still be in its old bucket
+    fn find_node<Q>(&self, hash: u64, key: &Q) -> *mut EntryNode<K, V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        if self.tab.is_empty() {
+            return ptr::null_mut();
+        }
+        let mask = self.tab.len() - 1;
+        let ptr = self.tab[hash as usize & mask].find_node(key);
+        if ptr.is_null() && self.is_rehashing() {
+            return self.old[self.old_index(hash)].find_node(key);
+        }
+        return ptr;
     }
 
-    pub fn get_mut(&mut self, hash: u64, key: &K) -> Option<&mut V> {
-        return self.get_node(hash, key);
+    pub fn get<Q>(&self, hash: u64, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let node = unsafe { self.find_node(hash, key).as_ref()? };
+        return Some(&node.value);
     }
 
-    #[inline]
-    fn get_node(&self, hash: u64, key: &K) -> Option<&mut V> {
-        debug_assert!(!self.tab.is_empty(), "map not initialized");
-        let mask = self.tab.len() - 1;
-        let index = hash as usize & mask;
-        self.tab[index].get(key)
+    pub fn get_mut<Q>(&mut self, hash: u64, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let node = unsafe { self.find_node(hash, key).as_mut()? };
+        return Some(&mut node.value);
     }
 
     pub fn put(&mut self, hash: u64, key: K, value: V, hasher: impl Fn(&K) -> u64) -> Option<V> {
-        self.reserve(1, hasher);
+        return self.try_put(hash, key, value, hasher).unwrap_or_else(|e| e.raise());
+    }
+
+    /// on error no entry is changed and `key` and `value` are dropped
+    pub fn try_put(&mut self, hash: u64, key: K, value: V, hasher: impl Fn(&K) -> u64) -> Result<Option<V>, TryReserveError> {
+        // replacing a value neither grows the table nor moves old buckets
+        if let Some(old) = self.get_mut(hash, &key) {
+            return Ok(Some(mem::replace(old, value)));
+        }
+        // only a new entry needs a node
+        self.try_reserve_one(hasher)?;
+        self.allocator.try_reserve(1)?;
+        self.add_node(hash, key, value);
+        return Ok(None);
+    }
+
+    pub fn remove<Q>(&mut self, hash: u64, key: &Q, hasher: impl Fn(&K) -> u64) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        debug_assert!(!self.tab.is_empty(), "map not initialized");
+        if self.is_rehashing() {
+            self.rehash_step(REHASH_STEP, hasher);
+        }
         let mask = self.tab.len() - 1;
         let index = hash as usize & mask;
-        return self.tab[index].write(key, value, &mut self.allocator).1;
+        let value = self.tab[index].remove(key, &mut self.allocator);
+        if value.is_none() && self.is_rehashing() {
+            let index = self.old_index(hash);
+            return self.old[index].remove(key, &mut self.allocator);
+        }
+        return value;
     }
 
-    pub fn remove(&mut self, hash: u64, key: &K) -> Option<V> {
-        debug_assert!(!self.tab.is_empty(), "map not initialized");
+    pub fn entry(&mut self, hash: u64, key: K, hasher: impl Fn(&K) -> u64) -> Entry<'_, K, V, HashTable<K, V, A>> {
+        let ptr = self.find_node(hash, &key);
+        if ptr.is_null() {
+            // only a vacant entry may add an entry
+            if let Err(e) = self.try_reserve_one(hasher) {
+                e.raise();
+            }
+            return Entry::Vacant(VacantEntry::new(key, hash, self));
+        }
+        let node = unsafe { &mut *ptr };
+        return Entry::Occupied(unsafe { OccupiedEntry::new(&node.key, &mut node.value, hash, self) });
+    }
+}
+
+impl<K, V, A> RawTable<K, V> for HashTable<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    fn add_node(&mut self, hash: u64, key: K, value: V) -> (&K, &mut V) {
         let mask = self.tab.len() - 1;
         let index = hash as usize & mask;
-        return self.tab[index].remove(key, &mut self.allocator);
+        return self.tab[index].add_node(key, value, &mut self.allocator);
     }
 
-    pub fn entry(&mut self, hash: u64, key: K, hasher: impl Fn(&K) -> u64) -> Entry<'_, K, V, ListBucket<K, V>> {
-        self.reserve(1, hasher);
+    unsafe fn remove_entry(&mut self, hash: u64, key: *const K) -> (K, V) {
         let mask = self.tab.len() - 1;
         let index = hash as usize & mask;
-        let bucket = &mut self.tab[index];
-        return bucket.entry(hash, key, &mut self.allocator);
+        if let Some(entry) = self.tab[index].unlink(key, &mut self.allocator) {
+            return entry;
+        }
+        let index = self.old_index(hash);
+        return self.old[index].unlink(key, &mut self.allocator).expect("entry is not in the table");
     }
 }
 
 // iterator
-impl<'a, K, V> IntoIterator for &'a HashTable<K, V>
+impl<'a, K, V, A> IntoIterator for &'a HashTable<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
 {
-    type Item = (&'a K, &'a mut V);
+    type Item = (&'a K, &'a V);
     type IntoIter = Iter<'a, K, V>;
 
     fn into_iter(self) -> Iter<'a, K, V> {
@@ -142,28 +420,232 @@ impl<'a, K, V> IntoIterator for &'a HashTable<K, V>
     }
 }
 
-pub struct Iter<'a, K, V> {
-    tab: &'a Vec<ListBucket<K, V>>,
+impl<'a, K, V, A> IntoIterator for &'a mut HashTable<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        self.iter_mut()
+    }
+}
+
+impl<K, V, A> IntoIterator for HashTable<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, A>;
+
+    fn into_iter(self) -> IntoIter<K, V, A> {
+        IntoIter { table: self, cursor: DrainCursor::new() }
+    }
+}
+
+/**
+Takes the chain of one bucket at a time and frees its nodes through the allocator,
+so `size()` always counts the entries that are not yielded yet. `index` counts the old buckets first.
+*/
+struct DrainCursor<K, V> {
     index: usize,
-    cur: ListBucketIter<K, V>
+    cur: *mut EntryNode<K, V>,
 }
 
-impl<'a, K, V> Iterator for Iter<'a, K, V>
+impl<K, V> DrainCursor<K, V> {
+    fn new() -> Self {
+        DrainCursor { index: 0, cur: std::ptr::null_mut() }
+    }
+
+    fn next<A: NodeAllocator<EntryNode<K, V>>>(&mut self, table: &mut HashTable<K, V, A>) -> Option<(K, V)> {
+        while self.cur.is_null() {
+            self.cur = table.bucket_mut(self.index)?.take();
+            self.index += 1;
+        }
+        let node = unsafe { table.allocator.free(NonNull::new_unchecked(self.cur)) };
+        self.cur = node.next;
+        Some((node.key, node.value))
+    }
+}
+
+pub struct IntoIter<K, V, A = SlabAllocator<EntryNode<K, V>>>
+where A: NodeAllocator<EntryNode<K, V>>,
 {
-    type Item = (&'a K, &'a mut V);
+    table: HashTable<K, V, A>,
+    cursor: DrainCursor<K, V>,
+}
+
+impl<K, V, A> Iterator for IntoIter<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cursor.next(&mut self.table)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.table.size(), Some(self.table.size()))
+    }
+}
+
+impl<K, V, A> ExactSizeIterator for IntoIter<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{}
+
+impl<K, V, A> Drop for IntoIter<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    fn drop(&mut self) {
+        while self.cursor.next(&mut self.table).is_some() {}
+    }
+}
+
+/// removes the remaining entries when dropped
+pub struct Drain<'a, K, V, A = SlabAllocator<EntryNode<K, V>>>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    table: &'a mut HashTable<K, V, A>,
+    cursor: DrainCursor<K, V>,
+}
+
+impl<K, V, A> Iterator for Drain<'_, K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cursor.next(self.table)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.table.size(), Some(self.table.size()))
+    }
+}
+
+impl<K, V, A> ExactSizeIterator for Drain<'_, K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{}
+
+impl<K, V, A> Drop for Drain<'_, K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    fn drop(&mut self) {
+        while self.cursor.next(self.table).is_some() {}
+    }
+}
+
+/// lazily removes the entries accepted by `pred`, the rest stay in the map once it is dropped
+pub struct ExtractIf<'a, K, V, F, A = SlabAllocator<EntryNode<K, V>>>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    table: &'a mut HashTable<K, V, A>,
+    index: usize,
+    cursor: ExtractCursor<K, V>,
+    pred: F,
+}
+
+impl<K, V, F, A> Iterator for ExtractIf<'_, K, V, F, A>
+where F: FnMut(&K, &mut V) -> bool, A: NodeAllocator<EntryNode<K, V>>,
+{
+    type Item = (K, V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let tab = self.tab;
         loop {
-            if let Some(mut ptr) = self.cur.next() {
-                let node = unsafe {  ptr.as_mut() };
-                return Some((&node.key, &mut node.value))
+            if let Some(item) = self.cursor.next(&mut self.pred, &mut self.table.allocator) {
+                return Some(item);
             }
-            if self.index >= tab.len() {
-                return None;
+            let bucket = self.table.bucket_mut(self.index)?;
+            self.cursor = ExtractCursor::new(bucket);
+            self.index += 1;
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.table.size()))
+    }
+}
+
+/// walks the buckets in order and yields every node, shared by `Iter` and `IterMut`
+struct RawIter<'a, K, V> {
+    tab: &'a [ListBucket<K, V>],
+    // the new buckets while `tab` holds the old ones of a running resize
+    next_tab: &'a [ListBucket<K, V>],
+    index: usize,
+    cur: ListBucketIter<K, V>,
+    remaining: usize,
+}
+
+impl<'a, K, V> RawIter<'a, K, V> {
+    fn new(old: &'a [ListBucket<K, V>], tab: &'a [ListBucket<K, V>], size: usize) -> Self {
+        RawIter { tab: old, next_tab: tab, index: 0, cur: ListBucketIter::empty(), remaining: size }
+    }
+}
+
+impl<K, V> Iterator for RawIter<'_, K, V>
+{
+    type Item = NonNull<EntryNode<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(ptr) = self.cur.next() {
+                self.remaining -= 1;
+                return Some(ptr);
+            }
+            if self.index >= self.tab.len() {
+                if self.next_tab.is_empty() {
+                    return None;
+                }
+                self.tab = mem::take(&mut self.next_tab);
+                self.index = 0;
+                continue;
             }
             self.cur = self.tab[self.index].iter();
-            self.index +=1;
+            self.index += 1;
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
 }
+
+pub struct Iter<'a, K, V> {
+    raw: RawIter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V>
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = unsafe { self.raw.next()?.as_ref() };
+        Some((&node.key, &node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+pub struct IterMut<'a, K, V> {
+    raw: RawIter<'a, K, V>,
+    _marker: PhantomData<&'a mut V>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V>
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = unsafe { self.raw.next()?.as_mut() };
+        Some((&node.key, &mut node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
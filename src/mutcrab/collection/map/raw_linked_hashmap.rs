@@ -0,0 +1,656 @@
+use crate::base::numbers::next_power_of_two;
+use crate::collection::map::allocator::{NodeAllocator, SlabAllocator};
+use crate::collection::map::list_bucket::{EntryNode, ListBucket};
+use crate::collection::map::map::{try_filled_vec, Entry, OccupiedEntry, RawTable, TryReserveError, VacantEntry};
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::ptr;
+
+const DEFAULT_INITIAL_CAPACITY: usize = 16;
+const DEFAULT_LOAD_FACTOR:f32 = 0.75;
+
+/**
+The value of a bucket node. It also keeps the hash, so a node taken from the order list can be
+found in its bucket again, and the `before`/`after` links of the order list. The links are only
+changed through `&mut` of the table.
+*/
+pub struct Linked<K, V> {
+    value: V,
+    hash: u64,
+    before: *mut Node<K, V>,
+    after: *mut Node<K, V>,
+}
+
+// the links point into the table that owns the node
+unsafe impl<K: Send, V: Send> Send for Linked<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for Linked<K, V> {}
+
+/// the node a `LinkedHashMap` takes from its allocator
+pub type LinkedNode<K, V> = EntryNode<K, Linked<K, V>>;
+
+type Node<K, V> = LinkedNode<K, V>;
+
+impl<K, V> Linked<K, V> {
+    fn new(hash: u64, value: V) -> Self {
+        Linked { value: value, hash: hash, before: ptr::null_mut(), after: ptr::null_mut() }
+    }
+}
+
+/**
+A `HashTable` whose nodes are also threaded on a doubly linked list, from the oldest entry at
+`head` to the newest at `tail`. In access order a write or a `get_refresh` moves the entry to
+the tail, the shared `get` leaves the order alone.
+*/
+pub struct LinkedHashTable<K, V, A = SlabAllocator<Node<K, V>>>
+where A: NodeAllocator<Node<K, V>>,
+{
+    tab: Vec<ListBucket<K, Linked<K, V>>>,
+    head: *mut Node<K, V>,
+    tail: *mut Node<K, V>,
+    access_order: bool,
+    threshold: usize,
+    load_factor:f32,
+    allocator: A
+}
+
+// the table owns its nodes and only relinks them through `&mut self`
+unsafe impl<K: Send, V: Send, A: Send + NodeAllocator<Node<K, V>>> Send for LinkedHashTable<K, V, A> {}
+unsafe impl<K: Sync, V: Sync, A: Sync + NodeAllocator<Node<K, V>>> Sync for LinkedHashTable<K, V, A> {}
+
+impl<K, V, A> Drop for LinkedHashTable<K, V, A>
+where A: NodeAllocator<Node<K, V>>,
+{
+    fn drop(&mut self) {
+        // the buckets do not own their nodes, give them back to the allocator
+        self.clear();
+    }
+}
+
+impl<K, V, A> Default for LinkedHashTable<K, V, A>
+where A: NodeAllocator<Node<K, V>> + Default,
+{
+    fn default() -> Self {
+        LinkedHashTable::with_allocator(DEFAULT_INITIAL_CAPACITY, DEFAULT_LOAD_FACTOR, false, A::default())
+    }
+}
+
+impl<K, V, A> Clone for LinkedHashTable<K, V, A>
+where K: Clone, V: Clone, A: NodeAllocator<Node<K, V>> + Default,
+{
+    fn clone(&self) -> Self {
+        let mut table = LinkedHashTable {
+            tab: Vec::with_capacity(self.tab.len()),
+            head: ptr::null_mut(),
+            tail: ptr::null_mut(),
+            access_order: self.access_order,
+            threshold: self.threshold,
+            load_factor: self.load_factor,
+            allocator: A::default(),
+        };
+        for _ in 0..self.tab.len() {
+            table.tab.push(ListBucket::new());
+        }
+        for (node, hash) in NodeIter::new(self) {
+            let (key, value) = unsafe { (&(*node).key, &(*node).value.value) };
+            table.add_node(hash, key.clone(), value.clone());
+        }
+        table
+    }
+}
+
+impl<K, V> LinkedHashTable<K, V>
+{
+    pub fn new() -> LinkedHashTable<K, V> {
+        Self::with_capacity(DEFAULT_INITIAL_CAPACITY)
+    }
+
+    pub fn with_capacity(init_cap: usize) -> LinkedHashTable<K, V> {
+        Self::with_capacity_factor_order(init_cap, DEFAULT_LOAD_FACTOR, false)
+    }
+
+    pub fn with_capacity_factor_order(init_cap: usize, load_factor: f32, access_order: bool) -> LinkedHashTable<K, V> {
+        LinkedHashTable::with_allocator(init_cap, load_factor, access_order, SlabAllocator::new())
+    }
+}
+
+impl<K, V, A> LinkedHashTable<K, V, A>
+where A: NodeAllocator<Node<K, V>>,
+{
+    pub fn with_allocator(init_cap: usize, load_factor: f32, access_order: bool, allocator: A) -> LinkedHashTable<K, V, A> {
+        let capacity = next_power_of_two(init_cap);
+        LinkedHashTable {
+            tab: Vec::new(),
+            head: ptr::null_mut(),
+            tail: ptr::null_mut(),
+            access_order: access_order,
+            allocator: allocator,
+            threshold: capacity,
+            load_factor: load_factor,
+        }
+    }
+
+    pub fn allocator(&self) -> &A {
+        &self.allocator
+    }
+
+    pub fn size(&self) -> usize {
+        self.allocator.size()
+    }
+
+    pub fn is_access_order(&self) -> bool {
+        self.access_order
+    }
+
+    // iterator
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { head: self.head, tail: self.tail, remaining: self.size(), _marker: PhantomData }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut { head: self.head, tail: self.tail, remaining: self.size(), _marker: PhantomData }
+    }
+
+    /// number of entries the table holds before it grows, 0 until the bucket table is allocated
+    pub fn capacity(&self) -> usize {
+        if self.tab.is_empty() { 0 } else { self.threshold }
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        if let Err(e) = self.try_reserve(additional) {
+            e.raise();
+        }
+    }
+
+    /// make room for `additional` more entries in the allocator and the bucket table, no entry is changed on error
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.size().checked_add(additional).ok_or(TryReserveError::CapacityOverflow)?;
+        self.allocator.try_reserve(additional)?;
+        return self.try_grow(additional);
+    }
+
+    /// make room for `additional` more entries in the bucket table only, the nodes are reserved by the caller
+    fn try_grow(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        let size = self.size().checked_add(additional).ok_or(TryReserveError::CapacityOverflow)?;
+        if !self.tab.is_empty() && size <= self.threshold {
+            return Ok(());
+        }
+        let old_capacity = self.tab.len();
+        let capacity = if old_capacity > 0 {
+            old_capacity.checked_mul(2).ok_or(TryReserveError::CapacityOverflow)?
+        } else {
+            self.threshold
+        };
+        let capacity = capacity.max(self.buckets_for(size).ok_or(TryReserveError::CapacityOverflow)?);
+        let new_tab = try_filled_vec(capacity, ListBucket::new)?;
+        self.resize(new_tab);
+        return Ok(());
+    }
+
+    /// rebuild the bucket table at the smallest power of two that holds the current entries
+    /// and give the unused node memory back to the allocator
+    pub fn shrink_to_fit(&mut self) {
+        self.allocator.shrink_to_fit();
+        if self.size() == 0 {
+            self.tab = Vec::new();
+            self.threshold = DEFAULT_INITIAL_CAPACITY;
+            return;
+        }
+        let capacity = self.buckets_for(self.size()).expect("the entries fit in the table");
+        if capacity < self.tab.len() {
+            // a failed allocation keeps the current buckets
+            if let Ok(new_tab) = try_filled_vec(capacity, ListBucket::new) {
+                self.resize(new_tab);
+            }
+        }
+    }
+
+    /// remove every entry, the bucket table keeps its capacity and the node memory goes back to the allocator
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+        self.allocator.shrink_to_fit();
+    }
+
+    /// `None` if the number of buckets does not fit in `usize`
+    fn buckets_for(&self, size: usize) -> Option<usize> {
+        // the cast saturates, and `usize::MAX` has no next power of two
+        ((size as f64 / self.load_factor as f64).ceil() as usize).checked_next_power_of_two()
+    }
+
+    /// move every entry into the empty buckets of `new_tab`, the nodes keep their hash so no hasher is needed
+    fn resize(&mut self, mut new_tab: Vec<ListBucket<K, Linked<K, V>>>) {
+        let capacity = new_tab.len();
+
+        for bucket in self.tab.iter_mut() {
+            let mut node = bucket.take();
+            while !node.is_null() {
+                let ptr = unsafe { &mut (*node) };
+                node = ptr.next;
+                let index = ptr.value.hash as usize & (capacity - 1);
+                new_tab[index].push(ptr);
+            }
+        }
+
+        self.tab = new_tab;
+        self.threshold = (capacity as f32 * self.load_factor) as usize;
+    }
+
+    pub fn front(&self) -> Option<(&K, &V)> {
+        let node = unsafe { self.head.as_ref()? };
+        Some((&node.key, &node.value.value))
+    }
+
+    pub fn back(&self) -> Option<(&K, &V)> {
+        let node = unsafe { self.tail.as_ref()? };
+        Some((&node.key, &node.value.value))
+    }
+
+    /// remove the oldest entry, in access order this is the least recently used one
+    pub fn pop_front(&mut self) -> Option<(K, V)> {
+        let node = self.head;
+        if node.is_null() {
+            return None;
+        }
+        Some(unsafe { self.unlink_node(node) })
+    }
+
+    pub fn pop_back(&mut self) -> Option<(K, V)> {
+        let node = self.tail;
+        if node.is_null() {
+            return None;
+        }
+        Some(unsafe { self.unlink_node(node) })
+    }
+
+    /// remove every entry in order, the remaining ones are removed when the `Drain` is dropped
+    pub fn drain(&mut self) -> Drain<'_, K, V, A> {
+        Drain { table: self }
+    }
+
+    pub fn retain<F>(&mut self, mut f: F)
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        let mut ptr = self.head;
+        while !ptr.is_null() {
+            let node = unsafe { &mut *ptr };
+            let next = node.value.after;
+            if !f(&node.key, &mut node.value.value) {
+                unsafe { self.unlink_node(ptr) };
+            }
+            ptr = next;
+        }
+    }
+
+    pub fn foreach<F: FnMut(&K, &mut V)>(&mut self, mut f: F) {
+        for (key, value) in self.iter_mut() {
+            f(key, value);
+        }
+    }
+
+    fn link_last(&mut self, node: *mut Node<K, V>) {
+        let last = self.tail;
+        unsafe {
+            (*node).value.before = last;
+            (*node).value.after = ptr::null_mut();
+            match last.as_mut() {
+                Some(last) => last.value.after = node,
+                None => self.head = node,
+            }
+        }
+        self.tail = node;
+    }
+
+    fn unlink_order(&mut self, node: *mut Node<K, V>) {
+        unsafe {
+            let before = (*node).value.before;
+            let after = (*node).value.after;
+            match before.as_mut() {
+                Some(before) => before.value.after = after,
+                None => self.head = after,
+            }
+            match after.as_mut() {
+                Some(after) => after.value.before = before,
+                None => self.tail = before,
+            }
+        }
+    }
+
+    /// in access order move the node that was just read or written to the tail
+    fn after_access(&mut self, node: *mut Node<K, V>) {
+        if self.access_order && self.tail != node {
+            self.unlink_order(node);
+            self.link_last(node);
+        }
+    }
+
+    /// `node` must belong to this table
+    unsafe fn unlink_node(&mut self, node: *mut Node<K, V>) -> (K, V) {
+        self.unlink_order(node);
+        let mask = self.tab.len() - 1;
+        let index = unsafe { (*node).value.hash } as usize & mask;
+        let (key, linked) = self.tab[index].unlink(unsafe { &(*node).key }, &mut self.allocator)
+            .expect("entry is not in the table");
+        (key, linked.value)
+    }
+
+    fn find<Q>(&self, hash: u64, key: &Q) -> *mut Node<K, V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        if self.tab.is_empty() {
+            return ptr::null_mut();
+        }
+        let mask = self.tab.len() - 1;
+        let index = hash as usize & mask;
+        self.tab[index].find_node(key)
+    }
+}
+
+impl<K, V, A> LinkedHashTable<K, V, A>
+where K: Eq, A: NodeAllocator<Node<K, V>>,
+{
+    /// the order is left alone, see `get_refresh`
+    pub fn get<Q>(&self, hash: u64, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let node = unsafe { self.find(hash, key).as_ref()? };
+        Some(&node.value.value)
+    }
+
+    /// like `get` but an access ordered table moves the entry to the back
+    pub fn get_refresh<Q>(&mut self, hash: u64, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let node = self.find(hash, key);
+        if node.is_null() {
+            return None;
+        }
+        self.after_access(node);
+        Some(unsafe { &(*node).value.value })
+    }
+
+    pub fn get_mut<Q>(&mut self, hash: u64, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let node = self.find(hash, key);
+        if node.is_null() {
+            return None;
+        }
+        self.after_access(node);
+        Some(unsafe { &mut (*node).value.value })
+    }
+
+    /// a new key goes to the back, an existing key keeps its place unless the table is access ordered
+    pub fn put(&mut self, hash: u64, key: K, value: V) -> Option<V> {
+        self.try_put(hash, key, value).unwrap_or_else(|e| e.raise())
+    }
+
+    /// on error no entry is changed and `key` and `value` are dropped
+    pub fn try_put(&mut self, hash: u64, key: K, value: V) -> Result<Option<V>, TryReserveError> {
+        let node = self.find(hash, &key);
+        if !node.is_null() {
+            self.after_access(node);
+            return Ok(Some(std::mem::replace(unsafe { &mut (*node).value.value }, value)));
+        }
+        // only a new entry needs a node
+        self.try_grow(1)?;
+        self.allocator.try_reserve(1)?;
+        self.add_node(hash, key, value);
+        return Ok(None);
+    }
+
+    pub fn remove<Q>(&mut self, hash: u64, key: &Q) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let node = self.find(hash, key);
+        if node.is_null() {
+            return None;
+        }
+        Some(unsafe { self.unlink_node(node) }.1)
+    }
+
+    pub fn entry(&mut self, hash: u64, key: K) -> Entry<'_, K, V, LinkedHashTable<K, V, A>> {
+        let ptr = self.find(hash, &key);
+        if ptr.is_null() {
+            // only a vacant entry may add an entry
+            self.reserve(1);
+            return Entry::Vacant(VacantEntry::new(key, hash, self));
+        }
+        self.after_access(ptr);
+        let node = unsafe { &mut *ptr };
+        return Entry::Occupied(unsafe { OccupiedEntry::new(&node.key, &mut node.value.value, hash, self) });
+    }
+}
+
+impl<K, V, A> RawTable<K, V> for LinkedHashTable<K, V, A>
+where A: NodeAllocator<Node<K, V>>,
+{
+    fn add_node(&mut self, hash: u64, key: K, value: V) -> (&K, &mut V) {
+        let mask = self.tab.len() - 1;
+        let index = hash as usize & mask;
+        let node = self.tab[index].push_new(key, Linked::new(hash, value), &mut self.allocator);
+        self.link_last(node);
+        let node = unsafe { &mut *node };
+        return (&node.key, &mut node.value.value);
+    }
+
+    unsafe fn remove_entry(&mut self, hash: u64, key: *const K) -> (K, V) {
+        let mask = self.tab.len() - 1;
+        let index = hash as usize & mask;
+        let node = self.tab[index].iter()
+            .find(|node| unsafe { ptr::eq(&node.as_ref().key, key) })
+            .expect("entry is not in the table");
+        return unsafe { self.unlink_node(node.as_ptr()) };
+    }
+}
+
+// iterator
+impl<'a, K, V, A> IntoIterator for &'a LinkedHashTable<K, V, A>
+where A: NodeAllocator<Node<K, V>>,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, A> IntoIterator for &'a mut LinkedHashTable<K, V, A>
+where A: NodeAllocator<Node<K, V>>,
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        self.iter_mut()
+    }
+}
+
+impl<K, V, A> IntoIterator for LinkedHashTable<K, V, A>
+where A: NodeAllocator<Node<K, V>>,
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, A>;
+
+    fn into_iter(self) -> IntoIter<K, V, A> {
+        IntoIter { table: self }
+    }
+}
+
+/// walks the order list with the hash of every node, used to rebuild a table
+struct NodeIter<K, V> {
+    cur: *mut Node<K, V>,
+}
+
+impl<K, V> NodeIter<K, V> {
+    fn new<A: NodeAllocator<Node<K, V>>>(table: &LinkedHashTable<K, V, A>) -> Self {
+        NodeIter { cur: table.head }
+    }
+}
+
+impl<K, V> Iterator for NodeIter<K, V> {
+    type Item = (*mut Node<K, V>, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = unsafe { self.cur.as_ref()? };
+        let ptr = self.cur;
+        self.cur = node.value.after;
+        Some((ptr, node.value.hash))
+    }
+}
+
+/**
+Iterates from the oldest to the newest entry. It counts the entries it still has to yield, so
+the front and back walks stop where they meet.
+*/
+pub struct Iter<'a, K, V> {
+    head: *mut Node<K, V>,
+    tail: *mut Node<K, V>,
+    remaining: usize,
+    _marker: PhantomData<&'a Node<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V>
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = unsafe { &*self.head };
+        self.head = node.value.after;
+        self.remaining -= 1;
+        Some((&node.key, &node.value.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = unsafe { &*self.tail };
+        self.tail = node.value.before;
+        self.remaining -= 1;
+        Some((&node.key, &node.value.value))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+pub struct IterMut<'a, K, V> {
+    head: *mut Node<K, V>,
+    tail: *mut Node<K, V>,
+    remaining: usize,
+    _marker: PhantomData<&'a mut Node<K, V>>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V>
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = unsafe { &mut *self.head };
+        self.head = node.value.after;
+        self.remaining -= 1;
+        Some((&node.key, &mut node.value.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IterMut<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = unsafe { &mut *self.tail };
+        self.tail = node.value.before;
+        self.remaining -= 1;
+        Some((&node.key, &mut node.value.value))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+pub struct IntoIter<K, V, A = SlabAllocator<Node<K, V>>>
+where A: NodeAllocator<Node<K, V>>,
+{
+    table: LinkedHashTable<K, V, A>,
+}
+
+impl<K, V, A> Iterator for IntoIter<K, V, A>
+where A: NodeAllocator<Node<K, V>>,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.table.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.table.size(), Some(self.table.size()))
+    }
+}
+
+impl<K, V, A> DoubleEndedIterator for IntoIter<K, V, A>
+where A: NodeAllocator<Node<K, V>>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.table.pop_back()
+    }
+}
+
+impl<K, V, A> ExactSizeIterator for IntoIter<K, V, A>
+where A: NodeAllocator<Node<K, V>>,
+{}
+
+/// removes the remaining entries when dropped
+pub struct Drain<'a, K, V, A = SlabAllocator<Node<K, V>>>
+where A: NodeAllocator<Node<K, V>>,
+{
+    table: &'a mut LinkedHashTable<K, V, A>,
+}
+
+impl<K, V, A> Iterator for Drain<'_, K, V, A>
+where A: NodeAllocator<Node<K, V>>,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.table.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.table.size(), Some(self.table.size()))
+    }
+}
+
+impl<K, V, A> DoubleEndedIterator for Drain<'_, K, V, A>
+where A: NodeAllocator<Node<K, V>>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.table.pop_back()
+    }
+}
+
+impl<K, V, A> ExactSizeIterator for Drain<'_, K, V, A>
+where A: NodeAllocator<Node<K, V>>,
+{}
+
+impl<K, V, A> Drop for Drain<'_, K, V, A>
+where A: NodeAllocator<Node<K, V>>,
+{
+    fn drop(&mut self) {
+        while self.table.pop_front().is_some() {}
+    }
+}
@@ -1,22 +1,68 @@
 use crate::base::numbers::next_power_of_two;
-use crate::collection::map::allocator::Allocator;
-use crate::collection::map::list_bucket::{EntryNode, ListBucket};
-use crate::collection::map::map::Entry;
-use crate::collection::map::map_bucket::{Bucket, BucketIter};
+use crate::collection::map::allocator::{NodeAllocator, SlabAllocator};
+use crate::collection::map::list_bucket::EntryNode;
+use crate::collection::map::map::{try_filled_vec, Entry, MapStats, OccupiedEntry, RawTable, TryReserveError, VacantEntry};
+use crate::collection::map::map_bucket::{Bucket, BucketCursor, BucketIter};
+use crate::collection::map::tree_bucket::KeyOrder;
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::{self, NonNull};
 
-const DEFAULT_LOAD_FACTOR: f32 = 0.75;
+pub(crate) const DEFAULT_INITIAL_CAPACITY: usize = 16;
+pub(crate) const DEFAULT_LOAD_FACTOR: f32 = 0.75;
+/// number of old buckets an incremental resize moves on every insert of a new key and every `remove`
+const REHASH_STEP: usize = 4;
 
 #[derive(Debug)]
-pub struct RBTreeHashMap<K, V> {
-    allocator: Allocator<EntryNode<K, V>>,
+pub struct RBTreeHashMap<K, V, A = SlabAllocator<EntryNode<K, V>>>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    allocator: A,
     pub tab: Vec<Bucket<K, V>>,
+    // the buckets an incremental resize still moves into `tab`, empty when no resize is running
+    old: Vec<Bucket<K, V>>,
+    // the buckets of `old` below this index are moved already
+    rehash_index: usize,
+    incremental: bool,
+    // orders the keys of one hash inside the tree buckets, only set when the keys are `Ord`
+    key_order: Option<KeyOrder<K>>,
     threshold: usize,
     load_factor: f32,
 }
 
+impl<K, V, A> Drop for RBTreeHashMap<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    fn drop(&mut self) {
+        // the buckets do not own their nodes, give them back to the allocator
+        self.clear();
+    }
+}
+
+impl<K, V, A> Clone for RBTreeHashMap<K, V, A>
+where K: Clone, V: Clone, A: NodeAllocator<EntryNode<K, V>> + Default,
+{
+    fn clone(&self) -> Self {
+        let mut allocator = A::default();
+        let tab = self.tab.iter().map(|bucket| bucket.clone_with(&mut allocator)).collect();
+        let old = self.old.iter().map(|bucket| bucket.clone_with(&mut allocator)).collect();
+        RBTreeHashMap {
+            allocator: allocator,
+            tab: tab,
+            old: old,
+            rehash_index: self.rehash_index,
+            incremental: self.incremental,
+            key_order: self.key_order,
+            threshold: self.threshold,
+            load_factor: self.load_factor,
+        }
+    }
+}
+
 impl<K, V> RBTreeHashMap<K, V> {
     pub fn new() -> RBTreeHashMap<K, V> {
-        Self::with_capacity(16)
+        Self::with_capacity(DEFAULT_INITIAL_CAPACITY)
     }
 
     pub fn with_capacity(init_cap: usize) -> RBTreeHashMap<K, V> {
@@ -24,129 +70,641 @@ impl<K, V> RBTreeHashMap<K, V> {
     }
 
     pub fn with_capacity_factor(init_cap: usize, load_factor: f32) -> RBTreeHashMap<K, V> {
+        RBTreeHashMap::with_allocator(init_cap, load_factor, SlabAllocator::new())
+    }
+}
+
+impl<K, V, A> Default for RBTreeHashMap<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>> + Default,
+{
+    fn default() -> Self {
+        RBTreeHashMap::with_allocator(DEFAULT_INITIAL_CAPACITY, DEFAULT_LOAD_FACTOR, A::default())
+    }
+}
+
+impl<K, V, A> RBTreeHashMap<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    pub fn with_allocator(init_cap: usize, load_factor: f32, allocator: A) -> RBTreeHashMap<K, V, A> {
         let capacity = next_power_of_two(init_cap);
         RBTreeHashMap {
-            allocator: Allocator::new(),
+            allocator: allocator,
             tab: Vec::new(),
+            old: Vec::new(),
+            rehash_index: 0,
+            incremental: false,
+            key_order: None,
             threshold: capacity,
             load_factor: load_factor,
         }
     }
 
+    pub fn allocator(&self) -> &A {
+        &self.allocator
+    }
+
+    /// number of entries the table holds before it grows, 0 until the bucket table is allocated
+    pub fn capacity(&self) -> usize {
+        if self.tab.is_empty() { 0 } else { self.threshold }
+    }
+
+    pub fn is_incremental(&self) -> bool {
+        self.incremental
+    }
+
+    /// switching it off finishes a running resize
+    pub fn set_incremental(&mut self, incremental: bool, hasher: impl Fn(&K) -> u64) {
+        if !incremental {
+            self.finish_rehash(hasher);
+        }
+        self.incremental = incremental;
+    }
+
+    pub fn is_key_ordered(&self) -> bool {
+        self.key_order.is_some()
+    }
+
+    /// the tree buckets sort the keys of one hash by `order`, or keep them in one list for `None`
+    pub fn set_key_order(&mut self, order: Option<KeyOrder<K>>) {
+        self.key_order = order;
+        for bucket in self.old.iter_mut().chain(self.tab.iter_mut()) {
+            bucket.set_key_order(order);
+        }
+    }
+
+    /// true while an incremental resize keeps the old buckets next to the new ones
+    pub fn is_rehashing(&self) -> bool {
+        !self.old.is_empty()
+    }
+
+    /**
+    Makes room for one more entry in the bucket table. In incremental mode a full table starts a resize that only
+    allocates the new buckets, every following call moves `REHASH_STEP` old buckets over.
+    */
+    fn try_reserve_one(&mut self, hasher: impl Fn(&K) -> u64) -> Result<(), TryReserveError> {
+        if self.is_rehashing() {
+            self.rehash_step(REHASH_STEP, &hasher);
+        }
+        if self.incremental && !self.tab.is_empty() && self.size() + 1 > self.threshold {
+            // the last resize is not done yet when the table fills up this fast
+            self.finish_rehash(&hasher);
+            let capacity = self.tab.len().checked_mul(2).ok_or(TryReserveError::CapacityOverflow)?;
+            let new_tab = try_filled_vec(capacity, Bucket::new_list)?;
+            self.old = mem::replace(&mut self.tab, new_tab);
+            self.rehash_index = 0;
+            self.threshold = (capacity as f32 * self.load_factor) as usize;
+            return Ok(());
+        }
+        self.try_grow(1, hasher)
+    }
+
+    /// move up to `count` buckets of `old` into `tab`
+    fn rehash_step(&mut self, count: usize, hasher: impl Fn(&K) -> u64) {
+        let end = self.rehash_index.saturating_add(count).min(self.old.len());
+        for bucket in &mut self.old[self.rehash_index..end] {
+            bucket.move_into(&mut self.tab, &hasher, self.key_order);
+        }
+        self.rehash_index = end;
+        if end == self.old.len() {
+            self.old = Vec::new();
+            self.rehash_index = 0;
+        }
+    }
+
+    fn finish_rehash(&mut self, hasher: impl Fn(&K) -> u64) {
+        if self.is_rehashing() {
+            self.rehash_step(usize::MAX, hasher);
+        }
+    }
+
     // hasher: impl Fn(&K) -> u64
-    fn reserve(&mut self, additional: usize, hasher: impl Fn(&K) -> u64) {
-        if !self.tab.is_empty() && self.size() + additional <= self.threshold {
-            return;
+    pub fn reserve(&mut self, additional: usize, hasher: impl Fn(&K) -> u64) {
+        if let Err(e) = self.try_reserve(additional, hasher) {
+            e.raise();
+        }
+    }
+
+    /// make room for `additional` more entries in the allocator and the bucket table, no entry is changed on error
+    pub fn try_reserve(&mut self, additional: usize, hasher: impl Fn(&K) -> u64) -> Result<(), TryReserveError> {
+        self.size().checked_add(additional).ok_or(TryReserveError::CapacityOverflow)?;
+        self.allocator.try_reserve(additional)?;
+        return self.try_grow(additional, hasher);
+    }
+
+    /// make room for `additional` more entries in the bucket table only, the nodes are reserved by the caller
+    fn try_grow(&mut self, additional: usize, hasher: impl Fn(&K) -> u64) -> Result<(), TryReserveError> {
+        let size = self.size().checked_add(additional).ok_or(TryReserveError::CapacityOverflow)?;
+        if !self.tab.is_empty() && size <= self.threshold {
+            return Ok(());
         }
-        // resize
         let old_capacity = self.tab.len();
-        let capacity = if old_capacity > 0 { old_capacity * 2 } else { self.threshold };
+        let capacity = if old_capacity > 0 {
+            old_capacity.checked_mul(2).ok_or(TryReserveError::CapacityOverflow)?
+        } else {
+            self.threshold
+        };
+        let capacity = capacity.max(self.buckets_for(size).ok_or(TryReserveError::CapacityOverflow)?);
+        let new_tab = try_filled_vec(capacity, Bucket::new_list)?;
+        self.resize(new_tab, hasher);
+        return Ok(());
+    }
+
+    /// rebuild the bucket table at the smallest power of two that holds the current entries
+    /// and give the unused node memory back to the allocator
+    pub fn shrink_to_fit(&mut self, hasher: impl Fn(&K) -> u64) {
+        self.finish_rehash(&hasher);
+        self.allocator.shrink_to_fit();
+        if self.size() == 0 {
+            self.tab = Vec::new();
+            self.threshold = DEFAULT_INITIAL_CAPACITY;
+            return;
+        }
+        let capacity = self.buckets_for(self.size()).expect("the entries fit in the table");
+        if capacity < self.tab.len() {
+            // a failed allocation keeps the current buckets
+            if let Ok(new_tab) = try_filled_vec(capacity, Bucket::new_list) {
+                self.resize(new_tab, hasher);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> MapStats {
+        let mut stats = MapStats::new(self.size(), self.old.len() + self.tab.len());
+        for bucket in self.old.iter().chain(self.tab.iter()) {
+            bucket.add_to(&mut stats);
+        }
+        return stats;
+    }
+
+    /// remove every entry, the bucket table keeps its capacity and the node memory goes back to the allocator
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+        // nothing is left to move
+        self.old = Vec::new();
+        self.rehash_index = 0;
+        self.allocator.shrink_to_fit();
+    }
+
+    /// `None` if the number of buckets does not fit in `usize`
+    fn buckets_for(&self, size: usize) -> Option<usize> {
+        // the cast saturates, and `usize::MAX` has no next power of two
+        ((size as f64 / self.load_factor as f64).ceil() as usize).checked_next_power_of_two()
+    }
 
-        let mut new_tab: Vec<Bucket<K, V>> = Vec::with_capacity(capacity);
-        for _ in 0..capacity {
-            new_tab.push(Bucket::new_list());
+    /// move every entry into the empty buckets of `new_tab`
+    fn resize(&mut self, mut new_tab: Vec<Bucket<K, V>>, hasher: impl Fn(&K) -> u64) {
+        self.finish_rehash(&hasher);
+        let capacity = new_tab.len();
+        // doubling splits every bucket in two, any other size rehashes every node
+        if self.tab.len() * 2 == capacity {
+            Bucket::transfer(&mut self.tab, &mut new_tab, hasher);
+        } else {
+            Bucket::rehash(&mut self.tab, &mut new_tab, hasher, self.key_order);
         }
-        Bucket::transfer(&mut self.tab, &mut new_tab, hasher);
         self.tab = new_tab;
         self.threshold = (capacity as f32 * self.load_factor) as usize;
     }
 
     // iterator
     pub fn iter(&self) -> Iter<'_, K, V> {
-        Iter {
-            tab: &self.tab,
-            index: 0,
-            cur_iter: BucketIter::empty(),
-        }
+        Iter { raw: RawIter::new(&self.old, &self.tab, self.size()) }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut { raw: RawIter::new(&self.old, &self.tab, self.size()), _marker: PhantomData }
     }
 
     #[inline]
     pub fn size(&self) -> usize {
-        self.allocator.size
+        self.allocator.size()
+    }
+
+    pub fn drain(&mut self) -> Drain<'_, K, V, A> {
+        Drain { table: self, cursor: DrainCursor::new() }
+    }
+
+    pub fn retain<F>(&mut self, mut f: F)
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        self.extract_if(|k, v| !f(k, v)).for_each(drop);
+    }
+
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, F, A>
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        ExtractIf { table: self, index: 0, cursor: BucketCursor::empty(), pred: pred }
     }
 
     pub fn foreach<F: FnMut(&K, &mut V)>(&mut self, mut f: F) {
-        if self.tab.is_empty() {
-            return;
+        for bucket in self.old.iter_mut().chain(self.tab.iter_mut()) {
+            bucket.foreach(&mut f);
         }
-        for i in 0..self.tab.len() {
-            self.tab[i].foreach(&mut f);
+    }
+
+    /// the buckets of `old` come first, the drain and extract cursors walk both tables by one index
+    fn bucket_mut(&mut self, index: usize) -> Option<&mut Bucket<K, V>> {
+        if index < self.old.len() {
+            return Some(&mut self.old[index]);
         }
+        self.tab.get_mut(index - self.old.len())
+    }
+
+    fn old_index(&self, hash: u64) -> usize {
+        hash as usize & (self.old.len() - 1)
     }
 }
 
-impl<K, V> RBTreeHashMap<K, V>
+impl<K, V, A> RBTreeHashMap<K, V, A>
 where
     K: Eq,
+    A: NodeAllocator<EntryNode<K, V>>,
 {
-    pub fn get(&self, hash: u64, key: &K) -> Option<&V> {
-        let node = self.get_node(hash, key)?;
-        return Some(node);
+    /// while a resize is running the key may still be in its old bucket
+    fn find_with(&self, hash: u64, find: impl Fn(&Bucket<K, V>) -> *mut EntryNode<K, V>) -> *mut EntryNode<K, V> {
+        debug_assert!(!self.tab.is_empty(), "map not initialized");
+        let mask = self.tab.len() - 1;
+        let ptr = find(&self.tab[hash as usize & mask]);
+        if ptr.is_null() && self.is_rehashing() {
+            return find(&self.old[self.old_index(hash)]);
+        }
+        return ptr;
+    }
+
+    fn find_node<Q>(&self, hash: u64, key: &Q) -> *mut EntryNode<K, V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        self.find_with(hash, |bucket| bucket.find_node(hash, key))
     }
 
-    pub fn get_mut(&mut self, hash: u64, key: &K) -> Option<&mut V> {
-        return self.get_node(hash, key);
+    /// like `find_node`, a tree bucket with ordered keys is searched by the `Ord` key
+    fn find_ordered<Q>(&self, hash: u64, key: &Q) -> *mut EntryNode<K, V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
+        self.find_with(hash, |bucket| bucket.find_ordered(hash, key))
     }
 
-    fn get_node(&self, hash: u64, key: &K) -> Option<&mut V> {
-        debug_assert!(!self.tab.is_empty(), "map not initialized");
+    /// like `find_node`, an owned key can be searched by its order
+    fn find_key(&self, hash: u64, key: &K) -> *mut EntryNode<K, V> {
+        if self.tab.is_empty() {
+            return ptr::null_mut();
+        }
+        let mask = self.tab.len() - 1;
+        let ptr = self.tab[hash as usize & mask].find_key(hash, key);
+        if ptr.is_null() && self.is_rehashing() {
+            return self.old[self.old_index(hash)].find_key(hash, key);
+        }
+        return ptr;
+    }
+
+    pub fn get<Q>(&self, hash: u64, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let node = unsafe { self.find_node(hash, key).as_ref()? };
+        return Some(&node.value);
+    }
+
+    pub fn get_mut<Q>(&mut self, hash: u64, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let node = unsafe { self.find_node(hash, key).as_mut()? };
+        return Some(&mut node.value);
+    }
+
+    pub fn get_ordered<Q>(&self, hash: u64, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
+        let node = unsafe { self.find_ordered(hash, key).as_ref()? };
+        return Some(&node.value);
+    }
+
+    pub fn get_mut_ordered<Q>(&mut self, hash: u64, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
+        let node = unsafe { self.find_ordered(hash, key).as_mut()? };
+        return Some(&mut node.value);
+    }
+
+    pub fn entry(&mut self, hash: u64, key: K, hasher: impl Fn(&K) -> u64) -> Entry<'_, K, V, RBTreeHashMap<K, V, A>>
+    {
+        let ptr = self.find_key(hash, &key);
+        if ptr.is_null() {
+            // only a vacant entry may add an entry
+            if let Err(e) = self.try_reserve_one(hasher) {
+                e.raise();
+            }
+            return Entry::Vacant(VacantEntry::new(key, hash, self));
+        }
+        let node = unsafe { &mut *ptr };
+        return Entry::Occupied(unsafe { OccupiedEntry::new(&node.key, &mut node.value, hash, self) });
+    }
+
+    pub fn put(&mut self, hash: u64, key: K, value: V, hasher: impl Fn(&K) -> u64) -> Option<V> {
+        return self.try_put(hash, key, value, hasher).unwrap_or_else(|e| e.raise());
+    }
+
+    /// on error no entry is changed and `key` and `value` are dropped
+    pub fn try_put(&mut self, hash: u64, key: K, value: V, hasher: impl Fn(&K) -> u64) -> Result<Option<V>, TryReserveError> {
+        // replacing a value neither grows the table nor moves old buckets
+        let node = self.find_key(hash, &key);
+        if !node.is_null() {
+            return Ok(Some(mem::replace(unsafe { &mut (*node).value }, value)));
+        }
+        // only a new entry needs a node
+        self.try_reserve_one(&hasher)?;
+        self.allocator.try_reserve(1)?;
         let mask = self.tab.len() - 1;
         let index = hash as usize & mask;
-        return self.tab[index].get(hash, key);
+        return Ok(self.tab[index].write(hash, key, value, &mut self.allocator, &hasher, self.key_order));
+    }
+
+    pub fn remove<Q>(&mut self, hash: u64, key: &Q, hasher: impl Fn(&K) -> u64) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        self.remove_with(hash, hasher, |bucket, allocator| bucket.remove(hash, key, allocator))
     }
 
-    pub fn entry(&mut self, hash: u64, key: K, hasher: impl Fn(&K) -> u64) -> Entry<'_, K, V, ListBucket<K, V>>
+    /// like `remove`, a tree bucket with ordered keys is searched by the `Ord` key
+    pub fn remove_ordered<Q>(&mut self, hash: u64, key: &Q, hasher: impl Fn(&K) -> u64) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
     {
-        self.reserve(1, hasher);
+        self.remove_with(hash, hasher, |bucket, allocator| bucket.remove_ordered(hash, key, allocator))
+    }
+
+    fn remove_with(&mut self, hash: u64, hasher: impl Fn(&K) -> u64, remove: impl Fn(&mut Bucket<K, V>, &mut A) -> Option<V>) -> Option<V> {
+        if self.is_rehashing() {
+            self.rehash_step(REHASH_STEP, hasher);
+        }
         let mask = self.tab.len() - 1;
         let index = hash as usize & mask;
-        return self.tab[index].entry(hash, key, &mut self.allocator)
+        let value = remove(&mut self.tab[index], &mut self.allocator);
+        if value.is_none() && self.is_rehashing() {
+            let index = self.old_index(hash);
+            return remove(&mut self.old[index], &mut self.allocator);
+        }
+        return value;
     }
+}
 
-    pub fn put(&mut self, hash: u64, key: K, value: V, hasher: impl Fn(&K) -> u64) -> Option<V> {
-        self.reserve(1, &hasher);
+impl<K, V, A> RawTable<K, V> for RBTreeHashMap<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    fn add_node(&mut self, hash: u64, key: K, value: V) -> (&K, &mut V) {
         let mask = self.tab.len() - 1;
         let index = hash as usize & mask;
-        return self.tab[index].write(hash, key, value, &mut self.allocator, &hasher);
+        return self.tab[index].add_node(hash, key, value, &mut self.allocator);
     }
 
-    pub fn remove(&mut self, hash: u64, key: &K) -> Option<V> {
+    unsafe fn remove_entry(&mut self, hash: u64, key: *const K) -> (K, V) {
         let mask = self.tab.len() - 1;
         let index = hash as usize & mask;
-        return self.tab[index].remove(hash, key, &mut self.allocator);
+        if let Some(entry) = self.tab[index].unlink(hash, key, &mut self.allocator) {
+            return entry;
+        }
+        let index = self.old_index(hash);
+        return self.old[index].unlink(hash, key, &mut self.allocator).expect("entry is not in the table");
     }
 }
 
-// iterator
-pub struct Iter<'a, K, V> {
-    tab: &'a Vec<Bucket<K, V>>,
+impl<K, V, A> IntoIterator for RBTreeHashMap<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, A>;
+
+    fn into_iter(self) -> IntoIter<K, V, A> {
+        IntoIter { table: self, cursor: DrainCursor::new() }
+    }
+}
+
+/**
+Takes the entries of one bucket at a time, a tree bucket is untreeified first,
+and frees the nodes through the allocator so `size()` stays correct
+*/
+struct DrainCursor<K, V> {
     index: usize,
-    cur_iter: BucketIter<'a, K, V>,
+    cur: *mut EntryNode<K, V>,
 }
 
-impl<'a, K, V> Iterator for Iter<'a, K, V> {
-    type Item = (&'a K, &'a mut V);
+impl<K, V> DrainCursor<K, V> {
+    fn new() -> Self {
+        DrainCursor { index: 0, cur: std::ptr::null_mut() }
+    }
+
+    fn next<A: NodeAllocator<EntryNode<K, V>>>(&mut self, table: &mut RBTreeHashMap<K, V, A>) -> Option<(K, V)> {
+        while self.cur.is_null() {
+            self.cur = table.bucket_mut(self.index)?.take_list();
+            self.index += 1;
+        }
+        let node = unsafe { table.allocator.free(NonNull::new_unchecked(self.cur)) };
+        self.cur = node.next;
+        Some((node.key, node.value))
+    }
+}
+
+pub struct IntoIter<K, V, A = SlabAllocator<EntryNode<K, V>>>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    table: RBTreeHashMap<K, V, A>,
+    cursor: DrainCursor<K, V>,
+}
+
+impl<K, V, A> Iterator for IntoIter<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    type Item = (K, V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let len = self.tab.len();
+        self.cursor.next(&mut self.table)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.table.size(), Some(self.table.size()))
+    }
+}
+
+impl<K, V, A> ExactSizeIterator for IntoIter<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{}
+
+impl<K, V, A> Drop for IntoIter<K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    fn drop(&mut self) {
+        while self.cursor.next(&mut self.table).is_some() {}
+    }
+}
+
+/// removes the remaining entries when dropped
+pub struct Drain<'a, K, V, A = SlabAllocator<EntryNode<K, V>>>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    table: &'a mut RBTreeHashMap<K, V, A>,
+    cursor: DrainCursor<K, V>,
+}
+
+impl<K, V, A> Iterator for Drain<'_, K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cursor.next(self.table)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.table.size(), Some(self.table.size()))
+    }
+}
+
+impl<K, V, A> ExactSizeIterator for Drain<'_, K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{}
+
+impl<K, V, A> Drop for Drain<'_, K, V, A>
+where A: NodeAllocator<EntryNode<K, V>>,
+{
+    fn drop(&mut self) {
+        while self.cursor.next(self.table).is_some() {}
+    }
+}
+
+/**
+Lazily removes the entries accepted by `pred`, the rest stay in the map once it is dropped.
+`index` is one past the bucket under the cursor, a tree bucket may untreeify when the cursor leaves it.
+*/
+pub struct ExtractIf<'a, K, V, F, A = SlabAllocator<EntryNode<K, V>>>
+where F: FnMut(&K, &mut V) -> bool, A: NodeAllocator<EntryNode<K, V>>,
+{
+    table: &'a mut RBTreeHashMap<K, V, A>,
+    index: usize,
+    cursor: BucketCursor<K, V>,
+    pred: F,
+}
+
+impl<K, V, F, A> Iterator for ExtractIf<'_, K, V, F, A>
+where F: FnMut(&K, &mut V) -> bool, A: NodeAllocator<EntryNode<K, V>>,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let table = &mut *self.table;
         loop {
-            if let Some(mut item) = self.cur_iter.next() {
-                let node = unsafe { item.as_mut() };
-                return Some((&node.key, &mut node.value));
+            if self.index > 0 {
+                // borrow the bucket and the allocator apart
+                let (old, i) = (table.old.len(), self.index - 1);
+                let bucket = if i < old { &mut table.old[i] } else { &mut table.tab[i - old] };
+                if let Some(item) = bucket.extract_next(&mut self.cursor, &mut self.pred, &mut table.allocator) {
+                    return Some(item);
+                }
+                bucket.shrink_tree();
             }
-
-            if self.index >= len {
+            let Some(bucket) = table.bucket_mut(self.index) else {
+                self.cursor = BucketCursor::empty();
                 return None;
-            }
+            };
+            self.cursor = bucket.extract_cursor();
+            self.index += 1;
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.table.size()))
+    }
+}
+
+impl<K, V, F, A> Drop for ExtractIf<'_, K, V, F, A>
+where F: FnMut(&K, &mut V) -> bool, A: NodeAllocator<EntryNode<K, V>>,
+{
+    fn drop(&mut self) {
+        if self.index > 0
+            && let Some(bucket) = self.table.bucket_mut(self.index - 1)
+        {
+            bucket.shrink_tree();
+        }
+    }
+}
+
+/// walks the buckets in order and yields every node, shared by `Iter` and `IterMut`
+struct RawIter<'a, K, V> {
+    tab: &'a [Bucket<K, V>],
+    // the new buckets while `tab` holds the old ones of a running resize
+    next_tab: &'a [Bucket<K, V>],
+    index: usize,
+    cur_iter: BucketIter<'a, K, V>,
+    remaining: usize,
+}
+
+impl<'a, K, V> RawIter<'a, K, V> {
+    fn new(old: &'a [Bucket<K, V>], tab: &'a [Bucket<K, V>], size: usize) -> Self {
+        RawIter { tab: old, next_tab: tab, index: 0, cur_iter: BucketIter::empty(), remaining: size }
+    }
+}
+
+impl<K, V> Iterator for RawIter<'_, K, V> {
+    type Item = NonNull<EntryNode<K, V>>;
 
-            // unsafe：转换生命周期，因为我们知道 tab 是 'a 生命周期
-            // let iter: BucketIter<'a, K, V> = unsafe { std::mem::transmute(iter) };
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(ptr) = self.cur_iter.next() {
+                self.remaining -= 1;
+                return Some(ptr);
+            }
+            if self.index >= self.tab.len() {
+                if self.next_tab.is_empty() {
+                    return None;
+                }
+                self.tab = mem::take(&mut self.next_tab);
+                self.index = 0;
+                continue;
+            }
             self.cur_iter = self.tab[self.index].iter();
             self.index += 1;
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    raw: RawIter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = unsafe { self.raw.next()?.as_ref() };
+        Some((&node.key, &node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+pub struct IterMut<'a, K, V> {
+    raw: RawIter<'a, K, V>,
+    _marker: PhantomData<&'a mut V>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = unsafe { self.raw.next()?.as_mut() };
+        Some((&node.key, &mut node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw.size_hint()
+    }
 }
 
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
 
 #[cfg(test)]
 mod test {
@@ -156,11 +714,11 @@ mod test {
     fn base_test1() {
         let mut map = RBTreeHashMap::<i32, &str>::new();
         let hasher = |x:&i32 | -> u64 { *x as u64 };
-        map.put(1, 1, &"a", hasher);
-        map.put(2, 2, &"b", hasher);
-        map.put(3, 3, &"c", hasher);
-        map.put(4, 4, &"d", hasher);
-        map.put(5, 5, &"e", hasher);
+        map.put(1, 1, "a", hasher);
+        map.put(2, 2, "b", hasher);
+        map.put(3, 3, "c", hasher);
+        map.put(4, 4, "d", hasher);
+        map.put(5, 5, "e", hasher);
 
         assert_eq!(map.get(1, &1), Some(&"a"));
         assert_eq!(map.get(2, &2), Some(&"b"));
@@ -1,58 +1,314 @@
 
 use crate::collection::tree::{RBTree, TreeCleaner, RBIter, TreeNode};
-use std::ptr::NonNull;
-use crate::collection::map::Entry;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::ptr::{self, NonNull};
 use crate::collection::map::map_bucket::Bucket;
-use crate::collection::map::list_bucket::{EntryNode, ListBucket, ListBucketIter};
-use crate::collection::map::allocator::Allocator;
+use crate::collection::map::list_bucket::{EntryNode, ExtractCursor, ListBucket, ListBucketIter};
+use crate::collection::map::allocator::NodeAllocator;
 
+/**
+When a tree bucket holds no more than 6 entries, it is converted back to a `ListBucket`,
+either after a remove or when it is split during resize
+*/
+const UNTREEIFY_THRESHOLD: usize = 6;
 
+/// orders the keys of the same hash, `Ord::cmp` of the key type
+pub(crate) type KeyOrder<K> = fn(&K, &K) -> Ordering;
+
+/**
+The key of a tree node. Without an order it is just the hash and its list holds every entry of that
+hash. With an order its list holds a single entry, `key` points to the key of that entry and the
+nodes are sorted by hash and then by key.
+*/
+#[derive(Debug)]
+pub struct TreeKey<K> {
+    hash: u64,
+    key: *const K,
+    order: Option<KeyOrder<K>>,
+}
+
+// it points into the nodes the bucket owns
+unsafe impl<K: Send> Send for TreeKey<K> {}
+unsafe impl<K: Sync> Sync for TreeKey<K> {}
+
+impl<K> Ord for TreeKey<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.hash.cmp(&other.hash).then_with(|| match self.order {
+            Some(order) => unsafe { order(&*self.key, &*other.key) },
+            None => Ordering::Equal,
+        })
+    }
+}
+
+impl<K> PartialOrd for TreeKey<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K> PartialEq for TreeKey<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K> Eq for TreeKey<K> {}
+
+type TreeBucketNode<K, V> = TreeNode<TreeKey<K>, ListBucket<K, V>>;
+
+/**
+The entries of a tree bucket sit in `ListBucket`s inside an `RBTree`. Without an order there is one
+list per hash, and keys that share the full hash are compared one by one. With an order the tree is
+sorted by hash and key and every list holds one entry, so a full hash collision costs a tree search.
+*/
 #[derive(Debug)]
 pub struct TreeBucket<K, V> {
-    pub tree: RBTree<u64, ListBucket<K, V>>,
+    tree: RBTree<TreeKey<K>, ListBucket<K, V>>,
+    order: Option<KeyOrder<K>>,
 }
 
 impl<K, V> TreeBucket<K, V> {
     pub fn new() -> Self {
+        Self::with_order(None)
+    }
+
+    pub fn with_order(order: Option<KeyOrder<K>>) -> Self {
         TreeBucket {
-            tree: RBTree::<u64, ListBucket<K, V>>::new(),
+            tree: RBTree::new(),
+            order: order,
+        }
+    }
+
+    /// start or stop ordering the keys, every entry moves to a tree of the new order
+    pub fn set_order(&mut self, order: Option<KeyOrder<K>>) {
+        let mut bucket = TreeBucket::with_order(order);
+        self.tree.clean_transfer(|mut tree_node| {
+            let mut ptr = tree_node.value.take();
+            while !ptr.is_null() {
+                let node = unsafe { &mut *ptr };
+                ptr = node.next;
+                bucket.push(tree_node.key.hash, node);
+            }
+        });
+        *self = bucket;
+    }
+
+    /// the tree key of an entry of `hash`, it points to the entry only when the keys are ordered
+    fn tree_key(&self, hash: u64, node: *const EntryNode<K, V>) -> TreeKey<K> {
+        let key = match self.order {
+            Some(_) => unsafe { ptr::addr_of!((*node).key) },
+            None => ptr::null(),
+        };
+        TreeKey { hash: hash, key: key, order: self.order }
+    }
+
+    /// the first tree node of `hash`, the other nodes of the hash follow it through `next`
+    fn first_of(&self, hash: u64) -> *mut TreeBucketNode<K, V> {
+        // never equal, so the search ends next to the first node of the hash
+        let node = self.tree.ceiling_node_by(|key| hash.cmp(&key.hash).then(Ordering::Less));
+        if node.is_null() || unsafe { (*node).key.hash } != hash {
+            return ptr::null_mut();
+        }
+        return node;
+    }
+
+    /// the tree node whose key `cmp` finds equal to the searched one, the keys must be ordered
+    fn node_by(&self, hash: u64, cmp: impl Fn(&K) -> Ordering) -> *mut TreeBucketNode<K, V> {
+        self.tree.get_node_by(|key| hash.cmp(&key.hash).then_with(|| cmp(unsafe { &*key.key })))
+    }
+
+    /// the number of entries of every hash, in hash order
+    pub fn chain_lengths(&self) -> impl Iterator<Item = usize> + '_ {
+        let mut iter = self.tree.iter().peekable();
+        std::iter::from_fn(move || {
+            let (key, list) = iter.next()?;
+            let mut len = list.count();
+            while let Some((_, list)) = iter.next_if(|(next, _)| next.hash == key.hash) {
+                len += list.count();
+            }
+            Some(len)
+        })
+    }
+
+    pub fn foreach(&mut self, mut f: impl FnMut(&K, &mut V)) {
+        for list in self.tree.values_mut() {
+            list.foreach(&mut f);
         }
     }
 }
 
 impl<K, V> TreeBucket<K, V> {
-    pub fn write(&mut self, hash: u64, key: K, value: V, allocator: &mut Allocator<EntryNode<K, V>>) -> Option<V>
+    pub fn write(&mut self, hash: u64, key: K, value: V, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> Option<V>
     where
         K: Eq,
     {
-        let list = self.tree.get_or_insert(hash, ListBucket::new());
-        return list.write(key, value, allocator).1;
+        if self.order.is_none() {
+            let list = self.tree.get_or_insert(self.tree_key(hash, ptr::null()), ListBucket::new());
+            return list.write(key, value, allocator).1;
+        }
+        let node = self.find_key(hash, &key);
+        if !node.is_null() {
+            return Some(std::mem::replace(unsafe { &mut (*node).value }, value));
+        }
+        self.add_node(hash, key, value, allocator);
+        return None;
+    }
+
+    /// without `Q: Ord` the entries of the hash are compared one by one
+    pub fn remove<Q>(&mut self, hash: u64, key: &Q, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let mut node = self.first_of(hash);
+        while !node.is_null() && unsafe { (*node).key.hash } == hash {
+            let list = unsafe { &mut (*node).value };
+            if let Some(value) = list.remove(key, allocator) {
+                if list.is_empty() {
+                    self.tree.remove_node(node);
+                }
+                return Some(value);
+            }
+            node = unsafe { (*node).next };
+        }
+        return None;
+    }
+
+    /// like `remove`, an `Ord` key is searched by the order of the tree when the keys are ordered
+    pub fn remove_ordered<Q>(&mut self, hash: u64, key: &Q, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
+        if self.order.is_none() {
+            return self.remove(hash, key, allocator);
+        }
+        let node = self.node_by(hash, |other| key.cmp(other.borrow()));
+        if node.is_null() {
+            return None;
+        }
+        let list = unsafe { &mut (*node).value };
+        let value = list.remove(key, allocator);
+        if list.is_empty() {
+            self.tree.remove_node(node);
+        }
+        return value;
+    }
+
+    pub fn unlink(&mut self, hash: u64, key: *const K, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> Option<(K, V)> {
+        let node = match self.order {
+            Some(order) => self.node_by(hash, |other| order(unsafe { &*key }, other)),
+            None => self.first_of(hash),
+        };
+        if node.is_null() {
+            return None;
+        }
+        let list = unsafe { &mut (*node).value };
+        // no other bucket holds an equal key, so this is `None` if the node is not here
+        let entry = list.unlink(key, allocator)?;
+        if list.is_empty() {
+            self.tree.remove_node(node);
+        }
+        return Some(entry);
+    }
+
+    pub fn clone_with(&self, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> TreeBucket<K, V>
+    where K: Clone, V: Clone,
+    {
+        let mut bucket = TreeBucket::with_order(self.order);
+        for (key, list) in self.tree.iter() {
+            let list = list.clone_with(allocator);
+            // an ordered key points into the copied list
+            let key = bucket.tree_key(key.hash, list.head());
+            bucket.tree.put(key, list);
+        }
+        return bucket;
+    }
+
+    pub fn should_untreeify(&self) -> bool {
+        self.iter().take(UNTREEIFY_THRESHOLD + 1).count() <= UNTREEIFY_THRESHOLD
+    }
+
+    pub fn untreeify(&mut self) -> ListBucket<K, V> {
+        let mut list = ListBucket::new();
+        self.tree.clean_transfer(|node| {
+            list.merge(node.value);
+        });
+        list
     }
 
     pub fn push(&mut self, hash: u64, ptr: &mut EntryNode<K, V>) {
-        self.tree.get_or_insert(hash, ListBucket::new()).push(ptr);
+        let key = self.tree_key(hash, ptr);
+        self.tree.get_or_insert(key, ListBucket::new()).push(ptr);
     }
 
-    pub fn get(&self, hash: u64, key: &K) -> Option<&mut V>
-    where
-        K: Eq,
+    pub fn get<Q>(&self, hash: u64, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
     {
-        return self.tree.get(&hash)?.get(key);
+        let node = unsafe { self.find_node(hash, key).as_ref()? };
+        return Some(&node.value);
     }
 
-    pub fn entry<'a>(&'a mut self, hash: u64, key: K, allocator: &'a mut Allocator<EntryNode<K, V>>) -> Entry<'a, K, V, ListBucket<K, V>>
-    where K: Eq {
-        let list:&mut ListBucket<K, V> = self.tree.get_or_insert(hash, ListBucket::new());
-        return list.entry(hash, key, allocator);
+    pub fn get_mut<Q>(&mut self, hash: u64, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let node = unsafe { self.find_node(hash, key).as_mut()? };
+        return Some(&mut node.value);
+    }
+
+    /// without `Q: Ord` the entries of the hash are compared one by one
+    pub fn find_node<Q>(&self, hash: u64, key: &Q) -> *mut EntryNode<K, V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let mut node = self.first_of(hash);
+        while !node.is_null() && unsafe { (*node).key.hash } == hash {
+            let found = unsafe { (*node).value.find_node(key) };
+            if !found.is_null() {
+                return found;
+            }
+            node = unsafe { (*node).next };
+        }
+        return ptr::null_mut();
     }
 
-    pub fn split_transfer(&mut self, old_cap: usize, i: usize, new_tab: &mut Vec<Bucket<K, V>>) {
-        let mut lo_head: *mut TreeNode<u64, ListBucket<K, V>> = std::ptr::null_mut();
-        let mut hi_head: *mut TreeNode<u64, ListBucket<K, V>> = std::ptr::null_mut();
+    /// like `find_node`, an `Ord` key is searched by the order of the tree when the keys are ordered
+    pub fn find_ordered<Q>(&self, hash: u64, key: &Q) -> *mut EntryNode<K, V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
+        if self.order.is_none() {
+            return self.find_node(hash, key);
+        }
+        let node = self.node_by(hash, |other| key.cmp(other.borrow()));
+        if node.is_null() {
+            return ptr::null_mut();
+        }
+        return unsafe { (*node).value.head() };
+    }
+
+    /// like `find_node`, an owned key is searched by its order
+    pub fn find_key(&self, hash: u64, key: &K) -> *mut EntryNode<K, V>
+    where K: Eq,
+    {
+        let Some(order) = self.order else {
+            return self.find_node(hash, key);
+        };
+        let node = self.node_by(hash, |other| order(key, other));
+        if node.is_null() {
+            return ptr::null_mut();
+        }
+        return unsafe { (*node).value.head() };
+    }
+
+    pub fn add_node(&mut self, hash: u64, key: K, value: V, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> (&K, &mut V) {
+        let node = unsafe { allocator.alloc(EntryNode { key: key, value: value, next: ptr::null_mut() }).as_mut() };
+        self.push(hash, node);
+        return (&node.key, &mut node.value);
+    }
+
+    pub fn split_transfer(&mut self, old_cap: usize, i: usize, new_tab: &mut [Bucket<K, V>]) {
+        let mut lo_head: *mut TreeBucketNode<K, V> = std::ptr::null_mut();
+        let mut hi_head: *mut TreeBucketNode<K, V> = std::ptr::null_mut();
         let mut lo_count: usize = 0;
         let mut hi_count: usize = 0;
         self.tree.clean_transfer(|mut tree_node| {
-            let hash = tree_node.key;
+            let hash = tree_node.key.hash;
             let count = tree_node.value.count(); // move
             debug_assert!(count > 0, "assert failed, key is hash: {hash},but value not found");
             if hash as usize & old_cap == 0 {
@@ -67,18 +323,19 @@ impl<K, V> TreeBucket<K, V> {
         });
 
         if lo_count > 0 {
-            new_tab[i] = Self::build_branch(lo_head, lo_count);
+            new_tab[i] = Self::build_branch(lo_head, lo_count, self.order);
         }
         if hi_count > 0 {
-            new_tab[i + old_cap] = Self::build_branch(hi_head, hi_count);
+            new_tab[i + old_cap] = Self::build_branch(hi_head, hi_count, self.order);
         }
     }
 
     fn build_branch(
-        head: *mut TreeNode<u64, ListBucket<K, V>>,
+        head: *mut TreeBucketNode<K, V>,
         count: usize,
+        order: Option<KeyOrder<K>>,
     ) -> Bucket<K, V> {
-        if count <= 6 {
+        if count <= UNTREEIFY_THRESHOLD {
             let mut ptr = head;
             let mut list = ListBucket::new();
             while !ptr.is_null() {
@@ -88,7 +345,7 @@ impl<K, V> TreeBucket<K, V> {
             }
             Bucket::with_list(list)
         } else {
-            let mut tree = TreeBucket::new();
+            let mut tree = TreeBucket::with_order(order);
             let mut ptr = head;
             while !ptr.is_null() {
                 let node = unsafe { Box::from_raw(ptr) };
@@ -107,8 +364,54 @@ impl<K, V> TreeBucket<K, V> {
     }
 }
 
+/**
+Walks the per-hash lists in hash order with an `ExtractCursor`.
+A tree node is removed as soon as its list becomes empty.
+*/
+pub struct TreeExtractCursor<K, V> {
+    node: *mut TreeBucketNode<K, V>,
+    cursor: ExtractCursor<K, V>,
+}
+
+impl<K, V> TreeExtractCursor<K, V> {
+    pub fn new(bucket: &mut TreeBucket<K, V>) -> Self {
+        let node = bucket.tree.first_node();
+        let cursor = if node.is_null() {
+            ExtractCursor::empty()
+        } else {
+            ExtractCursor::new(unsafe { &mut (*node).value })
+        };
+        TreeExtractCursor { node: node, cursor: cursor }
+    }
+
+    pub fn next<F>(&mut self, bucket: &mut TreeBucket<K, V>, f: &mut F, allocator: &mut impl NodeAllocator<EntryNode<K, V>>) -> Option<(K, V)>
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        while !self.node.is_null() {
+            let node = self.node;
+            let item = self.cursor.next(f, allocator);
+            let is_empty = unsafe { (*node).value.is_empty() };
+            if item.is_none() || is_empty {
+                self.node = unsafe { (*node).next };
+                self.cursor = if self.node.is_null() {
+                    ExtractCursor::empty()
+                } else {
+                    ExtractCursor::new(unsafe { &mut (*self.node).value })
+                };
+                if is_empty {
+                    bucket.tree.remove_node(node);
+                }
+            }
+            if item.is_some() {
+                return item;
+            }
+        }
+        None
+    }
+}
+
 pub struct TreeBucketIter<'a, K, V> {
-    tree_iter: RBIter<'a, u64, ListBucket<K, V>>,
+    tree_iter: RBIter<'a, TreeKey<K>, ListBucket<K, V>>,
     cur: ListBucketIter<K, V>,
 }
 
@@ -0,0 +1,252 @@
+use crate::collection::map::map::{OccupiedEntry, RawTable, TryReserveError, VacantEntry};
+use crate::collection::map::{Entry, Map};
+use crate::collection::iter::{IntoKeys, IntoValues, Keys, Values, ValuesMut};
+use crate::collection::tree::{RBIntoIter, RBIter, RBIterMut, RBRange, RBTree, TreeNode};
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::ops::RangeBounds;
+
+/**
+An ordered map backed by `RBTree`, entries are visited in ascending key order.
+*/
+pub struct TreeMap<K, V>(RBTree<K, V>);
+
+impl<K, V> TreeMap<K, V> {
+    pub fn new() -> Self {
+        Self(RBTree::new())
+    }
+
+    pub fn of(key: K, value: V) -> TreeMap<K, V>
+    where
+        K: Ord,
+    {
+        let mut map = Self::new();
+        map.put(key, value);
+        map
+    }
+
+    pub fn iter(&self) -> RBIter<'_, K, V> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> RBIterMut<'_, K, V> {
+        self.0.iter_mut()
+    }
+
+    pub fn keys(&self) -> Keys<RBIter<'_, K, V>> {
+        self.0.keys()
+    }
+
+    pub fn values(&self) -> Values<RBIter<'_, K, V>> {
+        self.0.values()
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<RBIterMut<'_, K, V>> {
+        self.0.values_mut()
+    }
+
+    pub fn into_keys(self) -> IntoKeys<RBIntoIter<K, V>> {
+        self.0.into_keys()
+    }
+
+    pub fn into_values(self) -> IntoValues<RBIntoIter<K, V>> {
+        self.0.into_values()
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.0.first_key_value()
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.0.last_key_value()
+    }
+}
+
+/// the lookups accept a borrowed form of the key that is ordered like `K`, like std's `BTreeMap`
+impl<K, V> TreeMap<K, V>
+where
+    K: Ord,
+{
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
+        self.0.get(key)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
+        self.0.get_mut(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
+        self.0.contains(key)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
+        self.0.remove(key)
+    }
+
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> RBRange<'_, K, V> {
+        self.0.range(range)
+    }
+
+    /// walk the entries in order for a key that is only known to be `Eq`
+    fn find_node<Q>(&self, key: &Q) -> *mut TreeNode<K, V>
+    where K: Borrow<Q>, Q: ?Sized + Eq,
+    {
+        let mut ptr = self.0.first_node();
+        while !ptr.is_null() {
+            let node = unsafe { &*ptr };
+            if node.key.borrow() == key {
+                break;
+            }
+            ptr = node.next;
+        }
+        ptr
+    }
+}
+
+/**
+`Map` looks a borrowed key up by `Hash + Eq`, which gives no order to search the tree with, so the
+trait lookups compare the key with each entry in turn. The inherent `get`, `get_mut`, `contains_key`
+and `remove` above search the tree by `Ord` and win method resolution on a `TreeMap`.
+*/
+impl<K, V> Map<K, V> for TreeMap<K, V>
+where
+    K: Ord,
+{
+    type Raw = RBTree<K, V>;
+
+    #[inline]
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        let ptr = self.find_node(key);
+        if ptr.is_null() { None } else { Some(unsafe { &(*ptr).value }) }
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        let ptr = self.find_node(key);
+        if ptr.is_null() { None } else { Some(unsafe { &mut (*ptr).value }) }
+    }
+
+    fn entry(&mut self, key: K) -> Entry<'_, K, V, Self::Raw> {
+        let ptr = self.0.get_node(&key);
+        if ptr.is_null() {
+            return Entry::Vacant(VacantEntry::new(key, 0, &mut self.0));
+        }
+        let node = unsafe { &mut *ptr };
+        Entry::Occupied(unsafe { OccupiedEntry::new(&node.key, &mut node.value, 0, &mut self.0) })
+    }
+
+    fn put(&mut self, key: K, value: V) -> Option<V> {
+        self.0.put(key, value)
+    }
+
+    /// the node is boxed when it is linked in, so only the count is checked
+    fn try_put(&mut self, key: K, value: V) -> Result<Option<V>, TryReserveError> {
+        self.try_reserve(1)?;
+        Ok(self.0.put(key, value))
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq,
+    {
+        let ptr = self.find_node(key);
+        if ptr.is_null() {
+            return None;
+        }
+        Some(self.0.remove_node(ptr).value)
+    }
+
+    fn foreach<F: FnMut(&K, &mut V)>(&mut self, mut f: F) {
+        for (k, v) in self.0.iter_mut() {
+            f(k, v);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.0 = RBTree::new();
+    }
+
+    /// a tree allocates a node per entry, so it holds just its entries before it grows
+    fn capacity(&self) -> usize {
+        self.0.len()
+    }
+
+    fn reserve(&mut self, _additional: usize) {}
+
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        match self.0.len().checked_add(additional) {
+            Some(_) => Ok(()),
+            None => Err(TryReserveError::CapacityOverflow),
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {}
+
+    fn retain<F>(&mut self, f: F)
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        self.0.retain(f)
+    }
+}
+
+impl<K, V> RawTable<K, V> for RBTree<K, V>
+where
+    K: Ord,
+{
+    fn add_node(&mut self, _hash: u64, key: K, value: V) -> (&K, &mut V) {
+        let node = unsafe { &mut *self.get_or_insert_node(key, value) };
+        (&node.key, &mut node.value)
+    }
+
+    unsafe fn remove_entry(&mut self, _hash: u64, key: *const K) -> (K, V) {
+        let node = self.get_node(unsafe { &*key });
+        let node = self.remove_node(node);
+        (node.key, node.value)
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a TreeMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = RBIter<'a, K, V>;
+
+    fn into_iter(self) -> RBIter<'a, K, V> {
+        self.0.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut TreeMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = RBIterMut<'a, K, V>;
+
+    fn into_iter(self) -> RBIterMut<'a, K, V> {
+        self.0.iter_mut()
+    }
+}
+
+impl<K, V> IntoIterator for TreeMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = RBIntoIter<K, V>;
+
+    fn into_iter(self) -> RBIntoIter<K, V> {
+        self.0.into_iter()
+    }
+}
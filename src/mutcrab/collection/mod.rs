@@ -1,4 +1,25 @@
+pub mod cache;
 pub mod graph;
+pub mod iter;
 pub mod list;
 pub mod map;
+pub mod set;
 pub mod tree;
+
+// the collections can cross threads whenever their elements can
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<map::HashMap<String, Vec<u8>>>();
+    assert_send_sync::<map::TreeHashMap<String, Vec<u8>>>();
+    assert_send_sync::<map::FlatHashMap<String, Vec<u8>>>();
+    assert_send_sync::<map::TreeMap<String, Vec<u8>>>();
+    assert_send_sync::<set::HashSet<String>>();
+    assert_send_sync::<set::TreeHashSet<String>>();
+    assert_send_sync::<tree::RBTree<String, Vec<u8>>>();
+    assert_send_sync::<list::LinkedList<String>>();
+    assert_send_sync::<list::LinkedBlockingQueue<String>>();
+    assert_send_sync::<map::LinkedHashMap<String, Vec<u8>>>();
+    // the eviction listener is only `Send`, so the cache can be moved but not shared
+    const fn assert_send<T: Send>() {}
+    assert_send::<cache::LruCache<String, Vec<u8>>>();
+};
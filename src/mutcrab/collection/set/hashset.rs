@@ -0,0 +1,6 @@
+hash_set! {
+    /**
+    A set of values, stored as the keys of a `HashMap<T, ()>`.
+    */
+    HashSet, HashMap, HashMapIter, HashMapIntoIter, Eq
+}
@@ -0,0 +1,286 @@
+/**
+Writes a set type over a map type with `()` values. `HashSet` and `TreeHashSet` only differ in
+the map they wrap, so the set and its algebra iterators are written once here. `$lookup` is the
+bound of the values that `contains` and `remove` take, `Ord` lets `TreeHashSet` search its ordered collisions.
+*/
+macro_rules! hash_set {
+    ($(#[$doc:meta])* $set:ident, $map:ident, $map_iter:ident, $map_into_iter:ident, $lookup:ident) => {
+        use crate::collection::iter::{IntoKeys, Keys};
+        use crate::collection::map::{Map, $map, $map_iter, $map_into_iter};
+        use std::borrow::Borrow;
+        use std::fmt::{self, Debug, Formatter};
+        use std::hash::{BuildHasher, Hash, RandomState};
+        use std::iter::Chain;
+
+        $(#[$doc])*
+        pub struct $set<T, S = RandomState>($map<T, (), S>);
+
+        pub type Iter<'a, T> = Keys<$map_iter<'a, T, ()>>;
+
+        pub type IntoIter<T> = IntoKeys<$map_into_iter<T, ()>>;
+
+        impl<T> $set<T, RandomState> {
+            pub fn new() -> Self {
+                Self($map::new())
+            }
+
+            pub fn with_capacity(capacity: usize) -> Self {
+                Self($map::with_capacity(capacity))
+            }
+        }
+
+        impl<T, S> $set<T, S> {
+            pub fn iter(&self) -> Iter<'_, T> {
+                self.0.keys()
+            }
+        }
+
+        impl<T, S> $set<T, S>
+        where S: BuildHasher
+        {
+            pub fn with_hasher(hash_builder: S) -> Self {
+                Self($map::with_hasher(hash_builder))
+            }
+
+            pub fn with_capacity_hasher(capacity: usize, hash_builder: S) -> Self {
+                Self($map::with_capacity_hasher(capacity, hash_builder))
+            }
+        }
+
+        impl<T, S> $set<T, S>
+        where T: Hash + Eq,
+              S: BuildHasher,
+        {
+            pub fn len(&self) -> usize {
+                self.0.size()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.0.is_empty()
+            }
+
+            /// returns false if the value was already present, the stored value is kept
+            pub fn insert(&mut self, value: T) -> bool {
+                self.0.put(value, ()).is_none()
+            }
+
+            pub fn clear(&mut self) {
+                self.0.clear()
+            }
+
+            pub fn retain<F>(&mut self, mut f: F)
+            where F: FnMut(&T) -> bool
+            {
+                self.0.retain(|k, _| f(k))
+            }
+        }
+
+        impl<T, S> $set<T, S>
+        where T: Hash + $lookup,
+              S: BuildHasher,
+        {
+            pub fn contains<Q>(&self, value: &Q) -> bool
+            where T: Borrow<Q>, Q: ?Sized + Hash + $lookup,
+            {
+                self.0.contains_key(value)
+            }
+
+            pub fn remove<Q>(&mut self, value: &Q) -> bool
+            where T: Borrow<Q>, Q: ?Sized + Hash + $lookup,
+            {
+                self.0.remove(value).is_some()
+            }
+
+            /// values in `self` or `other`, without duplicates
+            pub fn union<'a>(&'a self, other: &'a $set<T, S>) -> Union<'a, T, S> {
+                Union(self.iter().chain(other.difference(self)))
+            }
+
+            /// values in both `self` and `other`
+            pub fn intersection<'a>(&'a self, other: &'a $set<T, S>) -> Intersection<'a, T, S> {
+                // walk the smaller set, probe the larger one
+                if self.len() <= other.len() {
+                    Intersection { iter: self.iter(), other: other }
+                } else {
+                    Intersection { iter: other.iter(), other: self }
+                }
+            }
+
+            /// values in `self` but not in `other`
+            pub fn difference<'a>(&'a self, other: &'a $set<T, S>) -> Difference<'a, T, S> {
+                Difference { iter: self.iter(), other: other }
+            }
+
+            /// values in exactly one of `self` and `other`
+            pub fn symmetric_difference<'a>(&'a self, other: &'a $set<T, S>) -> SymmetricDifference<'a, T, S> {
+                SymmetricDifference(self.difference(other).chain(other.difference(self)))
+            }
+
+            pub fn is_subset(&self, other: &$set<T, S>) -> bool {
+                self.len() <= other.len() && self.iter().all(|v| other.contains(v))
+            }
+
+            pub fn is_superset(&self, other: &$set<T, S>) -> bool {
+                other.is_subset(self)
+            }
+        }
+
+        impl<T, S> Default for $set<T, S>
+        where S: Default,
+        {
+            fn default() -> Self {
+                Self($map::default())
+            }
+        }
+
+        impl<T, S> Clone for $set<T, S>
+        where T: Clone, S: Clone,
+        {
+            fn clone(&self) -> Self {
+                Self(self.0.clone())
+            }
+        }
+
+        impl<T, S> Debug for $set<T, S>
+        where T: Debug,
+        {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.debug_set().entries(self.iter()).finish()
+            }
+        }
+
+        impl<T, S> PartialEq for $set<T, S>
+        where T: Hash + $lookup, S: BuildHasher,
+        {
+            fn eq(&self, other: &Self) -> bool {
+                self.len() == other.len() && self.is_subset(other)
+            }
+        }
+
+        impl<T, S> Eq for $set<T, S>
+        where T: Hash + $lookup, S: BuildHasher,
+        {}
+
+        impl<T, S> FromIterator<T> for $set<T, S>
+        where T: Hash + Eq, S: BuildHasher + Default,
+        {
+            fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+                let mut set = Self::default();
+                set.extend(iter);
+                set
+            }
+        }
+
+        impl<T, S> Extend<T> for $set<T, S>
+        where T: Hash + Eq, S: BuildHasher,
+        {
+            fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+                self.0.extend(iter.into_iter().map(|v| (v, ())));
+            }
+        }
+
+        impl<'a, T, S> IntoIterator for &'a $set<T, S> {
+            type Item = &'a T;
+            type IntoIter = Iter<'a, T>;
+
+            fn into_iter(self) -> Iter<'a, T> {
+                self.iter()
+            }
+        }
+
+        impl<T, S> IntoIterator for $set<T, S> {
+            type Item = T;
+            type IntoIter = IntoIter<T>;
+
+            fn into_iter(self) -> IntoIter<T> {
+                self.0.into_keys()
+            }
+        }
+
+        pub struct Difference<'a, T, S> {
+            iter: Iter<'a, T>,
+            other: &'a $set<T, S>,
+        }
+
+        impl<'a, T, S> Iterator for Difference<'a, T, S>
+        where T: Hash + $lookup, S: BuildHasher,
+        {
+            type Item = &'a T;
+
+            fn next(&mut self) -> Option<&'a T> {
+                loop {
+                    let value = self.iter.next()?;
+                    if !self.other.contains(value) {
+                        return Some(value);
+                    }
+                }
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (0, self.iter.size_hint().1)
+            }
+        }
+
+        pub struct Intersection<'a, T, S> {
+            iter: Iter<'a, T>,
+            other: &'a $set<T, S>,
+        }
+
+        impl<'a, T, S> Iterator for Intersection<'a, T, S>
+        where T: Hash + $lookup, S: BuildHasher,
+        {
+            type Item = &'a T;
+
+            fn next(&mut self) -> Option<&'a T> {
+                loop {
+                    let value = self.iter.next()?;
+                    if self.other.contains(value) {
+                        return Some(value);
+                    }
+                }
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (0, self.iter.size_hint().1)
+            }
+        }
+
+        pub struct Union<'a, T, S>(Chain<Iter<'a, T>, Difference<'a, T, S>>);
+
+        impl<'a, T, S> Iterator for Union<'a, T, S>
+        where T: Hash + $lookup, S: BuildHasher,
+        {
+            type Item = &'a T;
+
+            fn next(&mut self) -> Option<&'a T> {
+                self.0.next()
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.0.size_hint()
+            }
+        }
+
+        pub struct SymmetricDifference<'a, T, S>(Chain<Difference<'a, T, S>, Difference<'a, T, S>>);
+
+        impl<'a, T, S> Iterator for SymmetricDifference<'a, T, S>
+        where T: Hash + $lookup, S: BuildHasher,
+        {
+            type Item = &'a T;
+
+            fn next(&mut self) -> Option<&'a T> {
+                self.0.next()
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.0.size_hint()
+            }
+        }
+    };
+}
+
+mod hashset;
+mod rbtree_hashset;
+
+pub use hashset::HashSet;
+pub use rbtree_hashset::TreeHashSet;
@@ -0,0 +1,19 @@
+hash_set! {
+    /**
+    A set of values, stored as the keys of a `TreeHashMap<T, ()>`.
+    */
+    TreeHashSet, TreeHashMap, TreeHashMapIter, TreeHashMapIntoIter, Ord
+}
+
+impl<T, S> TreeHashSet<T, S>
+where T: Hash + Ord, S: BuildHasher,
+{
+    /// order the values of one hash in the tree buckets, see `TreeHashMap::set_ordered_collisions`
+    pub fn set_ordered_collisions(&mut self, ordered: bool) {
+        self.0.set_ordered_collisions(ordered)
+    }
+
+    pub fn is_ordered_collisions(&self) -> bool {
+        self.0.is_ordered_collisions()
+    }
+}
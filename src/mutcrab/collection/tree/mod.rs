@@ -3,6 +3,9 @@ mod redblack_tree;
 mod tree_node;
 
 pub use redblack_tree::RBTree;
+pub use redblack_tree::RBRange;
+pub use redblack_tree::RBIter;
+pub use redblack_tree::RBIterMut;
+pub use redblack_tree::RBIntoIter;
 pub use tree_node::TreeCleaner;
-pub(crate) use redblack_tree::RBIter;
 pub(crate) use redblack_tree::TreeNode;
\ No newline at end of file
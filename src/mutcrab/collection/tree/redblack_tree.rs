@@ -1,6 +1,9 @@
+use crate::collection::iter::{IntoKeys, IntoValues, Keys, Values, ValuesMut};
 use crate::collection::tree::tree_node::TreeCleaner;
+use std::borrow::Borrow;
 use std::cmp::Ordering;
 use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
 use std::ptr;
 
 #[derive(Debug)]
@@ -19,16 +22,35 @@ pub struct TreeNode<K, V> {
 #[derive(Debug)]
 pub struct RBTree<K, V> {
     pub(crate) root: *mut TreeNode<K, V>,
+    // first and last node of the in-order list
+    head: *mut TreeNode<K, V>,
+    tail: *mut TreeNode<K, V>,
+    size: usize,
     _marker: PhantomData<(K, V)>,
 }
 
+// the tree owns its nodes and hands out `&mut V` only through `&mut self`
+unsafe impl<K: Send, V: Send> Send for RBTree<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for RBTree<K, V> {}
+
 impl<K, V> RBTree<K, V> {
     pub fn new() -> Self {
         Self {
             root: ptr::null_mut(),
+            head: ptr::null_mut(),
+            tail: ptr::null_mut(),
+            size: 0,
             _marker: PhantomData,
         }
     }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
 }
 
 impl<K, V> Drop for RBTree<K, V> {
@@ -43,75 +65,86 @@ impl<K, V> RBTree<K, V>
 where
     K: Ord,
 {
-    pub fn get(&self, key: &K) -> Option<&mut V> {
-        let mut ptr = self.root;
-        while !ptr.is_null() {
-            let node = unsafe { &mut *ptr };
-            ptr = match key.cmp(&node.key) {
-                Ordering::Equal => {
-                    return Some(&mut node.value);
-                },
-                Ordering::Greater => node.right,
-                Ordering::Less => node.left,
-            };
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
+        let ptr = self.get_node(key);
+        if ptr.is_null() {
+            return None;
         }
-        return None;
+        return Some(unsafe { &(*ptr).value });
     }
 
-    pub fn remove_if<F>(&mut self, key: &K, f: F)
-    where F: FnOnce(&mut V) -> bool
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
     {
-        let mut ptr = self.root;
-        while !ptr.is_null() {
-            let node = unsafe { &mut *ptr };
-            ptr = match key.cmp(&node.key) {
-                Ordering::Equal => {
-                    if f(&mut node.value) {
-                        todo!("to be remove");
-                    }
-                    return;
-                },
-                Ordering::Greater => node.right,
-                Ordering::Less => node.left,
-            };
+        let ptr = self.get_node(key);
+        if ptr.is_null() {
+            return None;
         }
-        return;
+        return Some(unsafe { &mut (*ptr).value });
     }
 
-    pub fn remove(&mut self, key: &K) -> Option<V> {
+    pub fn remove_if<Q, F>(&mut self, key: &Q, f: F) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Ord, F: FnOnce(&mut V) -> bool
+    {
+        let ptr = self.get_node(key);
+        if ptr.is_null() || !f(unsafe { &mut (*ptr).value }) {
+            return None;
+        }
+        return Some(self.remove_node(ptr).value);
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
+        let ptr = self.get_node(key);
+        if ptr.is_null() {
+            return None;
+        }
+        return Some(self.remove_node(ptr).value);
+    }
+
+    pub(crate) fn get_node<Q>(&self, key: &Q) -> *mut TreeNode<K, V>
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
         let mut ptr = self.root;
         while !ptr.is_null() {
-            let node = unsafe { &mut *ptr };
-            ptr = match key.cmp(&node.key) {
-                Ordering::Equal => {
-                    todo!("to be remove");
-                    unsafe { return Some(Box::from_raw(ptr).value); }
-                },
+            let node = unsafe { &*ptr };
+            ptr = match key.cmp(node.key.borrow()) {
+                Ordering::Equal => return ptr,
                 Ordering::Greater => node.right,
                 Ordering::Less => node.left,
             };
         }
-        return None;
+        return ptr::null_mut();
     }
 
     pub fn get_or_insert(&mut self, key: K, value: V) -> &mut V
+    {
+        let ptr = self.get_or_insert_node(key, value);
+        unsafe { &mut (*ptr).value }
+    }
+
+    pub(crate) fn get_or_insert_node(&mut self, key: K, value: V) -> *mut TreeNode<K, V>
     {
         if self.root.is_null() {
-            self.root = self.create_node(key, value, false);
-            return unsafe { &mut (*self.root).value };
+            let root = self.create_node(key, value, false);
+            self.insert_root(root);
+            return root;
         }
 
         let mut node = unsafe { &mut *self.root };
         loop {
             match key.cmp(&node.key) {
-                Ordering::Equal => loop {
-                    return &mut node.value;
+                Ordering::Equal => {
+                    return node;
                 },
                 Ordering::Greater => {
                     if node.right.is_null() {
                         let new_node = unsafe { &mut *self.create_node(key, value, true) };
                         self.insert_right(node, new_node);
-                        return &mut new_node.value;
+                        return new_node;
                     } else {
                         node = unsafe { &mut *node.right };
                     }
@@ -120,7 +153,7 @@ where
                     if node.left.is_null() {
                         let new_node = unsafe { &mut *self.create_node(key, value, true) };
                         self.insert_left(node, new_node);
-                        return &mut new_node.value;
+                        return new_node;
                     } else {
                         node = unsafe { &mut *node.left };
                     }
@@ -139,7 +172,7 @@ where
 
         if self.root.is_null() {
             other.is_red = false;
-            self.root = Box::into_raw(other);
+            self.insert_root(Box::into_raw(other));
             return None;
         }
 
@@ -173,7 +206,8 @@ where
     pub fn put(&mut self, key: K, value: V) -> Option<V>
     {
         if self.root.is_null() {
-            self.root = self.create_node(key, value, false);
+            let root = self.create_node(key, value, false);
+            self.insert_root(root);
             return None;
         }
 
@@ -203,9 +237,124 @@ where
         }
     }
 
-    pub fn contains(&self, key: &K) -> bool {
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where K: Borrow<Q>, Q: ?Sized + Ord,
+    {
         self.get(key).is_some()
     }
+
+    /// greatest key less than or equal to `key`
+    pub fn floor(&self, key: &K) -> Option<(&K, &V)> {
+        Self::node_ref(self.floor_node(key))
+    }
+
+    /// least key greater than or equal to `key`
+    pub fn ceiling(&self, key: &K) -> Option<(&K, &V)> {
+        Self::node_ref(self.ceiling_node(key))
+    }
+
+    /// greatest key strictly less than `key`
+    pub fn lower(&self, key: &K) -> Option<(&K, &V)> {
+        Self::node_ref(self.lower_node(key))
+    }
+
+    /// least key strictly greater than `key`
+    pub fn higher(&self, key: &K) -> Option<(&K, &V)> {
+        Self::node_ref(self.higher_node(key))
+    }
+
+    /**
+    Iterate over the entries between two bounds in ascending order, from either end.
+    An empty or inverted range yields nothing.
+    */
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> RBRange<'_, K, V> {
+        let front = match range.start_bound() {
+            Bound::Included(key) => self.ceiling_node(key),
+            Bound::Excluded(key) => self.higher_node(key),
+            Bound::Unbounded => self.first_node(),
+        };
+        let back = match range.end_bound() {
+            Bound::Included(key) => self.floor_node(key),
+            Bound::Excluded(key) => self.lower_node(key),
+            Bound::Unbounded => self.last_node(),
+        };
+        if front.is_null() || back.is_null() || unsafe { (*front).key > (*back).key } {
+            return RBRange::new(ptr::null_mut(), ptr::null_mut());
+        }
+        RBRange::new(front, back)
+    }
+
+    fn floor_node(&self, key: &K) -> *mut TreeNode<K, V> {
+        match self.search(key) {
+            None => ptr::null_mut(),
+            Some((ptr, Ordering::Equal | Ordering::Greater)) => ptr,
+            Some((ptr, Ordering::Less)) => unsafe { (*ptr).prev },
+        }
+    }
+
+    fn ceiling_node(&self, key: &K) -> *mut TreeNode<K, V> {
+        self.ceiling_node_by(|node_key| key.cmp(node_key))
+    }
+
+    /// like `ceiling_node`, `cmp` compares the searched key with the key of a node
+    pub(crate) fn ceiling_node_by(&self, cmp: impl FnMut(&K) -> Ordering) -> *mut TreeNode<K, V> {
+        match self.search_by(cmp) {
+            None => ptr::null_mut(),
+            Some((ptr, Ordering::Equal | Ordering::Less)) => ptr,
+            Some((ptr, Ordering::Greater)) => unsafe { (*ptr).next },
+        }
+    }
+
+    /// like `get_node`, `cmp` compares the searched key with the key of a node
+    pub(crate) fn get_node_by(&self, cmp: impl FnMut(&K) -> Ordering) -> *mut TreeNode<K, V> {
+        match self.search_by(cmp) {
+            Some((ptr, Ordering::Equal)) => ptr,
+            _ => ptr::null_mut(),
+        }
+    }
+
+    fn lower_node(&self, key: &K) -> *mut TreeNode<K, V> {
+        match self.search(key) {
+            None => ptr::null_mut(),
+            Some((ptr, Ordering::Greater)) => ptr,
+            Some((ptr, Ordering::Equal | Ordering::Less)) => unsafe { (*ptr).prev },
+        }
+    }
+
+    fn higher_node(&self, key: &K) -> *mut TreeNode<K, V> {
+        match self.search(key) {
+            None => ptr::null_mut(),
+            Some((ptr, Ordering::Less)) => ptr,
+            Some((ptr, Ordering::Equal | Ordering::Greater)) => unsafe { (*ptr).next },
+        }
+    }
+
+    /**
+    Descend towards `key` and return the matched node, or the last node visited with `key.cmp(node.key)`.
+    The neighbors of a missing key are that node and its `prev` or `next`.
+    */
+    fn search(&self, key: &K) -> Option<(*mut TreeNode<K, V>, Ordering)> {
+        self.search_by(|node_key| key.cmp(node_key))
+    }
+
+    fn search_by(&self, mut cmp: impl FnMut(&K) -> Ordering) -> Option<(*mut TreeNode<K, V>, Ordering)> {
+        let mut ptr = self.root;
+        if ptr.is_null() {
+            return None;
+        }
+        loop {
+            let node = unsafe { &*ptr };
+            let (order, child) = match cmp(&node.key) {
+                Ordering::Equal => return Some((ptr, Ordering::Equal)),
+                Ordering::Greater => (Ordering::Greater, node.right),
+                Ordering::Less => (Ordering::Less, node.left),
+            };
+            if child.is_null() {
+                return Some((ptr, order));
+            }
+            ptr = child;
+        }
+    }
 }
 
 impl<K, V> TreeCleaner<K, V> for RBTree<K, V> {
@@ -213,11 +362,9 @@ impl<K, V> TreeCleaner<K, V> for RBTree<K, V> {
     where
         F: FnMut(Box<TreeNode<K, V>>),
     {
-        if self.root.is_null() {
-            return;
-        }
-        let mut ptr = self.root;
-        loop {
+        // walk the in-order list, an owning iterator has already dropped the root
+        let mut ptr = self.head;
+        while !ptr.is_null() {
             let mut node = unsafe { Box::from_raw(ptr) };
             ptr = node.next;
             node.right =  ptr::null_mut();
@@ -226,33 +373,116 @@ impl<K, V> TreeCleaner<K, V> for RBTree<K, V> {
             node.next = ptr::null_mut();
             node.prev = ptr::null_mut();
             transfer(node);  // move node ownership
-            if ptr.is_null() {
-                break;
-            }
-        }
-        let mut ptr = unsafe { (*self.root).prev };
-        while !ptr.is_null() {
-            let mut node = unsafe { Box::from_raw(ptr) };
-            node.right =  ptr::null_mut();
-            node.left =  ptr::null_mut();
-            node.parent = ptr::null_mut();
-            ptr = node.prev;
-            transfer(node);  // move node ownership
         }
         self.root = ptr::null_mut();
+        self.head = ptr::null_mut();
+        self.tail = ptr::null_mut();
+        self.size = 0;
     }
 }
 
 impl<K, V> RBTree<K, V> {
-    pub fn iter(&self) -> RBIter<K, V> {
+    pub fn iter(&self) -> RBIter<'_, K, V> {
+        self.into_iter()
+    }
+
+    pub fn iter_mut(&mut self) -> RBIterMut<'_, K, V> {
         self.into_iter()
     }
 
+    pub fn keys(&self) -> Keys<RBIter<'_, K, V>> {
+        Keys(self.iter())
+    }
+
+    pub fn values(&self) -> Values<RBIter<'_, K, V>> {
+        Values(self.iter())
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<RBIterMut<'_, K, V>> {
+        ValuesMut(self.iter_mut())
+    }
+
+    pub fn into_keys(self) -> IntoKeys<RBIntoIter<K, V>> {
+        IntoKeys(self.into_iter())
+    }
+
+    pub fn into_values(self) -> IntoValues<RBIntoIter<K, V>> {
+        IntoValues(self.into_iter())
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        Self::node_ref(self.first_node())
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        Self::node_ref(self.last_node())
+    }
+
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        let ptr = self.first_node();
+        if ptr.is_null() {
+            return None;
+        }
+        let node = self.remove_node(ptr);
+        Some((node.key, node.value))
+    }
+
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        let ptr = self.last_node();
+        if ptr.is_null() {
+            return None;
+        }
+        let node = self.remove_node(ptr);
+        Some((node.key, node.value))
+    }
+
+    /// keep only the entries for which `f` returns true, they are visited in ascending order
+    pub fn retain<F>(&mut self, mut f: F)
+    where F: FnMut(&K, &mut V) -> bool
+    {
+        let mut ptr = self.first_node();
+        while !ptr.is_null() {
+            let node = unsafe { &mut *ptr };
+            let next = node.next;
+            if !f(&node.key, &mut node.value) {
+                self.remove_node(ptr);
+            }
+            ptr = next;
+        }
+    }
+
+    #[inline]
+    pub(crate) fn first_node(&self) -> *mut TreeNode<K, V> {
+        self.head
+    }
+
+    #[inline]
+    fn last_node(&self) -> *mut TreeNode<K, V> {
+        self.tail
+    }
+
+    #[inline]
+    fn node_ref<'a>(ptr: *mut TreeNode<K, V>) -> Option<(&'a K, &'a V)> {
+        if ptr.is_null() {
+            return None;
+        }
+        let node = unsafe { &*ptr };
+        Some((&node.key, &node.value))
+    }
+
     fn create_node(&self, key: K, value: V, is_red: bool) -> *mut TreeNode<K, V> {
         Box::into_raw(Box::new(TreeNode::new(key, value, is_red)))
     }
 
+    fn insert_root(&mut self, root: *mut TreeNode<K, V>) {
+        self.root = root;
+        self.head = root;
+        self.tail = root;
+        self.size = 1;
+    }
+
     fn insert_right(&mut self, parent: &mut TreeNode<K, V>, new_node: &mut TreeNode<K, V>) {
+        self.size += 1;
         parent.right = new_node;
         new_node.parent = parent;
         // In-order link
@@ -266,7 +496,9 @@ impl<K, V> RBTree<K, V> {
     fn insert_right_in_order_link(&mut self, new_node: &mut TreeNode<K, V>, parent: &mut TreeNode<K, V>, ) {
         new_node.prev = parent;
         new_node.next = parent.next;
-        if !parent.next.is_null() {
+        if parent.next.is_null() {
+            self.tail = new_node;
+        } else {
             unsafe { (*parent.next).prev = new_node; }
         }
         parent.next = new_node;
@@ -275,13 +507,16 @@ impl<K, V> RBTree<K, V> {
     fn insert_left_in_order_link(&mut self, new_node: &mut TreeNode<K, V>, parent: &mut TreeNode<K, V>) {
         new_node.next = parent;
         new_node.prev = parent.prev;
-        if !parent.prev.is_null() {
+        if parent.prev.is_null() {
+            self.head = new_node;
+        } else {
             unsafe { (*parent.prev).next = new_node; }
         }
         parent.prev = new_node;
     }
 
     fn insert_left(&mut self, parent: &mut TreeNode<K, V>, new_node: &mut TreeNode<K, V>) {
+        self.size += 1;
         parent.left = new_node;
         new_node.parent = parent;
         // In-order link
@@ -311,7 +546,7 @@ impl<K, V> RBTree<K, V> {
                 }
             }
             node.parent = right_child;
-            if node as *mut TreeNode<K, V> == self.root {
+            if ptr::eq(node, self.root) {
                 self.root = right_child;
             }
         }
@@ -336,7 +571,7 @@ impl<K, V> RBTree<K, V> {
                 }
             }
             node.parent = left_child;
-            if node as *mut TreeNode<K, V> == self.root {
+            if ptr::eq(node, self.root) {
                 self.root = left_child;
             }
         }
@@ -358,14 +593,14 @@ impl<K, V> RBTree<K, V> {
                 parent = &mut *(*n).parent; //NotNULL
                 grand_parent = &mut *parent.parent; //NotNULL
                 let uncle = (*n).get_uncle();
-                if !(!uncle.is_null() && (*uncle).is_red) {
+                if uncle.is_null() || !(*uncle).is_red {
                     break
                 }
                 //Parent and uncle is red
                 parent.is_red = false;
                 (*uncle).is_red = false;
                 grand_parent.is_red = true;
-                if grand_parent as *mut TreeNode<K, V> == self.root {
+                if ptr::eq(grand_parent, self.root) {
                     grand_parent.is_red = false;
                     return;
                 } else if !grand_parent.parent.is_null() && (*(grand_parent.parent)).is_red {
@@ -406,88 +641,548 @@ impl<K, V> RBTree<K, V> {
         }
     }
 
+    /**
+    Unlink `ptr` from the tree and the in-order list, rebalance, and hand the node back to the caller.
+    A node with two children first trades places with its successor (`next`), so the node is moved
+    rather than its key and value, and pointers to other nodes stay valid.
+    */
+    pub(crate) fn remove_node(&mut self, ptr: *mut TreeNode<K, V>) -> Box<TreeNode<K, V>> {
+        unsafe {
+            let node = &mut *ptr;
+            if !node.left.is_null() && !node.right.is_null() {
+                self.swap_with_successor(node);
+            }
+            // In-order link
+            if node.prev.is_null() {
+                self.head = node.next;
+            } else {
+                (*node.prev).next = node.next;
+            }
+            if node.next.is_null() {
+                self.tail = node.prev;
+            } else {
+                (*node.next).prev = node.prev;
+            }
+            self.size -= 1;
+            // now node has at most one child
+            let replacement = if node.left.is_null() { node.right } else { node.left };
+            if !replacement.is_null() {
+                //a black node with a single red child
+                let parent = node.parent;
+                (*replacement).parent = parent;
+                if parent.is_null() {
+                    self.root = replacement;
+                } else if node.is_left_node() {
+                    (*parent).left = replacement;
+                } else {
+                    (*parent).right = replacement;
+                }
+                (*replacement).is_red = false;
+            } else if node.parent.is_null() {
+                self.root = ptr::null_mut();
+            } else {
+                if !node.is_red {
+                    self.balance_remove(node);
+                }
+                let parent = &mut *node.parent;
+                if node.is_left_node() {
+                    parent.left = ptr::null_mut();
+                } else {
+                    parent.right = ptr::null_mut();
+                }
+            }
+            node.left = ptr::null_mut();
+            node.right = ptr::null_mut();
+            node.parent = ptr::null_mut();
+            node.prev = ptr::null_mut();
+            node.next = ptr::null_mut();
+            Box::from_raw(ptr)
+        }
+    }
+
+    /**
+    Exchange the tree positions and colors of `node` and its in-order successor.
+    The successor is the leftmost node of the right subtree, so afterwards `node` has no left child.
+    */
+    fn swap_with_successor(&mut self, node: &mut TreeNode<K, V>) {
+        unsafe {
+            let succ = &mut *node.next;
+            Self::swap_color(node, succ);
+            let node_left = node.left;
+            let node_right = node.right;
+            let node_parent = node.parent;
+            let succ_right = succ.right;
+            if ptr::eq(succ, node_right) {
+                //succ is the direct right child
+                node.parent = succ;
+                succ.right = node;
+            } else {
+                let succ_parent = succ.parent;
+                node.parent = succ_parent;
+                if ptr::eq(succ, (*succ_parent).left) {
+                    (*succ_parent).left = node;
+                } else {
+                    (*succ_parent).right = node;
+                }
+                succ.right = node_right;
+                (*node_right).parent = succ;
+            }
+            node.left = ptr::null_mut();
+            node.right = succ_right;
+            if !succ_right.is_null() {
+                (*succ_right).parent = node;
+            }
+            succ.left = node_left;
+            (*node_left).parent = succ;
+            succ.parent = node_parent;
+            if node_parent.is_null() {
+                self.root = succ;
+            } else if ptr::eq(node, (*node_parent).left) {
+                (*node_parent).left = succ;
+            } else {
+                (*node_parent).right = succ;
+            }
+        }
+    }
+
+    /**
+    Restore the black height after a black leaf is removed.
+    `removed` is still attached to the tree while the fixup runs.
+    */
+    fn balance_remove(&mut self, removed: &mut TreeNode<K, V>) {
+        let mut n: *mut TreeNode<K, V> = removed;
+        unsafe {
+            while n != self.root && !(*n).is_red {
+                let parent = &mut *(*n).parent;
+                let is_left = (*n).is_left_node();
+                let mut brother = &mut *(*n).get_brother(); //NotNULL
+                if brother.is_red {
+                    //brother is red, turn it into a black brother case
+                    Self::swap_color(brother, parent);
+                    if is_left {
+                        self.rotate_left(parent);
+                    } else {
+                        self.rotate_right(parent);
+                    }
+                    brother = &mut *(*n).get_brother();
+                }
+                let (near, far) = if is_left {
+                    (brother.left, brother.right)
+                } else {
+                    (brother.right, brother.left)
+                };
+                let is_black = |x: *mut TreeNode<K, V>| x.is_null() || !(*x).is_red;
+                if is_black(near) && is_black(far) {
+                    //brother and both nephews are black
+                    brother.is_red = true;
+                    n = parent;
+                    continue;
+                }
+                if is_black(far) {
+                    //near nephew is red, far nephew is black
+                    Self::swap_color(&mut *near, brother);
+                    if is_left {
+                        self.rotate_right(brother);
+                    } else {
+                        self.rotate_left(brother);
+                    }
+                    brother = &mut *(*n).get_brother();
+                }
+                //far nephew is red
+                let far = if is_left { brother.right } else { brother.left };
+                Self::swap_color(brother, parent);
+                (*far).is_red = false;
+                if is_left {
+                    self.rotate_left(parent);
+                } else {
+                    self.rotate_right(parent);
+                }
+                n = self.root;
+            }
+            (*n).is_red = false;
+        }
+    }
+
     fn swap_color(n1: &mut TreeNode<K, V>, n2: &mut TreeNode<K, V>) {
-        let color = n1.is_red;
-        n1.is_red = n2.is_red;
-        n2.is_red = color;
+        std::mem::swap(&mut n1.is_red, &mut n2.is_red);
     }
 }
 
 impl<'a, K, V> IntoIterator for &'a RBTree<K, V> {
-    type Item = (&'a K, &'a mut V);
+    type Item = (&'a K, &'a V);
     type IntoIter = RBIter<'a, K, V>;
 
     fn into_iter(self) -> Self::IntoIter {
-        RBIter::new(self.root)
+        RBIter {
+            front: self.head,
+            back: self.tail,
+            remaining: self.size,
+            _marker: PhantomData,
+        }
     }
 }
 
-pub struct RBIter<'a, K, V> {
-    root: *mut TreeNode<K, V>,
-    current: *mut TreeNode<K, V>,
-    forward: bool, //true -> next，false -> prev
-    _marker: PhantomData<&'a mut (K, V)>,
-}
+impl<'a, K, V> IntoIterator for &'a mut RBTree<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = RBIterMut<'a, K, V>;
 
-impl<'a, K, V> RBIter<'a, K, V> {
-    fn new(root: *mut TreeNode<K, V>) -> Self {
-        RBIter {
-            root: root,
-            current: root,
-            forward: true,
+    fn into_iter(self) -> Self::IntoIter {
+        RBIterMut {
+            front: self.head,
+            back: self.tail,
+            remaining: self.size,
             _marker: PhantomData,
         }
     }
 }
 
+impl<K, V> IntoIterator for RBTree<K, V> {
+    type Item = (K, V);
+    type IntoIter = RBIntoIter<K, V>;
+
+    /// the tree shape is dropped here, the nodes are popped off the in-order list
+    fn into_iter(mut self) -> Self::IntoIter {
+        self.root = ptr::null_mut();
+        RBIntoIter { tree: self }
+    }
+}
+
+/// in-order iterator, walks `next` from the head and `prev` from the tail
+pub struct RBIter<'a, K, V> {
+    front: *mut TreeNode<K, V>,
+    back: *mut TreeNode<K, V>,
+    remaining: usize,
+    _marker: PhantomData<&'a (K, V)>,
+}
+
 impl<'a, K, V> Iterator for RBIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        unsafe {
+            let node = &*self.front;
+            self.front = node.next;
+            Some((&node.key, &node.value))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for RBIter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        unsafe {
+            let node = &*self.back;
+            self.back = node.prev;
+            Some((&node.key, &node.value))
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for RBIter<'_, K, V> {}
+
+/// same walk as `RBIter`, borrows the tree mutably to hand out `&mut V`
+pub struct RBIterMut<'a, K, V> {
+    front: *mut TreeNode<K, V>,
+    back: *mut TreeNode<K, V>,
+    remaining: usize,
+    _marker: PhantomData<&'a mut (K, V)>,
+}
+
+impl<'a, K, V> Iterator for RBIterMut<'a, K, V> {
     type Item = (&'a K, &'a mut V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.root.is_null() {
+        if self.remaining == 0 {
             return None;
         }
-        while self.current.is_null() {
-            if self.forward {
-                self.forward = false;
-                self.current = unsafe { (*self.root).prev };
-            } else {
-                return None;
-            }
+        self.remaining -= 1;
+        unsafe {
+            let node = &mut *self.front;
+            self.front = node.next;
+            Some((&node.key, &mut node.value))
         }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
 
+impl<'a, K, V> DoubleEndedIterator for RBIterMut<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
         unsafe {
-            let node = &mut *self.current;
-            self.current = if self.forward { node.next } else { node.prev };
+            let node = &mut *self.back;
+            self.back = node.prev;
             Some((&node.key, &mut node.value))
         }
     }
 }
 
+impl<K, V> ExactSizeIterator for RBIterMut<'_, K, V> {}
+
+/// owning in-order iterator, the remaining nodes are freed by the tree's Drop
+pub struct RBIntoIter<K, V> {
+    tree: RBTree<K, V>,
+}
+
+impl<K, V> Iterator for RBIntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = &mut self.tree;
+        if tree.head.is_null() {
+            return None;
+        }
+        let node = unsafe { Box::from_raw(tree.head) };
+        tree.head = node.next;
+        if tree.head.is_null() {
+            tree.tail = ptr::null_mut();
+        } else {
+            unsafe { (*tree.head).prev = ptr::null_mut() };
+        }
+        tree.size -= 1;
+        Some((node.key, node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.tree.size, Some(self.tree.size))
+    }
+}
+
+impl<K, V> DoubleEndedIterator for RBIntoIter<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let tree = &mut self.tree;
+        if tree.tail.is_null() {
+            return None;
+        }
+        let node = unsafe { Box::from_raw(tree.tail) };
+        tree.tail = node.prev;
+        if tree.tail.is_null() {
+            tree.head = ptr::null_mut();
+        } else {
+            unsafe { (*tree.tail).next = ptr::null_mut() };
+        }
+        tree.size -= 1;
+        Some((node.key, node.value))
+    }
+}
+
+impl<K, V> ExactSizeIterator for RBIntoIter<K, V> {}
+
+pub struct RBRange<'a, K, V> {
+    front: *mut TreeNode<K, V>,
+    back: *mut TreeNode<K, V>,
+    _marker: PhantomData<&'a (K, V)>,
+}
+
+impl<'a, K, V> RBRange<'a, K, V> {
+    fn new(front: *mut TreeNode<K, V>, back: *mut TreeNode<K, V>) -> Self {
+        RBRange {
+            front: front,
+            back: back,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for RBRange<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front.is_null() {
+            return None;
+        }
+        let node = unsafe { &*self.front };
+        if self.front == self.back {
+            self.front = ptr::null_mut();
+            self.back = ptr::null_mut();
+        } else {
+            self.front = node.next;
+        }
+        Some((&node.key, &node.value))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for RBRange<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.back.is_null() {
+            return None;
+        }
+        let node = unsafe { &*self.back };
+        if self.front == self.back {
+            self.front = ptr::null_mut();
+            self.back = ptr::null_mut();
+        } else {
+            self.back = node.prev;
+        }
+        Some((&node.key, &node.value))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K: serde::Serialize, V: serde::Serialize> serde::Serialize for RBTree<K, V> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V> serde::Deserialize<'de> for RBTree<K, V>
+where K: serde::Deserialize<'de> + Ord, V: serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TreeVisitor<K, V>(PhantomData<fn() -> (K, V)>);
+
+        impl<'de, K, V> serde::de::Visitor<'de> for TreeVisitor<K, V>
+        where K: serde::Deserialize<'de> + Ord, V: serde::Deserialize<'de>,
+        {
+            type Value = RBTree<K, V>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("a map")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, mut access: A) -> Result<RBTree<K, V>, A::Error> {
+                let mut tree = RBTree::new();
+                while let Some((key, value)) = access.next_entry()? {
+                    tree.put(key, value);
+                }
+                return Ok(tree);
+            }
+        }
+
+        deserializer.deserialize_map(TreeVisitor(PhantomData))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::marker::PhantomData;
+    use std::ptr;
     use crate::collection::tree::RBTree;
+    use crate::collection::tree::TreeNode;
+
+    /// check the red-black rules, parent links and the `prev`/`next` list, return the in-order keys
+    fn check_tree<K: Ord + Copy, V>(tree: &RBTree<K, V>) -> Vec<K> {
+        fn black_height<K: Ord + Copy, V>(ptr: *mut TreeNode<K, V>, keys: &mut Vec<K>) -> usize {
+            if ptr.is_null() {
+                return 1;
+            }
+            let node = unsafe { &*ptr };
+            for child in [node.left, node.right] {
+                if !child.is_null() {
+                    assert!(ptr::eq(unsafe { (*child).parent }, ptr), "broken parent link");
+                    assert!(!(node.is_red && unsafe { (*child).is_red }), "red node with red child");
+                }
+            }
+            let left = black_height(node.left, keys);
+            keys.push(node.key);
+            let right = black_height(node.right, keys);
+            assert_eq!(left, right, "black height mismatch");
+            left + if node.is_red { 0 } else { 1 }
+        }
+
+        let mut keys = Vec::new();
+        if tree.root.is_null() {
+            assert!(tree.head.is_null() && tree.tail.is_null() && tree.size == 0);
+            return keys;
+        }
+        assert!(unsafe { !(*tree.root).is_red && (*tree.root).parent.is_null() });
+        black_height(tree.root, &mut keys);
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+
+        // walk the in-order links from the first node
+        let mut ptr = tree.head;
+        let mut linked = Vec::new();
+        let mut prev: *mut TreeNode<K, V> = ptr::null_mut();
+        while !ptr.is_null() {
+            let node = unsafe { &*ptr };
+            assert!(ptr::eq(node.prev, prev), "broken prev link");
+            linked.push(node.key);
+            prev = ptr;
+            ptr = node.next;
+        }
+        assert!(linked == keys, "in-order links do not match the tree");
+        assert!(ptr::eq(prev, tree.tail), "broken tail");
+        assert_eq!(tree.size, keys.len());
+        keys
+    }
 
     #[test]
     fn test_base() {
         let mut tree = RBTree::<i32, PhantomData<i32>>::new();
-        let arr = vec![5,3,7,2,4,6,8];   //
+        let arr = [5,3,7,2,4,6,8];   //
         for i in arr.iter() {
-            tree.put(i.clone(), PhantomData);
+            tree.put(*i, PhantomData);
         }
 
-        let mut ptr = tree.root;
+        let mut ptr = tree.head;
         while !ptr.is_null() {
             let node = unsafe { &*ptr };
             println!("{:?}", node.key);
             ptr = node.next;
         }
-        let mut ptr = tree.root;
+        let mut ptr = tree.tail;
         while !ptr.is_null() {
             let node = unsafe { &*ptr };
             println!("{:?}", node.key);
             ptr = node.prev;
         }
+        assert_eq!(check_tree(&tree), vec![2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_remove_rebalance() {
+        let mut tree = RBTree::<u32, u32>::new();
+        let mut keys: Vec<u32> = (0..512).map(|x| x * 7919 % 1024).collect();
+        for k in keys.iter() {
+            tree.put(*k, *k * 10);
+        }
+        check_tree(&tree);
+
+        // remove every other key in a scattered order, verifying the tree after each step
+        keys.sort_by_key(|x| x.wrapping_mul(2654435761) % 1031);
+        let (removed, kept) = keys.split_at(keys.len() / 2);
+        for k in removed {
+            assert_eq!(tree.remove(k), Some(*k * 10));
+            assert_eq!(tree.remove(k), None);
+            check_tree(&tree);
+        }
+        let mut expected = kept.to_vec();
+        expected.sort();
+        assert_eq!(check_tree(&tree), expected);
+
+        for k in kept {
+            assert_eq!(tree.remove(k), Some(*k * 10));
+        }
+        assert!(tree.root.is_null());
+    }
+
+    #[test]
+    fn test_remove_if() {
+        let mut tree = RBTree::<i32, i32>::new();
+        for i in 0..32 {
+            tree.put(i, i);
+        }
+        assert_eq!(tree.remove_if(&3, |v| *v > 10), None);
+        assert_eq!(tree.remove_if(&100, |_| true), None);
+        assert_eq!(tree.remove_if(&3, |v| *v == 3), Some(3));
+        assert!(!tree.contains(&3));
+        assert_eq!(check_tree(&tree).len(), 31);
     }
 }
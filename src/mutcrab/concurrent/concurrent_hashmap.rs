@@ -0,0 +1,450 @@
+use crate::base::numbers::next_power_of_two;
+use crate::collection::map::{make_hash, make_hasher, BoxAllocator, Bucket, EntryNode, KeyOrder, NodeAllocator};
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, Hash, RandomState};
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicIsize, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{OnceLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+const DEFAULT_INITIAL_CAPACITY: usize = 16;
+
+/// number of bins a thread claims at once while it helps a resize
+const TRANSFER_STRIDE: usize = 16;
+
+/// the nodes move between the bins of two tables, so each one is a `Box` of its own
+enum Bin<K, V> {
+    Nodes(Bucket<K, V>, BoxAllocator<EntryNode<K, V>>),
+    /// the nodes were moved to the `next` table of the table that holds this bin
+    Moved,
+}
+
+impl<K, V> Bin<K, V> {
+    fn new() -> Self {
+        Bin::Nodes(Bucket::new_list(), BoxAllocator::new())
+    }
+}
+
+/**
+One generation of bins. A resize creates `next` with twice the bins and moves the bins over one at
+a time, a moved bin is left as `Bin::Moved`. The table is replaced in the map once every bin is moved.
+A table owns its `next` one, so the first table holds every generation until the map is dropped.
+*/
+struct Table<K, V> {
+    bins: Box<[RwLock<Bin<K, V>>]>,
+    threshold: usize,
+    next: OnceLock<Box<Table<K, V>>>,
+    // the next bin to hand out to a transferring thread
+    transfer_index: AtomicUsize,
+    // the number of bins that are moved
+    transferred: AtomicUsize,
+}
+
+impl<K, V> Table<K, V> {
+    fn new(capacity: usize) -> Self {
+        Table {
+            bins: (0..capacity).map(|_| RwLock::new(Bin::new())).collect(),
+            threshold: capacity - capacity / 4,
+            next: OnceLock::new(),
+            transfer_index: AtomicUsize::new(0),
+            transferred: AtomicUsize::new(0),
+        }
+    }
+
+    fn read(&self, index: usize) -> RwLockReadGuard<'_, Bin<K, V>> {
+        // a panicking user function leaves the bin untouched, so a poisoned lock is still consistent
+        self.bins[index].read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self, index: usize) -> RwLockWriteGuard<'_, Bin<K, V>> {
+        self.bins[index].write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<K, V> Drop for Table<K, V> {
+    fn drop(&mut self) {
+        // the buckets do not own their nodes, a `Moved` bin has given its nodes to `next`
+        for bin in self.bins.iter_mut() {
+            if let Bin::Nodes(bucket, allocator) = bin.get_mut().unwrap_or_else(PoisonError::into_inner) {
+                let mut ptr = bucket.take_list();
+                while !ptr.is_null() {
+                    let node = unsafe { allocator.free(NonNull::new_unchecked(ptr)) };
+                    ptr = node.next;
+                }
+            }
+        }
+    }
+}
+
+/**
+A hash map that can be shared between threads, in the style of Java's `ConcurrentHashMap`.
+Every bin is a list or tree `Bucket` behind its own lock, so threads that work on different bins do
+not wait for each other. A resize moves the bins to a table twice as large while the map stays in
+use, threads that meet a moved bin help with the move before they continue in the new table.
+
+The functions passed to `compute`, `compute_if_absent`, `compute_if_present` and `merge` run once
+while the bin is locked, so they are atomic for their key but must not use the map themselves.
+Values are returned as clones because the entry may be replaced as soon as the lock is released.
+
+The installed table is published through an atomic pointer, so a lookup only locks its bin. A
+replaced table is not freed while the map is in use, a thread may still be reading it. Its bins
+are all moved by then, so the old tables together take less room than the current one.
+
+Keys made to share the full hash end up in one tree bin. With `set_ordered_collisions` the tree
+bins order them by `Ord`, so the lookups, which take an `Ord` key, search them in log time.
+*/
+pub struct ConcurrentHashMap<K, V, S = RandomState> {
+    // the first table, it owns the later ones through `next`
+    root: NonNull<Table<K, V>>,
+    // the installed table, `root` or one of its successors
+    table: AtomicPtr<Table<K, V>>,
+    count: AtomicIsize,
+    hash_builder: S,
+    // orders the keys of one hash in the tree bins
+    key_order: Option<KeyOrder<K>>,
+    _marker: PhantomData<Box<Table<K, V>>>,
+}
+
+// every thread that holds the map reaches the entries, so they must be `Send` and `Sync` for either
+unsafe impl<K: Send + Sync, V: Send + Sync, S: Send> Send for ConcurrentHashMap<K, V, S> {}
+unsafe impl<K: Send + Sync, V: Send + Sync, S: Sync> Sync for ConcurrentHashMap<K, V, S> {}
+
+impl<K, V, S> Drop for ConcurrentHashMap<K, V, S> {
+    fn drop(&mut self) {
+        drop(unsafe { Box::from_raw(self.root.as_ptr()) });
+    }
+}
+
+impl<K, V> ConcurrentHashMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self::with_capacity_hasher(DEFAULT_INITIAL_CAPACITY, Default::default())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_hasher(capacity, Default::default())
+    }
+}
+
+impl<K, V, S> ConcurrentHashMap<K, V, S> {
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::with_capacity_hasher(DEFAULT_INITIAL_CAPACITY, hash_builder)
+    }
+
+    /// room for `capacity` entries before the first resize
+    pub fn with_capacity_hasher(capacity: usize, hash_builder: S) -> Self {
+        let bins = capacity.checked_add(capacity / 3 + 1).expect("capacity overflow");
+        let root = NonNull::from(Box::leak(Box::new(Table::new(next_power_of_two(bins).max(2)))));
+        ConcurrentHashMap {
+            root: root,
+            table: AtomicPtr::new(root.as_ptr()),
+            count: AtomicIsize::new(0),
+            hash_builder: hash_builder,
+            key_order: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// the number of entries, it may be out of date while other threads modify the map
+    pub fn len(&self) -> usize {
+        self.count.load(Ordering::Relaxed).max(0) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_ordered_collisions(&self) -> bool {
+        self.key_order.is_some()
+    }
+
+    fn current(&self) -> &Table<K, V> {
+        // the tables live until the map is dropped
+        unsafe { &*self.table.load(Ordering::Acquire) }
+    }
+}
+
+impl<K, V, S> ConcurrentHashMap<K, V, S>
+where K: Hash + Eq, S: BuildHasher,
+{
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Ord, V: Clone,
+    {
+        let hash = make_hash(&self.hash_builder, key);
+        self.read_bin(hash, |bucket| unsafe { bucket.find_ordered(hash, key).as_ref() }.map(|node| node.value.clone()))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where K: Borrow<Q>, Q: ?Sized + Hash + Ord,
+    {
+        let hash = make_hash(&self.hash_builder, key);
+        self.read_bin(hash, |bucket| !bucket.find_ordered(hash, key).is_null())
+    }
+
+    /// insert or replace the value, the old value is returned
+    pub fn put(&self, key: K, value: V) -> Option<V> {
+        let hash = make_hash(&self.hash_builder, &key);
+        let old = self.write_bin(hash, |bucket, allocator| {
+            bucket.write(hash, key, value, allocator, make_hasher(&self.hash_builder), self.key_order)
+        });
+        if old.is_none() {
+            self.add_count(1);
+        }
+        old
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.put(key, value)
+    }
+
+    /// insert the value only if the key is absent, otherwise the current value is returned
+    pub fn put_if_absent(&self, key: K, value: V) -> Option<V>
+    where V: Clone,
+    {
+        let hash = make_hash(&self.hash_builder, &key);
+        let current = self.write_bin(hash, |bucket, allocator| {
+            let node = bucket.find_key(hash, &key);
+            if !node.is_null() {
+                return Some(unsafe { (*node).value.clone() });
+            }
+            bucket.write(hash, key, value, allocator, make_hasher(&self.hash_builder), self.key_order);
+            None
+        });
+        if current.is_none() {
+            self.add_count(1);
+        }
+        current
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Ord,
+    {
+        let hash = make_hash(&self.hash_builder, key);
+        let old = self.write_bin(hash, |bucket, allocator| bucket.remove_ordered(hash, key, allocator));
+        if old.is_some() {
+            self.add_count(-1);
+        }
+        old
+    }
+
+    /**
+    Replace the value of `key` by `f(key, current value)`, the entry is removed when `f` returns `None`.
+    Returns the new value.
+    */
+    pub fn compute<F>(&self, key: K, f: F) -> Option<V>
+    where F: FnOnce(&K, Option<&V>) -> Option<V>, V: Clone,
+    {
+        let hash = make_hash(&self.hash_builder, &key);
+        let (value, delta) = self.write_bin(hash, |bucket, allocator| {
+            let node = bucket.find_key(hash, &key);
+            if node.is_null() {
+                return match f(&key, None) {
+                    Some(value) => {
+                        bucket.write(hash, key, value.clone(), allocator, make_hasher(&self.hash_builder), self.key_order);
+                        (Some(value), 1)
+                    }
+                    None => (None, 0),
+                };
+            }
+            Self::remap(bucket, allocator, hash, node, |k, v| f(k, Some(v)))
+        });
+        if delta != 0 {
+            self.add_count(delta);
+        }
+        value
+    }
+
+    /// insert `f(key)` if the key is absent, returns the current or the new value
+    pub fn compute_if_absent<F>(&self, key: K, f: F) -> V
+    where F: FnOnce(&K) -> V, V: Clone,
+    {
+        let hash = make_hash(&self.hash_builder, &key);
+        let (value, inserted) = self.write_bin(hash, |bucket, allocator| {
+            let node = bucket.find_key(hash, &key);
+            if !node.is_null() {
+                return (unsafe { (*node).value.clone() }, false);
+            }
+            let value = f(&key);
+            bucket.write(hash, key, value.clone(), allocator, make_hasher(&self.hash_builder), self.key_order);
+            (value, true)
+        });
+        if inserted {
+            self.add_count(1);
+        }
+        value
+    }
+
+    /// replace the value of a present key by `f(key, value)`, or remove it when `f` returns `None`
+    pub fn compute_if_present<Q, F>(&self, key: &Q, f: F) -> Option<V>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Ord, F: FnOnce(&K, &V) -> Option<V>, V: Clone,
+    {
+        let hash = make_hash(&self.hash_builder, key);
+        let (value, delta) = self.write_bin(hash, |bucket, allocator| {
+            let node = bucket.find_ordered(hash, key);
+            if node.is_null() {
+                return (None, 0);
+            }
+            Self::remap(bucket, allocator, hash, node, f)
+        });
+        if delta != 0 {
+            self.add_count(delta);
+        }
+        value
+    }
+
+    /// insert `value` for an absent key, otherwise replace the value by `f(current, value)`
+    /// or remove it when `f` returns `None`. Returns the new value.
+    pub fn merge<F>(&self, key: K, value: V, f: F) -> Option<V>
+    where F: FnOnce(&V, V) -> Option<V>, V: Clone,
+    {
+        let hash = make_hash(&self.hash_builder, &key);
+        let (value, delta) = self.write_bin(hash, |bucket, allocator| {
+            let node = bucket.find_key(hash, &key);
+            if node.is_null() {
+                bucket.write(hash, key, value.clone(), allocator, make_hasher(&self.hash_builder), self.key_order);
+                return (Some(value), 1);
+            }
+            Self::remap(bucket, allocator, hash, node, |_, current| f(current, value))
+        });
+        if delta != 0 {
+            self.add_count(delta);
+        }
+        value
+    }
+
+    /// set the value of `node` to `f(key, value)`, `None` unlinks the node. Returns the new value and the change of `len`
+    fn remap<F>(bucket: &mut Bucket<K, V>, allocator: &mut BoxAllocator<EntryNode<K, V>>, hash: u64, node: *mut EntryNode<K, V>, f: F) -> (Option<V>, isize)
+    where F: FnOnce(&K, &V) -> Option<V>, V: Clone,
+    {
+        let node = unsafe { &mut *node };
+        match f(&node.key, &node.value) {
+            Some(value) => {
+                node.value = value.clone();
+                (Some(value), 0)
+            }
+            None => {
+                let key: *const K = &node.key;
+                bucket.unlink(hash, key, allocator);
+                (None, -1)
+            }
+        }
+    }
+
+    fn read_bin<R>(&self, hash: u64, f: impl FnOnce(&Bucket<K, V>) -> R) -> R {
+        let mut table = self.current();
+        loop {
+            let bin = table.read(hash as usize & (table.bins.len() - 1));
+            if let Bin::Nodes(bucket, _) = &*bin {
+                return f(bucket);
+            }
+            drop(bin);
+            table = self.forward(table);
+        }
+    }
+
+    fn write_bin<R>(&self, hash: u64, f: impl FnOnce(&mut Bucket<K, V>, &mut BoxAllocator<EntryNode<K, V>>) -> R) -> R {
+        let mut table = self.current();
+        loop {
+            let mut bin = table.write(hash as usize & (table.bins.len() - 1));
+            if let Bin::Nodes(bucket, allocator) = &mut *bin {
+                return f(bucket, allocator);
+            }
+            drop(bin);
+            table = self.forward(table);
+        }
+    }
+
+    /// help the resize of `table` and continue in the table its bins are moved to
+    fn forward<'a>(&self, table: &'a Table<K, V>) -> &'a Table<K, V> {
+        self.transfer(table);
+        table.next.get().expect("a moved bin without a next table")
+    }
+
+    fn add_count(&self, delta: isize) {
+        let count = self.count.fetch_add(delta, Ordering::Relaxed) + delta;
+        if delta > 0 {
+            let table = self.current();
+            if count > table.threshold as isize {
+                self.transfer(table);
+            }
+        }
+    }
+
+    /**
+    Move the bins of `table` to its `next` table, creating it on the first call. Every caller claims
+    `TRANSFER_STRIDE` bins at a time until all are handed out, and the thread that moves the
+    last bin installs the new table. Only the installed table is resized, so `next` never has moved bins.
+    */
+    fn transfer(&self, table: &Table<K, V>) {
+        let n = table.bins.len();
+        let next = table.next.get_or_init(|| Box::new(Table::new(n.checked_mul(2).expect("capacity overflow"))));
+        loop {
+            let start = table.transfer_index.fetch_add(TRANSFER_STRIDE, Ordering::AcqRel);
+            if start >= n {
+                return;
+            }
+            let end = (start + TRANSFER_STRIDE).min(n);
+            for i in start..end {
+                self.move_bin(table, next, i);
+            }
+            if table.transferred.fetch_add(end - start, Ordering::AcqRel) + (end - start) == n {
+                let installed = self.table.swap(&**next as *const Table<K, V> as *mut Table<K, V>, Ordering::AcqRel);
+                debug_assert!(std::ptr::eq(installed, table), "only the installed table is resized");
+            }
+        }
+    }
+
+    /// split bin `i` into bins `i` and `i + n` of `next`, no other thread reaches them before the bin is `Moved`
+    fn move_bin(&self, table: &Table<K, V>, next: &Table<K, V>, i: usize) {
+        let n = table.bins.len();
+        let mut bin = table.write(i);
+        let mut bucket = match mem::replace(&mut *bin, Bin::Moved) {
+            Bin::Nodes(bucket, _) => bucket,
+            Bin::Moved => unreachable!("bin {} is moved twice", i),
+        };
+        let hasher = make_hasher(&self.hash_builder);
+        let (mut lo, mut hi) = (Bucket::new_list(), Bucket::new_list());
+        let (mut lo_size, mut hi_size) = (0, 0);
+        let mut ptr = bucket.take_list();
+        while !ptr.is_null() {
+            let node = unsafe { &mut *ptr };
+            ptr = node.next;
+            let hash = hasher(&node.key);
+            if hash as usize & n == 0 {
+                lo.push(hash, node);
+                lo_size += 1;
+            } else {
+                hi.push(hash, node);
+                hi_size += 1;
+            }
+        }
+        lo.treeify_if_long(&hasher, self.key_order);
+        hi.treeify_if_long(&hasher, self.key_order);
+        *next.write(i) = Bin::Nodes(lo, BoxAllocator::with_size(lo_size));
+        *next.write(i + n) = Bin::Nodes(hi, BoxAllocator::with_size(hi_size));
+    }
+}
+
+impl<K, V, S> ConcurrentHashMap<K, V, S>
+where K: Hash + Ord, S: BuildHasher,
+{
+    /// order the keys of one hash in the tree bins by `Ord`, like `TreeHashMap::set_ordered_collisions`
+    pub fn set_ordered_collisions(&mut self, ordered: bool) {
+        self.key_order = if ordered { Some(K::cmp) } else { None };
+        // no other thread holds the map, so every bin of the installed table has its nodes
+        let table = self.current();
+        for i in 0..table.bins.len() {
+            if let Bin::Nodes(bucket, _) = &mut *table.write(i) {
+                bucket.set_key_order(self.key_order);
+            }
+        }
+    }
+}
+
+impl<K, V, S> Default for ConcurrentHashMap<K, V, S>
+where S: Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
@@ -1,6 +1,14 @@
-
 mod thread_pool;
+mod concurrent_hashmap;
 
 pub mod thread {
     pub use super::thread_pool::ThreadPool;
 }
+
+pub use concurrent_hashmap::ConcurrentHashMap;
+
+// the map is shared by reference, every bin is behind its own lock
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ConcurrentHashMap<String, Vec<u8>>>();
+};
@@ -0,0 +1,168 @@
+use mutcrab::collection::map::{BoxAllocator, EntryNode, HashMap, Map, NodeAllocator, SlabAllocator, TreeHashMap};
+use std::hash::{BuildHasherDefault, RandomState};
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+mod common;
+use common::ModHasher;
+
+/// counts every call and hands the work to a `BoxAllocator`
+struct CountingAllocator<T> {
+    inner: BoxAllocator<T>,
+    allocs: Arc<AtomicUsize>,
+    frees: Arc<AtomicUsize>,
+}
+
+impl<T> CountingAllocator<T> {
+    fn new() -> Self {
+        CountingAllocator { inner: BoxAllocator::new(), allocs: Arc::default(), frees: Arc::default() }
+    }
+}
+
+unsafe impl<T> NodeAllocator<T> for CountingAllocator<T> {
+    fn alloc(&mut self, obj: T) -> NonNull<T> {
+        self.allocs.fetch_add(1, Ordering::Relaxed);
+        self.inner.alloc(obj)
+    }
+
+    unsafe fn free(&mut self, ptr: NonNull<T>) -> T {
+        self.frees.fetch_add(1, Ordering::Relaxed);
+        unsafe { self.inner.free(ptr) }
+    }
+
+    fn size(&self) -> usize {
+        self.inner.size()
+    }
+}
+
+#[test]
+fn slab_reuse_test() {
+    let mut slab = SlabAllocator::new();
+    let a = slab.alloc(1);
+    let b = slab.alloc(2);
+    assert_eq!(slab.size(), 2);
+    assert_eq!(unsafe { slab.free(a) }, 1);
+    // the freed slot is handed out first
+    let c = slab.alloc(3);
+    assert_eq!(c, a);
+    assert_eq!(unsafe { *b.as_ref() + *c.as_ref() }, 5);
+    unsafe {
+        slab.free(b);
+        slab.free(c);
+    }
+    assert_eq!(slab.size(), 0);
+}
+
+#[test]
+fn slab_churn_test() {
+    let mut map = HashMap::<u32, String>::new();
+    for i in 0..1000 {
+        map.put(i, i.to_string());
+    }
+    let slots = map.allocator().slots();
+    assert!(slots >= 1000);
+    // removed nodes go back to the free list, so the churn needs no new chunk
+    for round in 1..20 {
+        for i in 0..1000 {
+            assert_eq!(map.remove(&i), Some((i + (round - 1) * 1000).to_string()));
+            map.put(i, (i + round * 1000).to_string());
+        }
+    }
+    assert_eq!(map.allocator().slots(), slots);
+    assert_eq!(map.allocator().size(), 1000);
+    map.retain(|k, _| k % 2 == 0);
+    map.extend((1000..1500).map(|i| (i, i.to_string())));
+    assert_eq!(map.allocator().slots(), slots);
+    assert_eq!(map.len(), 1000);
+}
+
+#[test]
+fn slab_shrink_test() {
+    let mut slab = SlabAllocator::new();
+    let nodes: Vec<_> = (0..100).map(|i| slab.alloc(i)).collect();
+    let chunks = slab.chunks();
+    assert!(chunks > 1);
+    // the first chunks only hold freed slots, the last node keeps its chunk
+    for node in &nodes[..99] {
+        unsafe { slab.free(*node) };
+    }
+    slab.shrink_to_fit();
+    assert_eq!(slab.chunks(), 1);
+    assert!(slab.slots() < 100);
+    let node = slab.alloc(100);
+    unsafe {
+        assert_eq!(slab.free(nodes[99]), 99);
+        assert_eq!(slab.free(node), 100);
+    }
+    slab.shrink_to_fit();
+    assert_eq!((slab.chunks(), slab.slots()), (0, 0));
+}
+
+#[test]
+fn map_shrink_releases_chunks_test() {
+    let mut map = HashMap::<u32, u32>::new();
+    let mut tree_map = TreeHashMap::<u32, u32>::new();
+    for i in 0..2000 {
+        map.put(i, i);
+        tree_map.put(i, i);
+    }
+    let chunks = map.allocator().chunks();
+    assert_eq!(tree_map.allocator().chunks(), chunks);
+    // the oldest nodes fill the first chunks
+    map.retain(|k, _| *k >= 1500);
+    tree_map.retain(|k, _| *k >= 1500);
+    map.shrink_to_fit();
+    tree_map.shrink_to_fit();
+    assert!(map.allocator().chunks() < chunks);
+    assert!(tree_map.allocator().chunks() < chunks);
+    assert!((1500..2000).all(|i| map.get(&i) == Some(&i) && tree_map.get(&i) == Some(&i)));
+    map.clear();
+    tree_map.clear();
+    assert_eq!(map.allocator().chunks(), 0);
+    assert_eq!(tree_map.allocator().chunks(), 0);
+    map.put(1, 1);
+    assert_eq!(map.get(&1), Some(&1));
+}
+
+#[test]
+fn custom_allocator_test() {
+    let allocator = CountingAllocator::<EntryNode<i32, i32>>::new();
+    let (allocs, frees) = (allocator.allocs.clone(), allocator.frees.clone());
+    let mut map = HashMap::with_hasher_in(RandomState::new(), allocator);
+    for i in 0..100 {
+        map.put(i, i);
+    }
+    for i in 0..50 {
+        assert_eq!(map.remove(&i), Some(i));
+    }
+    assert_eq!(map.allocator().size(), 50);
+    assert_eq!(map.iter().count(), 50);
+    drop(map);
+    assert_eq!(allocs.load(Ordering::Relaxed), 100);
+    assert_eq!(frees.load(Ordering::Relaxed), 100);
+}
+
+#[test]
+fn tree_map_allocator_test() {
+    let allocator = CountingAllocator::<EntryNode<i32, Rc<i32>>>::new();
+    let (allocs, frees) = (allocator.allocs.clone(), allocator.frees.clone());
+    let value = Rc::new(0);
+    // four hashes, so every bucket turns into a tree
+    let mut map = TreeHashMap::with_capacity_hasher_in(4, BuildHasherDefault::<ModHasher<4>>::default(), allocator);
+    for i in 0..200 {
+        map.put(i, value.clone());
+    }
+    assert_eq!(map.extract_if(|k, _| k % 3 == 0).count(), 67);
+    assert_eq!(Rc::strong_count(&value), 134);
+    let copy: TreeHashMap<i32, Rc<i32>> = map.iter().map(|(k, v)| (*k, v.clone())).collect();
+    assert_eq!(copy.allocator().size(), 133);
+    drop(copy);
+    let mut iter = map.into_iter();
+    iter.next();
+    drop(iter);
+    assert_eq!(Rc::strong_count(&value), 1);
+    assert_eq!(allocs.load(Ordering::Relaxed), 200);
+    assert_eq!(frees.load(Ordering::Relaxed), 200);
+}
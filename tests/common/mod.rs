@@ -1 +1,55 @@
-pub mod ptrbox;
\ No newline at end of file
+// each test crate uses its own part of the fixtures
+#![allow(dead_code)]
+
+use std::hash::Hasher;
+
+pub mod ptrbox;
+
+/// hashes a `u32` or `u64` key to its own value, so a test picks the bucket of every key
+#[derive(Default)]
+pub struct IntegerHasher(u64);
+
+impl Hasher for IntegerHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = match bytes.len() {
+            4 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64,
+            8 => u64::from_le_bytes(bytes.try_into().unwrap()),
+            _ => panic!("Invalid byte length: {}. Only 4 or 8 byte lengths are supported.", bytes.len()),
+        }
+    }
+}
+
+/**
+Folds the key bytes into a number and hashes it to that number modulo `M`, times `SCALE`. A small
+`M` makes many keys share a hash, and a `SCALE` of the bucket count puts every hash in bucket 0.
+*/
+#[derive(Default)]
+pub struct ModHasher<const M: u64, const SCALE: u64 = 1>(u64);
+
+impl<const M: u64, const SCALE: u64> Hasher for ModHasher<M, SCALE> {
+    fn finish(&self) -> u64 {
+        self.0 % M * SCALE
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 = self.0.wrapping_mul(31).wrapping_add(*b as u64);
+        }
+    }
+}
+
+/// every key gets the full hash `H`, the worst case of a flooded map
+#[derive(Default)]
+pub struct ConstHasher<const H: u64>;
+
+impl<const H: u64> Hasher for ConstHasher<H> {
+    fn finish(&self) -> u64 {
+        H
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {}
+}
@@ -0,0 +1,145 @@
+use mutcrab::concurrent::ConcurrentHashMap;
+use std::hash::BuildHasherDefault;
+use std::sync::Arc;
+use std::thread;
+
+mod common;
+use common::ModHasher;
+
+#[test]
+fn basic_test() {
+    let map = ConcurrentHashMap::<String, i32>::new();
+    assert!(map.is_empty());
+    assert_eq!(map.put("a".to_string(), 1), None);
+    assert_eq!(map.put("a".to_string(), 2), Some(1));
+    assert_eq!(map.get("a"), Some(2));
+    assert!(map.contains_key("a"));
+    assert_eq!(map.put_if_absent("a".to_string(), 3), Some(2));
+    assert_eq!(map.put_if_absent("b".to_string(), 3), None);
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.remove("a"), Some(2));
+    assert_eq!(map.remove("a"), None);
+    assert_eq!(map.get("a"), None);
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn compute_test() {
+    let map = ConcurrentHashMap::<&str, i32>::new();
+    assert_eq!(map.compute("a", |_, v| v.map(|v| v + 1)), None);
+    assert_eq!(map.len(), 0);
+    assert_eq!(map.compute("a", |_, v| Some(v.map_or(1, |v| v + 1))), Some(1));
+    assert_eq!(map.compute("a", |_, v| Some(v.map_or(1, |v| v + 1))), Some(2));
+    assert_eq!(map.compute("a", |_, _| None), None);
+    assert_eq!(map.len(), 0);
+
+    assert_eq!(map.compute_if_absent("b", |_| 10), 10);
+    assert_eq!(map.compute_if_absent("b", |_| panic!("b is present")), 10);
+    assert_eq!(map.compute_if_present("c", |_, v| Some(v + 1)), None);
+    assert_eq!(map.compute_if_present("b", |_, v| Some(v + 1)), Some(11));
+    assert_eq!(map.compute_if_present("b", |_, _| None), None);
+    assert!(!map.contains_key("b"));
+
+    assert_eq!(map.merge("c", 1, |old, new| Some(old + new)), Some(1));
+    assert_eq!(map.merge("c", 5, |old, new| Some(old + new)), Some(6));
+    assert_eq!(map.merge("c", 5, |_, _| None), None);
+    assert!(map.is_empty());
+}
+
+#[test]
+fn concurrent_put_resize_test() {
+    let map = Arc::new(ConcurrentHashMap::<u32, u32>::with_capacity(2));
+    let handles: Vec<_> = (0..8).map(|t| {
+        let map = map.clone();
+        thread::spawn(move || {
+            for i in 0..5000 {
+                let key = t * 5000 + i;
+                assert_eq!(map.put(key, key), None);
+                assert_eq!(map.get(&key), Some(key));
+            }
+        })
+    }).collect();
+    for h in handles {
+        h.join().unwrap();
+    }
+    assert_eq!(map.len(), 40000);
+    for key in 0..40000 {
+        assert_eq!(map.get(&key), Some(key));
+    }
+
+    thread::scope(|s| {
+        for t in 0..8 {
+            let map = &map;
+            s.spawn(move || {
+                for i in (0..5000).step_by(2) {
+                    assert_eq!(map.remove(&(t * 5000 + i)), Some(t * 5000 + i));
+                }
+            });
+        }
+    });
+    assert_eq!(map.len(), 20000);
+    assert!((0..40000).all(|key| map.contains_key(&key) == (key % 2 == 1)));
+}
+
+#[test]
+fn concurrent_merge_test() {
+    let map = ConcurrentHashMap::<u32, u64>::with_capacity(1);
+    thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| {
+                for i in 0..10000 {
+                    map.merge(i % 500, 1, |old, new| Some(old + new));
+                    map.compute(i % 7 + 1000, |_, v| Some(v.copied().unwrap_or(0) + 1));
+                }
+            });
+        }
+    });
+    assert_eq!(map.len(), 507);
+    assert_eq!((0..500).map(|i| map.get(&i).unwrap()).sum::<u64>(), 80000);
+    assert_eq!((1000..1007).map(|i| map.get(&i).unwrap()).sum::<u64>(), 80000);
+}
+
+#[test]
+fn put_if_absent_race_test() {
+    let map = ConcurrentHashMap::<u32, usize>::new();
+    let winners: usize = thread::scope(|s| {
+        let handles: Vec<_> = (0..8).map(|t| {
+            let map = &map;
+            s.spawn(move || (0..1000).filter(|i| map.put_if_absent(*i, t).is_none()).count())
+        }).collect();
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    });
+    assert_eq!(winners, 1000);
+    assert_eq!(map.len(), 1000);
+}
+
+#[test]
+fn tree_bin_test() {
+    // a few hash values only, so the bins turn into trees and get split by the resizes
+    let map = ConcurrentHashMap::<u32, u32, BuildHasherDefault<ModHasher<32>>>::with_capacity_hasher(2, Default::default());
+    thread::scope(|s| {
+        for t in 0..4 {
+            let map = &map;
+            s.spawn(move || {
+                for i in 0..2000 {
+                    map.put(t * 2000 + i, i);
+                }
+            });
+        }
+    });
+    assert_eq!(map.len(), 8000);
+    for key in 0..8000 {
+        assert_eq!(map.get(&key), Some(key % 2000));
+        assert_eq!(map.compute_if_present(&key, |_, v| Some(v + 1)), Some(key % 2000 + 1));
+    }
+    for key in 0..8000 {
+        assert_eq!(map.remove(&key), Some(key % 2000 + 1));
+    }
+    assert!(map.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "capacity overflow")]
+fn capacity_overflow_test() {
+    ConcurrentHashMap::<i32, i32>::with_capacity(usize::MAX);
+}
@@ -0,0 +1,188 @@
+use mutcrab::collection::map::{Entry, FlatHashMap as HashMap, Map};
+use std::hash::BuildHasherDefault;
+use std::rc::Rc;
+
+mod common;
+use common::ConstHasher;
+
+#[test]
+fn flat_map_test() {
+    let mut map = HashMap::<i32, i32>::of(1, 2);
+    map.put(2, 3);
+    assert_eq!(map.get(&1), Some(&2));
+    assert_eq!(map.remove(&2), Some(3));
+    assert_eq!(map.get(&2), None);
+    assert_eq!(map.put(1, 4), Some(2));
+    assert_eq!(map.size(), 1);
+    *map.get_mut(&1).unwrap() += 1;
+    assert_eq!(map[&1], 5);
+    assert!(map.contains_key(&1));
+}
+
+#[test]
+fn grow_and_churn_test() {
+    let mut map = HashMap::<u32, u32>::new();
+    for i in 0..10000 {
+        assert_eq!(map.put(i, i), None);
+    }
+    assert_eq!(map.len(), 10000);
+    assert!(map.capacity() >= 10000);
+    // remove and insert over and over, the tombstones must not make the table grow forever
+    for round in 0..20 {
+        for i in 0..10000 {
+            assert_eq!(map.remove(&i), Some(i + round * 10000));
+            map.put(i + (round + 1) * 10000, 0);
+            map.remove(&(i + (round + 1) * 10000));
+            map.put(i, i + (round + 1) * 10000);
+        }
+    }
+    assert_eq!(map.len(), 10000);
+    assert!(map.capacity() < 40000);
+    for i in 0..10000 {
+        assert_eq!(map.get(&i), Some(&(i + 200000)));
+    }
+}
+
+#[test]
+fn collision_test() {
+    let mut map = HashMap::<String, usize, BuildHasherDefault<ConstHasher<0x0123_4567>>>::default();
+    for i in 0..200 {
+        map.put(i.to_string(), i);
+    }
+    for i in (0..200).step_by(3) {
+        assert_eq!(map.remove(i.to_string().as_str()), Some(i));
+    }
+    for i in 0..200 {
+        assert_eq!(map.get(i.to_string().as_str()), if i % 3 == 0 { None } else { Some(&i) });
+    }
+    map.shrink_to_fit();
+    assert_eq!(map.len(), 133);
+    assert_eq!(map.iter().count(), 133);
+}
+
+#[test]
+fn random_ops_test() {
+    let mut map = HashMap::<u64, u64>::with_capacity(0);
+    let mut expect = std::collections::HashMap::new();
+    let mut seed: u64 = 42;
+    for _ in 0..100000 {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let key = (seed >> 33) % 2000;
+        match (seed >> 20) % 4 {
+            0 | 1 => assert_eq!(map.put(key, seed), expect.insert(key, seed)),
+            2 => assert_eq!(map.remove(&key), expect.remove(&key)),
+            _ => assert_eq!(map.get(&key), expect.get(&key)),
+        }
+    }
+    assert_eq!(map.len(), expect.len());
+    let mut entries: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+    let mut expected: Vec<_> = expect.into_iter().collect();
+    entries.sort();
+    expected.sort();
+    assert_eq!(entries, expected);
+}
+
+#[test]
+fn entry_test() {
+    let mut map = HashMap::<&str, i32>::new();
+    *map.entry("a").or_insert(0) += 1;
+    *map.entry("a").or_insert(0) += 1;
+    map.entry("b").and_modify(|v| *v += 1).or_insert(10);
+    assert_eq!(map.get(&"a"), Some(&2));
+    assert_eq!(map.get(&"b"), Some(&10));
+    match map.entry("a") {
+        Entry::Occupied(x) => assert_eq!(x.remove_entry(), ("a", 2)),
+        Entry::Vacant(_) => panic!("a is in the map"),
+    }
+    assert_eq!(map.len(), 1);
+    assert_eq!(map.entry("c").key(), &"c");
+}
+
+#[test]
+fn drop_test() {
+    let value = Rc::new(0);
+    let mut map = HashMap::new();
+    for i in 0..100 {
+        map.put(i, value.clone());
+    }
+    let copy = map.clone();
+    assert_eq!(Rc::strong_count(&value), 201);
+    drop(copy);
+
+    map.retain(|k, _| k % 2 == 0);
+    assert_eq!(Rc::strong_count(&value), 51);
+    assert_eq!(map.extract_if(|k, _| k % 4 == 0).count(), 25);
+    assert_eq!(Rc::strong_count(&value), 26);
+    assert_eq!(map.drain().take(5).count(), 5);
+    assert!(map.is_empty());
+    assert_eq!(Rc::strong_count(&value), 1);
+
+    for i in 0..100 {
+        map.put(i, value.clone());
+    }
+    map.clear();
+    assert_eq!(Rc::strong_count(&value), 1);
+    for i in 0..100 {
+        map.put(i, value.clone());
+    }
+    let mut iter = map.into_iter();
+    iter.next();
+    drop(iter);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
+
+#[test]
+fn reserve_shrink_test() {
+    let mut map = HashMap::<i32, i32>::new();
+    assert_eq!(map.capacity(), 0);
+    map.reserve(100);
+    let capacity = map.capacity();
+    assert!(capacity >= 100);
+    for i in 0..100 {
+        map.put(i, i);
+    }
+    assert_eq!(map.capacity(), capacity);
+    map.retain(|k, _| *k < 10);
+    map.shrink_to_fit();
+    assert!(map.capacity() >= 10 && map.capacity() < capacity);
+    assert_eq!(map.keys().count(), 10);
+    map.clear();
+    map.shrink_to_fit();
+    assert_eq!(map.capacity(), 0);
+
+    // an emptied table starts over at the capacity it was created with
+    let mut map = HashMap::<i32, i32>::with_capacity(1000);
+    map.put(0, 0);
+    let capacity = map.capacity();
+    map.remove(&0);
+    map.shrink_to_fit();
+    map.put(0, 0);
+    assert_eq!(map.capacity(), capacity);
+
+    // an occupied entry does not grow a full table
+    let mut map = HashMap::<i32, i32>::new();
+    map.put(0, 0);
+    let capacity = map.capacity();
+    for i in 1..capacity as i32 {
+        map.put(i, i);
+    }
+    assert_eq!(map.len(), capacity);
+    *map.entry(0).or_insert(0) += 1;
+    assert_eq!(map.capacity(), capacity);
+    map.entry(-1).or_insert(0);
+    assert!(map.capacity() > capacity);
+}
+
+#[test]
+fn std_traits_test() {
+    let map: HashMap<i32, &str> = vec![(1, "a"), (2, "b")].into_iter().collect();
+    let mut copy = map.clone();
+    assert_eq!(copy, map);
+    copy.put(3, "c");
+    assert_ne!(copy, map);
+    for (_, v) in &mut copy {
+        *v = "x";
+    }
+    assert!(copy.values().all(|v| *v == "x"));
+    assert_eq!(format!("{:?}", HashMap::<i32, i32>::of(1, 1)), "{1: 1}");
+}
@@ -1,5 +1,9 @@
-use mutcrab::collection::map::{HashMap, Map};
+use mutcrab::collection::map::{Entry, HashMap, Map};
 use std::hash::BuildHasherDefault;
+use std::rc::Rc;
+
+mod common;
+use common::IntegerHasher;
 
 #[test]
 fn my_map2_test() {
@@ -33,7 +37,7 @@ fn hashmap_test() {
     assert_eq!(map.get(&"b"), Some(&false));
     assert_eq!(map.get(&"c"), Some(&false));
     assert_eq!(map.get(&"d"), Some(&false));
-    assert_eq!(map.contains_key(&"a"), true);
+    assert!(map.contains_key(&"a"));
 }
 
 #[test]
@@ -69,7 +73,7 @@ fn entry_get_test() {
     assert_eq!(map.get(&"a"), Some(&2));
     assert_eq!(*map.entry("a").or_insert(2), 2);
 
-    let v = map.entry("b").and_modify(|x| *x = *x * 10 + 2).value();
+    let v = map.entry("b").and_modify(|x| *x = *x * 10 + 2).or_insert(0);
     assert_eq!(*v, 22);
 }
 
@@ -78,9 +82,59 @@ fn entry_get_test() {
 fn entry_insert_test() {
     let mut map = HashMap::<&str, i32>::new();
     map.entry("a").or_insert(1);
-    map.entry("b").and_modify(|x| *x = 2).value();
+    map.entry("b").and_modify(|x| *x = 3).or_insert(2);
     assert_eq!(map.get(&"a"), Some(&1));
     assert_eq!(map.get(&"b"), Some(&2));
+    *map.entry("c").or_insert_with(|| 4) += 1;
+    assert_eq!(*map.entry("d").or_insert_with_key(|k| k.len() as i32), 1);
+    assert_eq!(*map.entry("e").or_default(), 0);
+    assert_eq!(map.get(&"c"), Some(&5));
+    assert_eq!(map.len(), 5);
+}
+
+#[test]
+fn entry_and_modify_test() {
+    let mut map = HashMap::<&str, i32>::new();
+    map.entry("a").and_modify(|x| *x += 1);
+    assert!(!map.contains_key(&"a"));
+    map.put("a", 1);
+    map.entry("a").and_modify(|x| *x += 1);
+    assert_eq!(map.get(&"a"), Some(&2));
+}
+
+#[test]
+fn occupied_entry_test() {
+    let mut map = HashMap::<String, i32>::new();
+    for i in 0..20 {
+        map.put(i.to_string(), i);
+    }
+    match map.entry("5".to_string()) {
+        Entry::Occupied(mut x) => {
+            assert_eq!(x.key(), "5");
+            assert_eq!(*x.get(), 5);
+            *x.get_mut() += 10;
+            assert_eq!(x.insert(20), 15);
+        }
+        Entry::Vacant(_) => panic!("5 is present"),
+    }
+    assert_eq!(map.get("5"), Some(&20));
+    match map.entry("5".to_string()) {
+        Entry::Occupied(x) => assert_eq!(x.remove(), 20),
+        Entry::Vacant(_) => panic!("5 is present"),
+    }
+    match map.entry("6".to_string()) {
+        Entry::Occupied(x) => assert_eq!(x.remove_entry(), ("6".to_string(), 6)),
+        Entry::Vacant(_) => panic!("6 is present"),
+    }
+    assert_eq!(map.len(), 18);
+    assert_eq!(map.get("5"), None);
+    assert_eq!(map.get("6"), None);
+    match map.entry("5".to_string()) {
+        Entry::Occupied(_) => panic!("5 is removed"),
+        Entry::Vacant(x) => *x.insert(1) += 1,
+    }
+    assert_eq!(map.get("5"), Some(&2));
+    assert_eq!(map.len(), 19);
 }
 
 #[test]
@@ -112,7 +166,7 @@ fn test_modify_value_iterator() {
     map.put("b", 2);
     map.put("c", 3);
     map.put("d", 4);
-    for (k, v) in &map {
+    for (_, v) in &mut map {
         *v += 1;
     }
     assert_eq!(map.get(&"a"), Some(&2));
@@ -121,26 +175,9 @@ fn test_modify_value_iterator() {
     assert_eq!(map.get(&"d"), Some(&5));
 }
 
-#[derive(Default)]
-struct MyIntegerHasher(u64);
-
-impl std::hash::Hasher for MyIntegerHasher {
-    fn finish(&self) -> u64 {
-        self.0
-    }
-
-    fn write(&mut self, bytes: &[u8]) {
-        self.0 = match bytes.len() {
-            4 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64,
-            8 => u64::from_le_bytes(bytes.try_into().unwrap()),
-            _ => panic!("Invalid byte length: {}. Only 4 or 8 byte lengths are supported.", bytes.len()),
-        }
-    }
-}
-
 #[test]
 fn test_hasher_test() {
-    type BuildHasher = BuildHasherDefault<MyIntegerHasher>;
+    type BuildHasher = BuildHasherDefault<IntegerHasher>;
     let mut map:HashMap<i32, &str, BuildHasher> = HashMap::with_hasher(BuildHasher::new());
     map.insert(1, "a");
     map.insert(2, "b");
@@ -149,3 +186,176 @@ fn test_hasher_test() {
     assert_eq!(map.get(&2), Some(&"b"));
     assert_eq!(map.get(&3), Some(&"c"));
 }
+
+#[test]
+fn into_iter_test() {
+    let mut map = HashMap::<i32, String>::new();
+    for i in 0..100 {
+        map.put(i, i.to_string());
+    }
+    let iter = map.into_iter();
+    assert_eq!(iter.len(), 100);
+    let mut entries: Vec<(i32, String)> = iter.collect();
+    entries.sort();
+    assert_eq!(entries, (0..100).map(|i| (i, i.to_string())).collect::<Vec<_>>());
+}
+
+#[test]
+fn keys_values_test() {
+    let mut map = HashMap::<i32, i32>::new();
+    for i in 0..50 {
+        map.put(i, i * 10);
+    }
+    assert_eq!(map.iter().len(), 50);
+    for v in map.values_mut() {
+        *v += 1;
+    }
+    for (k, v) in map.iter_mut() {
+        *v += *k;
+    }
+    let mut keys: Vec<i32> = map.keys().copied().collect();
+    keys.sort();
+    assert_eq!(keys, (0..50).collect::<Vec<_>>());
+    let mut values: Vec<i32> = map.values().copied().collect();
+    values.sort();
+    assert_eq!(values, (0..50).map(|i| i * 11 + 1).collect::<Vec<_>>());
+    let mut values: Vec<i32> = map.into_values().collect();
+    values.sort();
+    assert_eq!(values, (0..50).map(|i| i * 11 + 1).collect::<Vec<_>>());
+}
+
+#[test]
+fn borrowed_lookup_test() {
+    let mut map = HashMap::<String, i32>::new();
+    for i in 0..20 {
+        map.put(i.to_string(), i);
+    }
+    assert_eq!(map.get("7"), Some(&7));
+    assert!(map.contains_key("19"));
+    assert!(!map.contains_key("20"));
+    *map.get_mut("3").unwrap() += 10;
+    assert_eq!(map.remove("3"), Some(13));
+    assert_eq!(map.get("3"), None);
+}
+
+#[test]
+fn reserve_shrink_test() {
+    let mut map = HashMap::<i32, i32>::new();
+    assert_eq!(map.capacity(), 0);
+    map.reserve(1000);
+    let capacity = map.capacity();
+    assert!(capacity >= 1000);
+    map.extend((0..1000).map(|i| (i, i)));
+    assert_eq!(map.capacity(), capacity);
+    map.retain(|k, _| k % 10 == 0);
+    assert_eq!(map.len(), 100);
+    map.shrink_to_fit();
+    assert!(map.capacity() >= 100 && map.capacity() < capacity);
+    for i in 0..1000 {
+        assert_eq!(map.get(&i), if i % 10 == 0 { Some(&i) } else { None });
+    }
+    let capacity = map.capacity();
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.capacity(), capacity);
+    map.shrink_to_fit();
+    assert_eq!(map.capacity(), 0);
+    map.put(1, 1);
+    assert_eq!(map.get(&1), Some(&1));
+}
+
+#[test]
+fn std_traits_test() {
+    let map: HashMap<i32, String> = (0..10).map(|i| (i, i.to_string())).collect();
+    assert_eq!(map.len(), 10);
+    assert_eq!(map[&3], "3");
+
+    let mut copy = map.clone();
+    assert_eq!(copy, map);
+    copy.put(3, "x".to_string());
+    assert_ne!(copy, map);
+    assert_eq!(map[&3], "3");
+    copy.remove(&3);
+    assert_eq!(copy.len(), 9);
+    assert_ne!(copy, map);
+
+    let mut other = HashMap::<i32, String>::default();
+    other.extend(map.iter().map(|(k, v)| (*k, v.clone())));
+    assert_eq!(other, map);
+
+    let single: HashMap<&str, i32> = [("a", 1)].into_iter().collect();
+    assert_eq!(format!("{:?}", single), r#"{"a": 1}"#);
+}
+
+#[test]
+#[should_panic(expected = "no entry found for key")]
+fn index_missing_test() {
+    let map = HashMap::<i32, i32>::of(1, 1);
+    let _ = map[&2];
+}
+
+#[test]
+fn drain_test() {
+    let value = Rc::new(());
+    let mut map = HashMap::<i32, Rc<()>>::new();
+    for i in 0..50 {
+        map.put(i, value.clone());
+    }
+    let mut keys: Vec<i32> = map.drain().map(|(k, _)| k).collect();
+    keys.sort();
+    assert_eq!(keys, (0..50).collect::<Vec<_>>());
+    assert_eq!(map.size(), 0);
+    assert_eq!(map.get(&1), None);
+    assert_eq!(Rc::strong_count(&value), 1);
+
+    // a partly consumed drain still empties the map
+    for i in 0..50 {
+        map.put(i, value.clone());
+    }
+    assert_eq!(map.drain().take(10).count(), 10);
+    assert!(map.is_empty());
+    assert_eq!(Rc::strong_count(&value), 1);
+
+    map.put(7, value.clone());
+    assert_eq!(map.size(), 1);
+    let mut iter = map.into_iter();
+    assert_eq!(iter.next().map(|(k, _)| k), Some(7));
+    assert_eq!(iter.next(), None);
+    drop(iter);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
+
+#[test]
+fn retain_test() {
+    let mut map = HashMap::<i32, i32>::new();
+    for i in 0..100 {
+        map.put(i, i);
+    }
+    map.retain(|k, v| {
+        *v += 1;
+        k % 3 == 0
+    });
+    assert_eq!(map.size(), 34);
+    for i in 0..100 {
+        assert_eq!(map.get(&i).copied(), if i % 3 == 0 { Some(i + 1) } else { None });
+    }
+}
+
+#[test]
+fn extract_if_test() {
+    let mut map = HashMap::<i32, String>::new();
+    for i in 0..100 {
+        map.put(i, i.to_string());
+    }
+    let mut evens: Vec<(i32, String)> = map.extract_if(|k, _| k % 2 == 0).collect();
+    evens.sort();
+    assert_eq!(evens, (0..100).step_by(2).map(|i| (i, i.to_string())).collect::<Vec<_>>());
+    assert_eq!(map.size(), 50);
+    assert_eq!(map.get(&2), None);
+    assert_eq!(map.get(&3).map(|x| x.as_str()), Some("3"));
+
+    // stop early, the remaining entries are kept
+    assert_eq!(map.extract_if(|_, _| true).take(10).count(), 10);
+    assert_eq!(map.size(), 40);
+    assert_eq!(map.iter().count(), 40);
+}
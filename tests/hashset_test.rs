@@ -0,0 +1,70 @@
+use mutcrab::collection::set::{HashSet, TreeHashSet};
+use std::hash::BuildHasherDefault;
+
+mod common;
+use common::ModHasher;
+
+#[test]
+fn hashset_test() {
+    let mut set = HashSet::<String>::new();
+    assert!(set.insert("a".to_string()));
+    assert!(set.insert("b".to_string()));
+    assert!(!set.insert("a".to_string()));
+    assert_eq!(set.len(), 2);
+    assert!(set.contains("a"));
+    assert!(!set.contains("c"));
+    assert!(set.remove("a"));
+    assert!(!set.remove("a"));
+    assert_eq!(set.iter().collect::<Vec<_>>(), vec!["b"]);
+    set.clear();
+    assert!(set.is_empty());
+}
+
+fn sorted<'a>(iter: impl Iterator<Item = &'a i32>) -> Vec<i32> {
+    let mut values: Vec<i32> = iter.copied().collect();
+    values.sort();
+    values
+}
+
+#[test]
+fn set_algebra_test() {
+    let a: HashSet<i32> = (0..6).collect();
+    let b: HashSet<i32> = (3..10).collect();
+    assert_eq!(sorted(a.union(&b)), (0..10).collect::<Vec<_>>());
+    assert_eq!(sorted(a.intersection(&b)), vec![3, 4, 5]);
+    assert_eq!(sorted(b.intersection(&a)), vec![3, 4, 5]);
+    assert_eq!(sorted(a.difference(&b)), vec![0, 1, 2]);
+    assert_eq!(sorted(b.difference(&a)), vec![6, 7, 8, 9]);
+    assert_eq!(sorted(a.symmetric_difference(&b)), vec![0, 1, 2, 6, 7, 8, 9]);
+
+    let c: HashSet<i32> = a.intersection(&b).copied().collect();
+    assert!(c.is_subset(&a));
+    assert!(c.is_subset(&b));
+    assert!(!a.is_subset(&b));
+    assert!(a.is_superset(&c));
+    assert_eq!(c, [5, 4, 3].into_iter().collect());
+    assert_eq!(format!("{:?}", HashSet::<i32>::from_iter([1])), "{1}");
+}
+
+#[test]
+fn tree_hashset_test() {
+    type BuildHasher = BuildHasherDefault<ModHasher<4, 64>>;
+    let mut a: TreeHashSet<i32, BuildHasher> = (0..40).collect();
+    let b: TreeHashSet<i32, BuildHasher> = (20..60).collect();
+    assert_eq!(a.len(), 40);
+    assert!(!a.insert(10));
+    assert!(a.contains(&39));
+    assert_eq!(sorted(a.intersection(&b)), (20..40).collect::<Vec<_>>());
+    assert_eq!(sorted(a.union(&b)), (0..60).collect::<Vec<_>>());
+    assert_eq!(sorted(a.symmetric_difference(&b)).len(), 40);
+    assert_eq!(a.difference(&b).count(), 20);
+
+    a.retain(|v| *v >= 20);
+    assert!(a.is_subset(&b));
+    for v in 0..20 {
+        assert!(!a.remove(&v));
+    }
+    let mut values: Vec<i32> = a.clone().into_iter().collect();
+    values.sort();
+    assert_eq!(values, (20..40).collect::<Vec<_>>());
+}
@@ -0,0 +1,155 @@
+use mutcrab::collection::map::{Entry, HashMap, Map, TreeHashMap};
+use std::hash::BuildHasherDefault;
+use std::rc::Rc;
+
+mod common;
+use common::ModHasher;
+
+#[test]
+fn lookup_during_rehash_test() {
+    let mut map = HashMap::<u32, u32>::with_capacity(16);
+    map.set_incremental_rehash(true);
+    let mut seen_rehash = false;
+    for i in 0..5000 {
+        map.put(i, i);
+        if map.is_rehashing() {
+            seen_rehash = true;
+            // every key is found, whether its bucket is moved yet or not
+            assert!((0..=i).step_by(97).all(|k| map.get(&k) == Some(&k)));
+            assert_eq!(map.iter().count(), map.len());
+        }
+    }
+    assert!(seen_rehash);
+    assert_eq!(map.len(), 5000);
+    assert!((0..5000).all(|k| map.get(&k) == Some(&k)));
+}
+
+#[test]
+fn update_remove_during_rehash_test() {
+    let mut map = HashMap::<u32, u32>::with_capacity(16);
+    map.set_incremental_rehash(true);
+    let mut i = 0;
+    while !map.is_rehashing() {
+        map.put(i, i);
+        i += 1;
+    }
+    // the old buckets are still there, overwrite and remove go through them
+    assert_eq!(map.put(0, 100), Some(0));
+    assert_eq!(map.get(&0), Some(&100));
+    assert_eq!(map.remove(&1), Some(1));
+    assert_eq!(map.remove(&1), None);
+    match map.entry(2) {
+        Entry::Occupied(x) => assert_eq!(x.remove_entry(), (2, 2)),
+        Entry::Vacant(_) => panic!("2 is in the map"),
+    }
+    *map.entry(3).or_insert(0) += 10;
+    assert_eq!(map[&3], 13);
+    assert_eq!(map.len(), i as usize - 2);
+
+    let copy = map.clone();
+    assert_eq!(copy, map);
+    map.set_incremental_rehash(false);
+    assert!(!map.is_rehashing());
+    assert_eq!(copy, map);
+}
+
+#[test]
+fn replace_does_not_move_buckets_test() {
+    let mut map = HashMap::<u32, u32>::with_capacity(16);
+    let mut tree_map = TreeHashMap::<u32, u32>::with_capacity(16);
+    map.set_incremental_rehash(true);
+    tree_map.set_incremental_rehash(true);
+    let mut i = 0;
+    while !map.is_rehashing() || !tree_map.is_rehashing() {
+        map.put(i, i);
+        tree_map.put(i, i);
+        i += 1;
+    }
+    // only a new key moves old buckets, replacing a value leaves the resize where it is
+    for round in 0..100 {
+        assert_eq!(map.put(0, round + 1), Some(round));
+        assert_eq!(tree_map.put(0, round + 1), Some(round));
+        *map.entry(1).or_insert(0) += 1;
+        *tree_map.entry(1).or_insert(0) += 1;
+    }
+    assert!(map.is_rehashing() && tree_map.is_rehashing());
+    while map.is_rehashing() || tree_map.is_rehashing() {
+        map.put(i, i);
+        tree_map.put(i, i);
+        i += 1;
+    }
+    assert_eq!((map[&0], map[&1]), (100, 101));
+    assert_eq!((tree_map[&0], tree_map[&1]), (100, 101));
+}
+
+#[test]
+fn random_ops_test() {
+    let mut map = HashMap::<u64, u64>::new();
+    map.set_incremental_rehash(true);
+    let mut expect = std::collections::HashMap::new();
+    let mut seed: u64 = 7;
+    for _ in 0..100000 {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let key = (seed >> 33) % 20000;
+        match (seed >> 20) % 5 {
+            0..=2 => assert_eq!(map.put(key, seed), expect.insert(key, seed)),
+            3 => assert_eq!(map.remove(&key), expect.remove(&key)),
+            _ => assert_eq!(map.get(&key), expect.get(&key)),
+        }
+    }
+    assert_eq!(map.len(), expect.len());
+    let mut entries: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+    let mut expected: Vec<_> = expect.into_iter().collect();
+    entries.sort();
+    expected.sort();
+    assert_eq!(entries, expected);
+}
+
+#[test]
+fn drop_during_rehash_test() {
+    let value = Rc::new(0);
+    let mut map = HashMap::with_capacity(16);
+    map.set_incremental_rehash(true);
+    let mut i = 0;
+    while !map.is_rehashing() {
+        map.put(i, value.clone());
+        i += 1;
+    }
+    assert_eq!(map.extract_if(|k, _| k % 2 == 0).count(), (i as usize).div_ceil(2));
+    assert_eq!(Rc::strong_count(&value), 1 + i as usize / 2);
+    map.clear();
+    assert!(!map.is_rehashing());
+    assert_eq!(Rc::strong_count(&value), 1);
+    for i in 0..100 {
+        map.put(i, value.clone());
+    }
+    drop(map);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
+
+#[test]
+fn tree_map_rehash_test() {
+    // 64 hashes, so the buckets turn into trees while the table grows
+    let mut map = TreeHashMap::<u32, u32, BuildHasherDefault<ModHasher<64>>>::with_hasher(Default::default());
+    map.set_incremental_rehash(true);
+    let mut seen_rehash = false;
+    for i in 0..3000 {
+        map.put(i, i);
+        seen_rehash |= map.is_rehashing();
+        if i % 3 == 0 {
+            assert_eq!(map.remove(&(i / 2)), Some(i / 2));
+        }
+    }
+    assert!(seen_rehash);
+    for i in 0..3000 {
+        // `i / 2` was removed for every `i` that is a multiple of 3
+        let removed = i < 1500 && ((i * 2) % 3 == 0 || (i * 2 + 1) % 3 == 0);
+        assert_eq!(map.get(&i), if removed { None } else { Some(&i) });
+    }
+    assert_eq!(map.iter().count(), map.len());
+    map.retain(|k, _| k % 2 == 0);
+    assert!(map.keys().all(|k| k % 2 == 0));
+    map.shrink_to_fit();
+    assert!(!map.is_rehashing());
+    assert_eq!(map.iter().count(), map.len());
+}
@@ -25,7 +25,7 @@ fn test_take_on_empty_queue() {
 
     // 这个测试模拟 take 在空队列时的行为
     // take 应该阻塞直到有数据
-    assert_eq!(queue.poll().is_none(), true);
+    assert!(queue.poll().is_none());
     let queue1= Arc::clone(&queue);
     thread::spawn(move || {
         thread::sleep(Duration::from_millis(10));  //block time
@@ -65,10 +65,8 @@ fn test_foreach() {
     });
     // put block
     queue.push(3);
-    let mut i = 1;
-    for num in queue.iter() {
+    for (i, num) in (1..).zip(queue.iter()) {
         assert_eq!(i, *num);
-        i += 1;
     }
 }
 
@@ -135,7 +133,7 @@ fn single_read_write_test() {
     // 等待消费者完成
     let v1 = c1.join().unwrap();
     assert_eq!(v1, (0..10000).collect::<Vec<_>>());
-    sc.send(1);
+    sc.send(1).unwrap();
 }
 
 #[ignore]
@@ -218,7 +216,7 @@ fn mut_readwrite_test() {
     let v2 = c2.join().unwrap();
 
     // 验证所有数据是否被正确消费
-    let mut all_data = vec![v1, v2].concat();
+    let mut all_data = [v1, v2].concat();
     all_data.sort();
     assert_eq!(all_data, (0..3000).collect::<Vec<_>>());
 }
\ No newline at end of file
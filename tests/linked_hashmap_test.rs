@@ -0,0 +1,177 @@
+use mutcrab::collection::map::{BoxAllocator, Entry, LinkedHashMap, LinkedNode, Map, NodeAllocator};
+use std::hash::BuildHasherDefault;
+use std::rc::Rc;
+
+mod common;
+use common::ConstHasher;
+
+fn keys<S>(map: &LinkedHashMap<i32, i32, S>) -> Vec<i32> {
+    map.keys().copied().collect()
+}
+
+#[test]
+fn insertion_order_test() {
+    let mut map = LinkedHashMap::<i32, i32>::new();
+    for i in (0..100).rev() {
+        map.put(i, i * 10);
+    }
+    assert_eq!(map.len(), 100);
+    assert_eq!(keys(&map), (0..100).rev().collect::<Vec<_>>());
+    // putting an existing key or reading it keeps its place
+    assert_eq!(map.put(50, 0), Some(500));
+    assert_eq!(map.get(&10), Some(&100));
+    assert_eq!(map.front(), Some((&99, &990)));
+    assert_eq!(map.back(), Some((&0, &0)));
+    assert_eq!(keys(&map), (0..100).rev().collect::<Vec<_>>());
+
+    assert_eq!(map.remove(&99), Some(990));
+    map.put(99, 1);
+    assert_eq!(map.back(), Some((&99, &1)));
+    assert_eq!(map.iter().rev().take(2).map(|(k, _)| *k).collect::<Vec<_>>(), vec![99, 0]);
+}
+
+#[test]
+fn access_order_test() {
+    let mut map = LinkedHashMap::<i32, i32>::with_access_order();
+    assert!(map.is_access_order());
+    for i in 0..5 {
+        map.put(i, i);
+    }
+    assert_eq!(map.get_refresh(&1), Some(&1));
+    assert_eq!(keys(&map), vec![0, 2, 3, 4, 1]);
+    *map.get_mut(&0).unwrap() += 10;
+    assert_eq!(keys(&map), vec![2, 3, 4, 1, 0]);
+    map.put(3, 30);
+    assert_eq!(keys(&map), vec![2, 4, 1, 0, 3]);
+    map.entry(4).or_insert(0);
+    assert_eq!(keys(&map), vec![2, 1, 0, 3, 4]);
+
+    // the shared lookups are not accesses
+    assert_eq!(map.get(&2), Some(&2));
+    assert_eq!(map[&1], 1);
+    assert!(map.contains_key(&1));
+    assert_eq!(keys(&map), vec![2, 1, 0, 3, 4]);
+
+    assert_eq!(map.get_refresh(&5), None);
+    assert_eq!(map.pop_front(), Some((2, 2)));
+    assert_eq!(map.pop_back(), Some((4, 4)));
+    assert_eq!(map.into_iter().collect::<Vec<_>>(), vec![(1, 1), (0, 10), (3, 30)]);
+}
+
+#[test]
+fn resize_keeps_order_test() {
+    let mut map = LinkedHashMap::<i32, i32>::with_capacity(2);
+    for i in 0..1000 {
+        map.put(i * 7 % 1000, i);
+    }
+    assert_eq!(map.len(), 1000);
+    let expect: Vec<i32> = (0..1000).map(|i| i * 7 % 1000).collect();
+    assert_eq!(keys(&map), expect);
+    for i in 0..1000 {
+        assert_eq!(map.get(&(i * 7 % 1000)), Some(&i));
+    }
+    map.retain(|k, _| k % 2 == 0);
+    map.shrink_to_fit();
+    assert_eq!(keys(&map), expect.iter().copied().filter(|k| k % 2 == 0).collect::<Vec<_>>());
+}
+
+#[test]
+fn collision_test() {
+    // every key lands in the same bucket
+    let mut map = LinkedHashMap::<i32, i32, BuildHasherDefault<ConstHasher<0>>>::default();
+    for i in 0..20 {
+        map.put(i, i);
+    }
+    assert_eq!(map.remove(&7), Some(7));
+    assert_eq!(map.remove(&0), Some(0));
+    assert_eq!(map.remove(&19), Some(19));
+    assert_eq!(keys(&map), (1..19).filter(|k| *k != 7).collect::<Vec<_>>());
+}
+
+#[test]
+fn entry_test() {
+    let mut map = LinkedHashMap::<&str, i32>::new();
+    *map.entry("a").or_insert(0) += 1;
+    *map.entry("b").or_insert(0) += 1;
+    *map.entry("a").or_insert(0) += 1;
+    assert_eq!(map.get(&"a"), Some(&2));
+    match map.entry("a") {
+        Entry::Occupied(x) => assert_eq!(x.remove_entry(), ("a", 2)),
+        Entry::Vacant(_) => panic!("a is in the map"),
+    }
+    map.entry("c").or_default();
+    assert_eq!(map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(), vec![("b", 1), ("c", 0)]);
+}
+
+#[test]
+fn drain_clear_drop_test() {
+    let value = Rc::new(0);
+    let mut map = LinkedHashMap::new();
+    for i in 0..10 {
+        map.put(i, value.clone());
+    }
+    assert_eq!(map.drain().take(3).map(|(k, _)| k).collect::<Vec<_>>(), vec![0, 1, 2]);
+    assert!(map.is_empty());
+    assert_eq!(Rc::strong_count(&value), 1);
+
+    for i in 0..10 {
+        map.put(i, value.clone());
+    }
+    map.clear();
+    assert_eq!(Rc::strong_count(&value), 1);
+    assert_eq!(map.get(&1), None);
+
+    map.put(1, value.clone());
+    let mut iter = map.clone().into_iter();
+    assert_eq!(Rc::strong_count(&value), 3);
+    iter.next();
+    drop(iter);
+    drop(map);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
+
+#[test]
+fn std_traits_test() {
+    let map: LinkedHashMap<i32, &str> = vec![(3, "c"), (1, "a"), (2, "b")].into_iter().collect();
+    assert_eq!(format!("{:?}", map), r#"{3: "c", 1: "a", 2: "b"}"#);
+    assert_eq!(map[&1], "a");
+
+    let mut copy = map.clone();
+    assert_eq!(copy.iter().map(|(k, _)| *k).collect::<Vec<_>>(), vec![3, 1, 2]);
+    assert_eq!(copy, map);
+    copy.remove(&3);
+    copy.put(3, "c");
+    // the order is not compared
+    assert_eq!(copy, map);
+    Extend::extend(&mut copy, vec![(4, "d")]);
+    assert_ne!(copy, map);
+    for (_, v) in &mut copy {
+        *v = "x";
+    }
+    assert!(copy.values().all(|v| *v == "x"));
+    let mut keys = Vec::new();
+    for (k, _) in &map {
+        keys.push(*k);
+    }
+    assert_eq!(keys, vec![3, 1, 2]);
+}
+
+#[test]
+fn allocator_test() {
+    let hasher = std::hash::RandomState::new();
+    let mut map: LinkedHashMap<i32, i32, _, BoxAllocator<LinkedNode<i32, i32>>> =
+        LinkedHashMap::with_capacity_factor_order_hasher_in(4, 0.75, true, hasher, BoxAllocator::new());
+    for i in 0..100 {
+        map.put(i, i);
+    }
+    assert_eq!(map.allocator().size(), 100);
+    map.get_refresh(&0);
+    assert_eq!(map.back(), Some((&0, &0)));
+    map.retain(|k, _| k % 2 == 0);
+    assert_eq!(map.allocator().size(), 50);
+    let copy = map.clone();
+    assert_eq!(copy, map);
+    assert_eq!(copy.keys().take(2).copied().collect::<Vec<_>>(), vec![2, 4]);
+    drop(map);
+    assert_eq!(copy.into_iter().count(), 50);
+}
@@ -34,7 +34,7 @@ fn remove_test() {
     list.add(2);
     list.add(3);
     assert_eq!(list.size(), 3);
-    assert_eq!(list.remove(&2), true);
+    assert!(list.remove(&2));
     assert_eq!(list.size(), 2);
 }
 
@@ -81,6 +81,6 @@ fn iter_test() {
     for i in list.iter() {
         println!("list iter: {}", i);
     }
-    let vec:Vec<&str> = list.iter().map(|x|{*x}).collect();
+    let vec:Vec<&str> = list.iter().copied().collect();
     assert_eq!(vec, vec!["a", "b", "c", "d"]);
 }
@@ -0,0 +1,112 @@
+use mutcrab::collection::cache::LruCache;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+fn keys<V>(cache: &LruCache<String, V>) -> Vec<&str> {
+    cache.iter().map(|(k, _)| k.as_str()).collect()
+}
+
+#[test]
+fn evict_lru_test() {
+    let mut cache = LruCache::new(3);
+    cache.put("a".to_string(), 1);
+    cache.put("b".to_string(), 2);
+    cache.put("c".to_string(), 3);
+    assert_eq!(keys(&cache), vec!["c", "b", "a"]);
+
+    // get marks "a" as used, so "b" is evicted
+    assert_eq!(cache.get("a"), Some(&1));
+    assert_eq!(cache.put("d".to_string(), 4), None);
+    assert_eq!(cache.len(), 3);
+    assert!(!cache.contains("b"));
+    assert_eq!(keys(&cache), vec!["d", "a", "c"]);
+
+    // peek does not
+    assert_eq!(cache.peek("c"), Some(&3));
+    assert_eq!(cache.peek_lru(), Some((&"c".to_string(), &3)));
+    assert_eq!(cache.put("a".to_string(), 10), Some(1));
+    cache.put("e".to_string(), 5);
+    assert_eq!(keys(&cache), vec!["e", "a", "d"]);
+
+    *cache.get_mut("d").unwrap() += 1;
+    assert_eq!(keys(&cache), vec!["d", "e", "a"]);
+    assert_eq!(cache.peek("d"), Some(&5));
+}
+
+#[test]
+fn pop_remove_test() {
+    let mut cache = LruCache::new(4);
+    for i in 0..4 {
+        cache.put(i.to_string(), i);
+    }
+    assert_eq!(cache.pop_lru(), Some(("0".to_string(), 0)));
+    assert_eq!(cache.remove("2"), Some(2));
+    assert_eq!(cache.remove("2"), None);
+    assert_eq!(keys(&cache), vec!["3", "1"]);
+    assert_eq!(cache.pop_lru(), Some(("1".to_string(), 1)));
+    assert_eq!(cache.pop_lru(), Some(("3".to_string(), 3)));
+    assert_eq!(cache.pop_lru(), None);
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn listener_test() {
+    let evicted = Arc::new(Mutex::new(Vec::new()));
+    let sink = evicted.clone();
+    let mut cache = LruCache::with_listener(2, move |k: String, v: i32| sink.lock().unwrap().push((k, v)));
+    cache.put("a".to_string(), 1);
+    cache.put("b".to_string(), 2);
+    cache.put("c".to_string(), 3);
+    assert_eq!(*evicted.lock().unwrap(), vec![("a".to_string(), 1)]);
+
+    // removing is not an eviction
+    cache.pop_lru();
+    cache.remove("c");
+    assert_eq!(evicted.lock().unwrap().len(), 1);
+
+    for i in 0..5 {
+        cache.put(i.to_string(), i);
+    }
+    cache.resize(1);
+    assert_eq!(cache.capacity(), 1);
+    assert_eq!(keys(&cache), vec!["4"]);
+    let evicted: Vec<i32> = evicted.lock().unwrap().iter().map(|(_, v)| *v).collect();
+    assert_eq!(evicted, vec![1, 0, 1, 2, 3]);
+}
+
+#[test]
+fn resize_grow_test() {
+    let mut cache = LruCache::new(1);
+    cache.put(1, 1);
+    cache.resize(100);
+    for i in 0..200 {
+        cache.put(i, i);
+    }
+    assert_eq!(cache.len(), 100);
+    assert_eq!(cache.peek_lru(), Some((&100, &100)));
+    assert_eq!(cache.iter().next(), Some((&199, &199)));
+}
+
+#[test]
+fn drop_test() {
+    let value = Rc::new(0);
+    let mut cache = LruCache::new(8);
+    for i in 0..20 {
+        cache.put(i, value.clone());
+    }
+    assert_eq!(Rc::strong_count(&value), 9);
+    cache.clear();
+    assert_eq!(Rc::strong_count(&value), 1);
+    assert_eq!(cache.get(&19), None);
+    for i in 0..4 {
+        cache.put(i, value.clone());
+    }
+    drop(cache);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
+
+#[test]
+#[should_panic]
+fn zero_capacity_test() {
+    LruCache::<i32, i32>::new(0);
+}
@@ -0,0 +1,65 @@
+use mutcrab::collection::map::{HashMap, Map, TreeHashMap};
+use std::hash::BuildHasherDefault;
+
+mod common;
+use common::ModHasher;
+
+#[test]
+fn hashmap_stats_test() {
+    let mut map = HashMap::<u32, u32>::new();
+    let stats = map.stats();
+    assert_eq!((stats.entries, stats.buckets, stats.load_factor), (0, 0, 0.0));
+    for i in 0..1000 {
+        map.put(i, i);
+    }
+    let stats = map.stats();
+    assert_eq!(stats.entries, 1000);
+    assert_eq!(stats.buckets, 2048);
+    assert_eq!(stats.chain_lengths.iter().sum::<usize>(), stats.buckets);
+    assert_eq!(stats.chain_lengths.iter().enumerate().map(|(len, n)| len * n).sum::<usize>(), 1000);
+    assert_eq!(stats.empty_buckets, stats.chain_lengths[0]);
+    assert_eq!(stats.longest_chain, stats.chain_lengths.len() - 1);
+    assert_eq!(stats.tree_buckets, 0);
+    assert!((stats.load_factor - 1000.0 / 2048.0).abs() < 1e-9);
+}
+
+#[test]
+fn stats_during_rehash_test() {
+    let mut map = HashMap::<u32, u32>::with_capacity(16);
+    map.set_incremental_rehash(true);
+    let mut i = 0;
+    while !map.is_rehashing() {
+        map.put(i, i);
+        i += 1;
+    }
+    // the old buckets still hold entries and count as well
+    let stats = map.stats();
+    assert_eq!(stats.buckets, 16 + 32);
+    assert_eq!(stats.chain_lengths.iter().enumerate().map(|(len, n)| len * n).sum::<usize>(), map.len());
+}
+
+#[test]
+fn tree_map_stats_test() {
+    // eight hashes in 16 buckets, a flooded map
+    let mut map = TreeHashMap::<u32, u32, BuildHasherDefault<ModHasher<8>>>::with_capacity_hasher(16, Default::default());
+    for i in 0..10 {
+        map.put(i, i);
+    }
+    let stats = map.stats();
+    assert_eq!(stats.tree_buckets, 0);
+    assert!(stats.longest_chain >= 2);
+
+    for i in 10..200 {
+        map.put(i, i);
+    }
+    let stats = map.stats();
+    assert_eq!(stats.entries, 200);
+    assert!(stats.tree_buckets > 0);
+    // no hash is in two buckets
+    assert!(stats.tree_hashes <= 8);
+    assert!(stats.longest_chain >= 200 / 8);
+    assert!(stats.empty_buckets >= stats.buckets - 8);
+    let in_lists: usize = stats.chain_lengths.iter().enumerate().map(|(len, n)| len * n).sum();
+    assert!(in_lists < 200);
+    assert_eq!(stats.chain_lengths.iter().sum::<usize>() + stats.tree_buckets, stats.buckets);
+}
@@ -0,0 +1,182 @@
+use mutcrab::collection::map::{Entry, Map, TreeHashMap};
+use mutcrab::collection::set::TreeHashSet;
+use mutcrab::concurrent::ConcurrentHashMap;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+use std::rc::Rc;
+
+mod common;
+use common::ConstHasher;
+
+/// every key gets the same full hash
+type FloodedMap<K, V> = TreeHashMap<K, V, BuildHasherDefault<ConstHasher<7>>>;
+
+thread_local! {
+    static COMPARISONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// a key that counts how often it is compared
+#[derive(Debug, Clone, Copy)]
+struct Counted(u32);
+
+impl Counted {
+    /// the comparisons `f` makes on this thread
+    fn comparisons(f: impl FnOnce()) -> usize {
+        COMPARISONS.set(0);
+        f();
+        COMPARISONS.get()
+    }
+}
+
+impl Hash for Counted {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl PartialEq for Counted {
+    fn eq(&self, other: &Self) -> bool {
+        COMPARISONS.set(COMPARISONS.get() + 1);
+        self.0 == other.0
+    }
+}
+
+impl Eq for Counted {}
+
+impl PartialOrd for Counted {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Counted {
+    fn cmp(&self, other: &Self) -> Ordering {
+        COMPARISONS.set(COMPARISONS.get() + 1);
+        self.0.cmp(&other.0)
+    }
+}
+
+#[test]
+fn ordered_collisions_test() {
+    let mut map = FloodedMap::<u32, u32>::with_hasher(Default::default());
+    map.set_ordered_collisions(true);
+    assert!(map.is_ordered_collisions());
+    for i in (0..3000).rev() {
+        assert_eq!(map.put(i, i), None);
+    }
+    assert_eq!(map.put(42, 420), Some(42));
+    assert_eq!(map.len(), 3000);
+    let stats = map.stats();
+    assert_eq!((stats.tree_buckets, stats.tree_hashes, stats.longest_chain), (1, 1, 3000));
+    // the `Map` methods and the inherent ones find the same entries
+    assert!((0..3000).all(|i| Map::get(&map, &i) == Some(if i == 42 { &420 } else { &i })));
+    assert_eq!(map.get(&2999), Some(&2999));
+
+    match map.entry(7) {
+        Entry::Occupied(x) => assert_eq!(x.remove_entry(), (7, 7)),
+        Entry::Vacant(_) => panic!("7 is in the map"),
+    }
+    *map.entry(7).or_insert(0) += 1;
+    *map.entry(8).or_insert(0) += 1;
+    assert_eq!((map[&7], map[&8]), (1, 9));
+    for i in 100..200 {
+        assert_eq!(Map::remove(&mut map, &i), Some(i));
+    }
+    assert_eq!(Map::remove(&mut map, &150), None);
+    assert_eq!(map.remove(&200), Some(200));
+    *Map::get_mut(&mut map, &201).unwrap() += 1;
+    assert_eq!(map[&201], 202);
+    assert_eq!(map.put(150, 1), None);
+    assert_eq!(map.len(), 2900);
+}
+
+#[test]
+fn extract_and_clone_test() {
+    let value = Rc::new(0);
+    let mut map = FloodedMap::with_hasher(Default::default());
+    map.set_ordered_collisions(true);
+    for i in 0..500 {
+        map.put(i, value.clone());
+    }
+    assert_eq!(map.extract_if(|k, _| k % 2 == 0).count(), 250);
+    // the cursor removed the tree nodes of the freed entries
+    for i in 0..500 {
+        assert_eq!(map.put(i, value.clone()).is_some(), i % 2 == 1);
+    }
+    let copy = map.clone();
+    assert!(copy.is_ordered_collisions());
+    assert_eq!(copy.len(), 500);
+    assert!((0..500).all(|i| Map::contains_key(&copy, &i)));
+    map.retain(|k, _| *k >= 490);
+    assert_eq!(map.len(), 10);
+    assert_eq!(map.put(3, value.clone()), None);
+    drop(copy);
+    drop(map);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
+
+#[test]
+fn resize_and_switch_test() {
+    let mut map = FloodedMap::<String, usize>::with_hasher(Default::default());
+    let mut expect = std::collections::HashMap::new();
+    map.set_incremental_rehash(true);
+    for i in 0..200 {
+        map.put(i.to_string(), i);
+        expect.insert(i.to_string(), i);
+    }
+    // turning it on sorts the existing tree buckets
+    map.set_ordered_collisions(true);
+    for i in 200..2000 {
+        assert_eq!(map.put(i.to_string(), i), expect.insert(i.to_string(), i));
+        if i % 3 == 0 {
+            let key = (i / 2).to_string();
+            assert_eq!(Map::remove(&mut map, &key), expect.remove(&key));
+        }
+    }
+    assert_eq!(map.len(), expect.len());
+    assert!(expect.iter().all(|(k, v)| map.get(k) == Some(v)));
+    map.set_ordered_collisions(false);
+    assert!(!map.is_ordered_collisions());
+    assert!(expect.iter().all(|(k, v)| Map::get(&map, k) == Some(v)));
+    map.put("x".to_string(), 0);
+    map.shrink_to_fit();
+    assert_eq!(map.iter().count(), map.len());
+}
+
+#[test]
+fn ordered_lookup_comparisons_test() {
+    let mut map = FloodedMap::<Counted, u32>::with_hasher(Default::default());
+    for i in 0..3000 {
+        map.put(Counted(i), i);
+    }
+    // without an order the keys of the hash are compared one by one
+    assert_eq!(Counted::comparisons(|| assert_eq!(map.get(&Counted(3000)), None)), 3000);
+    map.set_ordered_collisions(true);
+    let mut set = TreeHashSet::<Counted, BuildHasherDefault<ConstHasher<7>>>::with_hasher(Default::default());
+    set.set_ordered_collisions(true);
+    let mut concurrent = ConcurrentHashMap::<Counted, u32, BuildHasherDefault<ConstHasher<7>>>::with_hasher(Default::default());
+    concurrent.set_ordered_collisions(true);
+    for i in 0..3000 {
+        set.insert(Counted(i));
+        concurrent.put(Counted(i), i);
+    }
+    // a tree of 3000 keys is at most 24 deep
+    let limit = 30;
+    for i in [0, 1500, 2999, 3000] {
+        let key = Counted(i);
+        let value = if i < 3000 { Some(i) } else { None };
+        assert!(Counted::comparisons(|| assert_eq!(map.get(&key), value.as_ref())) < limit);
+        assert!(Counted::comparisons(|| assert_eq!(map.get_mut(&key).copied(), value)) < limit);
+        assert!(Counted::comparisons(|| assert_eq!(map.contains_key(&key), value.is_some())) < limit);
+        assert!(Counted::comparisons(|| assert_eq!(set.contains(&key), value.is_some())) < limit);
+        assert!(Counted::comparisons(|| assert_eq!(concurrent.get(&key), value)) < limit);
+        assert!(Counted::comparisons(|| assert_eq!(concurrent.contains_key(&key), value.is_some())) < limit);
+    }
+    assert!(Counted::comparisons(|| assert_eq!(map[&Counted(700)], 700)) < limit);
+    assert!(Counted::comparisons(|| assert_eq!(map.remove(&Counted(700)), Some(700))) < limit);
+    assert!(Counted::comparisons(|| assert!(set.remove(&Counted(700)))) < limit);
+    assert!(Counted::comparisons(|| assert_eq!(concurrent.remove(&Counted(700)), Some(700))) < limit);
+    assert_eq!((map.get(&Counted(700)), set.contains(&Counted(700)), concurrent.get(&Counted(700))), (None, false, None));
+    assert_eq!((map.len(), set.len(), concurrent.len()), (2999, 2999, 2999));
+}
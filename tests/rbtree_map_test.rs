@@ -1,6 +1,11 @@
 
 
-use mutcrab::collection::map::{TreeHashMap as HashMap, Map};
+mod common;
+
+use common::{IntegerHasher, ModHasher};
+use mutcrab::collection::map::{Entry, TreeHashMap as HashMap, Map};
+use std::hash::BuildHasherDefault;
+use std::rc::Rc;
 
 
 #[test]
@@ -18,3 +23,257 @@ fn my_map2_test() {
         println!("forache key {} value is {}", *k, *v);
     });
 }
+
+#[test]
+fn tree_bucket_resize_test() {
+    type BuildHasher = BuildHasherDefault<IntegerHasher>;
+    let mut map: HashMap<u32, u32, BuildHasher> = HashMap::with_hasher(BuildHasher::new());
+    // nine keys in bucket 0 make it a tree, they all stay in bucket 0 after the resize
+    let keys: Vec<u32> = (0..9).map(|x| x * 32).chain(1..5).collect();
+    for k in &keys {
+        map.put(*k, *k + 100);
+    }
+    assert_eq!(map.size(), keys.len());
+    for k in &keys {
+        assert_eq!(map.get(k), Some(&(k + 100)));
+    }
+}
+
+#[test]
+fn tree_bucket_untreeify_on_resize_test() {
+    type BuildHasher = BuildHasherDefault<IntegerHasher>;
+    let mut map: HashMap<u32, u32, BuildHasher> = HashMap::with_hasher(BuildHasher::new());
+    // the resize splits the tree in bucket 0 into two short lists, 5 keys stay and 4 move to bucket 16
+    let keys: Vec<u32> = (0..9).map(|x| x * 16).chain(1..5).collect();
+    for k in &keys {
+        map.put(*k, *k + 100);
+    }
+    assert_eq!(map.size(), keys.len());
+    for k in &keys {
+        assert_eq!(map.get(k), Some(&(k + 100)));
+    }
+}
+
+/// every key lands in bucket 0, keys `0..16` share 4 distinct hashes
+type CollisionHasher = ModHasher<4, 64>;
+
+#[test]
+fn tree_bucket_remove_test() {
+    type BuildHasher = BuildHasherDefault<CollisionHasher>;
+    let mut map: HashMap<u32, u32, BuildHasher> = HashMap::with_hasher(BuildHasher::new());
+    for i in 0..16 {
+        map.put(i, i + 100);
+    }
+    assert_eq!(map.size(), 16);
+    for i in 0..16 {
+        assert_eq!(map.remove(&i), Some(i + 100));
+        assert_eq!(map.remove(&i), None);
+        assert_eq!(map.size(), 15 - i as usize);
+        for j in i + 1..16 {
+            assert_eq!(map.get(&j), Some(&(j + 100)));
+        }
+    }
+    map.put(1, 1);
+    assert_eq!(map.get(&1), Some(&1));
+}
+
+#[test]
+fn tree_bucket_drain_test() {
+    type BuildHasher = BuildHasherDefault<CollisionHasher>;
+    let value = Rc::new(());
+    let mut map: HashMap<u32, Rc<()>, BuildHasher> = HashMap::with_hasher(BuildHasher::new());
+    for i in 0..16 {
+        map.put(i, value.clone());
+    }
+    let drain = map.drain();
+    assert_eq!(drain.len(), 16);
+    let mut keys: Vec<u32> = drain.map(|(k, _)| k).collect();
+    keys.sort();
+    assert_eq!(keys, (0..16).collect::<Vec<_>>());
+    assert_eq!(map.size(), 0);
+    assert_eq!(Rc::strong_count(&value), 1);
+
+    for i in 0..16 {
+        map.put(i, value.clone());
+    }
+    assert_eq!(map.get(&3), Some(&value));
+    let mut iter = map.into_iter();
+    assert!(iter.next().is_some());
+    assert_eq!(iter.len(), 15);
+    drop(iter);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
+
+#[test]
+fn into_iter_test() {
+    let mut map = HashMap::<i32, i32>::new();
+    for i in 0..100 {
+        map.put(i, i * 2);
+    }
+    let mut entries: Vec<(i32, i32)> = map.into_iter().collect();
+    entries.sort();
+    assert_eq!(entries, (0..100).map(|i| (i, i * 2)).collect::<Vec<_>>());
+}
+
+#[test]
+fn tree_bucket_iter_mut_test() {
+    type BuildHasher = BuildHasherDefault<CollisionHasher>;
+    let mut map: HashMap<u32, u32, BuildHasher> = HashMap::with_hasher(BuildHasher::new());
+    for i in 0..32 {
+        map.put(i, i);
+    }
+    for (k, v) in &mut map {
+        *v += *k;
+    }
+    for v in map.values_mut() {
+        *v += 1;
+    }
+    assert_eq!(map.iter().len(), 32);
+    for i in 0..32 {
+        assert_eq!(map.get(&i), Some(&(i * 2 + 1)));
+    }
+    let mut keys: Vec<u32> = map.keys().copied().collect();
+    keys.sort();
+    assert_eq!(keys, (0..32).collect::<Vec<_>>());
+    let mut keys: Vec<u32> = map.into_keys().collect();
+    keys.sort();
+    assert_eq!(keys, (0..32).collect::<Vec<_>>());
+}
+
+/// eight hashes, all in bucket 0
+type StrCollisionHasher = ModHasher<8, 64>;
+
+#[test]
+fn tree_bucket_borrowed_lookup_test() {
+    type BuildHasher = BuildHasherDefault<StrCollisionHasher>;
+    let mut map: HashMap<String, usize, BuildHasher> = HashMap::with_hasher(BuildHasher::new());
+    for i in 0..40 {
+        map.put(format!("key{}", i), i);
+    }
+    for i in 0..40 {
+        assert_eq!(map.get(format!("key{}", i).as_str()), Some(&i));
+    }
+    assert!(map.contains_key("key39"));
+    *map.get_mut("key5").unwrap() += 100;
+    assert_eq!(map.remove("key5"), Some(105));
+    assert_eq!(map.get("key5"), None);
+    assert_eq!(map.size(), 39);
+}
+
+#[test]
+fn tree_bucket_entry_remove_test() {
+    type BuildHasher = BuildHasherDefault<CollisionHasher>;
+    let value = Rc::new(());
+    let mut map: HashMap<u32, Rc<()>, BuildHasher> = HashMap::with_hasher(BuildHasher::new());
+    for i in 0..16 {
+        map.put(i, value.clone());
+    }
+    for i in 0..12 {
+        match map.entry(i) {
+            Entry::Occupied(x) => assert_eq!(x.remove_entry().0, i),
+            Entry::Vacant(_) => panic!("{} is present", i),
+        }
+    }
+    assert_eq!(map.size(), 4);
+    assert_eq!(Rc::strong_count(&value), 5);
+    for i in 12..16 {
+        assert_eq!(map.get(&i), Some(&value));
+    }
+    *map.entry(20).or_insert_with(|| value.clone()) = Rc::new(());
+    assert_eq!(Rc::strong_count(&value), 5);
+}
+
+#[test]
+fn tree_bucket_reserve_shrink_test() {
+    type BuildHasher = BuildHasherDefault<CollisionHasher>;
+    let mut map: HashMap<u32, u32, BuildHasher> = HashMap::with_hasher(BuildHasher::new());
+    map.extend((0..64).map(|i| (i, i)));
+    // grows from 128 to 2048 buckets in one step, the tree buckets are rehashed
+    map.reserve(1500);
+    assert!(map.capacity() >= 1564);
+    for i in 0..64 {
+        assert_eq!(map.get(&i), Some(&i));
+    }
+    map.retain(|k, _| *k < 40);
+    map.shrink_to_fit();
+    assert!(map.capacity() >= 40 && map.capacity() < 1564);
+    for i in 0..64 {
+        assert_eq!(map.get(&i), if i < 40 { Some(&i) } else { None });
+    }
+    assert_eq!(map.remove(&7), Some(7));
+    assert_eq!(map.size(), 39);
+    map.clear();
+    assert_eq!(map.size(), 0);
+    assert_eq!(map.get(&1), None);
+}
+
+#[test]
+fn tree_bucket_clone_test() {
+    type BuildHasher = BuildHasherDefault<CollisionHasher>;
+    let map: HashMap<u32, String, BuildHasher> = (0..32).map(|i| (i, i.to_string())).collect();
+    let mut copy = map.clone();
+    assert_eq!(copy, map);
+    assert_eq!(copy.size(), 32);
+    for i in 0..32 {
+        assert_eq!(copy[&i], i.to_string());
+    }
+    for i in 0..20 {
+        assert_eq!(copy.remove(&i), Some(i.to_string()));
+    }
+    assert_eq!(copy.size(), 12);
+    assert_eq!(map.size(), 32);
+    assert_eq!(map[&5], "5");
+    copy.extend((0..20).map(|i| (i, i.to_string())));
+    assert_eq!(copy, map);
+    assert_eq!(format!("{:?}", HashMap::<u32, u32, BuildHasher>::default()), "{}");
+}
+
+#[test]
+fn tree_bucket_retain_test() {
+    type BuildHasher = BuildHasherDefault<CollisionHasher>;
+    let mut map: HashMap<u32, u32, BuildHasher> = HashMap::with_hasher(BuildHasher::new());
+    for i in 0..32 {
+        map.put(i, i);
+    }
+    map.retain(|k, _| k % 4 != 1);
+    assert_eq!(map.size(), 24);
+    map.retain(|k, v| {
+        *v *= 10;
+        k % 8 < 3
+    });
+    assert_eq!(map.size(), 8);
+    for i in 0..32 {
+        let expected = if i % 8 < 3 && i % 4 != 1 { Some(i * 10) } else { None };
+        assert_eq!(map.get(&i).copied(), expected);
+    }
+    map.retain(|_, _| false);
+    assert!(map.is_empty());
+    map.put(1, 1);
+    assert_eq!(map.get(&1), Some(&1));
+}
+
+#[test]
+fn tree_bucket_extract_if_test() {
+    type BuildHasher = BuildHasherDefault<CollisionHasher>;
+    let mut map: HashMap<u32, u32, BuildHasher> = HashMap::with_hasher(BuildHasher::new());
+    for i in 0..32 {
+        map.put(i, i + 100);
+    }
+    let mut odd: Vec<u32> = map.extract_if(|k, _| k % 2 == 1).map(|(k, _)| k).collect();
+    odd.sort();
+    assert_eq!(odd, (1..32).step_by(2).collect::<Vec<_>>());
+    assert_eq!(map.size(), 16);
+
+    // stop early, then check the tree bucket is still usable
+    assert_eq!(map.extract_if(|_, _| true).take(5).count(), 5);
+    assert_eq!(map.size(), 11);
+    assert_eq!(map.iter().count(), 11);
+    for i in 0..32 {
+        map.put(i, i);
+    }
+    assert_eq!(map.size(), 32);
+    for i in 0..32 {
+        assert_eq!(map.remove(&i), Some(i));
+    }
+    assert!(map.is_empty());
+}
@@ -1,4 +1,6 @@
 use mutcrab::collection::tree::RBTree;
+use std::ops::Bound;
+use std::rc::Rc;
 
 #[test]
 fn test_tree_put() {
@@ -7,8 +9,8 @@ fn test_tree_put() {
     tree.put(2,2);
     tree.put(3,3);
     tree.put(4,4);
-    assert_eq!(tree.get(&100).map(|x|x), None);
-    assert_eq!(tree.get(&3).map(|x| *x), Some(3));
+    assert_eq!(tree.get(&100), None);
+    assert_eq!(tree.get(&3).copied(), Some(3));
 }
 
 #[test]
@@ -18,8 +20,10 @@ fn test_tree_iterator() {
     tree.put(2,2);
     tree.put(3,3);
     tree.put(4,4);
-    let arr = tree.into_iter().map(|x| *x.0).collect::<Vec<i32>>();
-    assert_eq!(arr, vec![2, 3, 4, 1]);
+    let arr = tree.iter().map(|x| *x.0).collect::<Vec<i32>>();
+    assert_eq!(arr, vec![1, 2, 3, 4]);
+    let arr = tree.iter().rev().map(|x| *x.0).collect::<Vec<i32>>();
+    assert_eq!(arr, vec![4, 3, 2, 1]);
 }
 
 #[test]
@@ -29,11 +33,155 @@ fn test_tree_put2() {
         12, 23, 45, 34, 40, 67, 78, 89, 90, 100, 110, 120, 130, 140, 150, 160, 170, 180,
     ];
     for i in &arr {
-        tree.put(i.clone(), i.clone());
+        tree.put(*i, *i);
     }
-    let rs = tree.into_iter().map(|x| *x.0).collect::<Vec<i32>>();
+    let rs = tree.iter().map(|x| *x.0).collect::<Vec<i32>>();
     assert_eq!(
         rs,
-        vec![89, 90, 100, 110, 120, 130, 140, 150, 160, 170, 180, 78, 67, 45, 40, 34, 23, 12]
+        vec![12, 23, 34, 40, 45, 67, 78, 89, 90, 100, 110, 120, 130, 140, 150, 160, 170, 180]
     );
 }
+
+#[test]
+fn test_tree_remove() {
+    let mut tree = RBTree::new();
+    for i in 0..100 {
+        tree.put(i, i * 2);
+    }
+    for i in (0..100).step_by(3) {
+        assert_eq!(tree.remove(&i), Some(i * 2));
+    }
+    assert_eq!(tree.remove(&3), None);
+    for i in 0..100 {
+        assert_eq!(tree.contains(&i), i % 3 != 0);
+    }
+    assert_eq!(tree.len(), 66);
+    let keys = tree.iter().map(|x| *x.0).collect::<Vec<i32>>();
+    assert_eq!(keys, (0..100).filter(|x| x % 3 != 0).collect::<Vec<i32>>());
+}
+
+#[test]
+fn test_tree_navigable() {
+    let mut tree = RBTree::new();
+    assert_eq!(tree.first_key_value(), None);
+    assert_eq!(tree.floor(&1), None);
+    assert_eq!(tree.lower(&1), None);
+    assert_eq!(tree.higher(&1), None);
+    assert_eq!(tree.pop_last(), None);
+    for i in (10..=50).step_by(10) {
+        tree.put(i, i * 2);
+    }
+    assert_eq!(tree.first_key_value(), Some((&10, &20)));
+    assert_eq!(tree.last_key_value(), Some((&50, &100)));
+
+    assert_eq!(tree.floor(&30), Some((&30, &60)));
+    assert_eq!(tree.floor(&35), Some((&30, &60)));
+    assert_eq!(tree.floor(&5), None);
+    assert_eq!(tree.ceiling(&30), Some((&30, &60)));
+    assert_eq!(tree.ceiling(&35), Some((&40, &80)));
+    assert_eq!(tree.ceiling(&55), None);
+    assert_eq!(tree.lower(&30), Some((&20, &40)));
+    assert_eq!(tree.lower(&35), Some((&30, &60)));
+    assert_eq!(tree.lower(&10), None);
+    assert_eq!(tree.higher(&30), Some((&40, &80)));
+    assert_eq!(tree.higher(&25), Some((&30, &60)));
+    assert_eq!(tree.higher(&50), None);
+
+    assert_eq!(tree.pop_first(), Some((10, 20)));
+    assert_eq!(tree.pop_last(), Some((50, 100)));
+    assert_eq!(tree.first_key_value(), Some((&20, &40)));
+    assert_eq!(tree.last_key_value(), Some((&40, &80)));
+    assert_eq!(tree.lower(&20), None);
+    assert_eq!(tree.higher(&40), None);
+}
+
+#[test]
+fn test_tree_range() {
+    let mut tree = RBTree::new();
+    for i in (0..100).step_by(10) {
+        tree.put(i, i.to_string());
+    }
+    let keys = |it: &mut dyn Iterator<Item = (&i32, &String)>| it.map(|x| *x.0).collect::<Vec<i32>>();
+    assert_eq!(keys(&mut tree.range(20..50)), vec![20, 30, 40]);
+    assert_eq!(keys(&mut tree.range(15..=50)), vec![20, 30, 40, 50]);
+    assert_eq!(keys(&mut tree.range(..25)), vec![0, 10, 20]);
+    assert_eq!(keys(&mut tree.range(85..)), vec![90]);
+    assert_eq!(keys(&mut tree.range(..)).len(), 10);
+    assert_eq!(keys(&mut tree.range((Bound::Excluded(20), Bound::Excluded(50)))), vec![30, 40]);
+    assert_eq!(keys(&mut tree.range(20..50).rev()), vec![40, 30, 20]);
+    assert_eq!(keys(&mut tree.range(41..49)), Vec::<i32>::new());
+    assert_eq!(keys(&mut tree.range((Bound::Included(60), Bound::Excluded(30)))), Vec::<i32>::new());
+    assert_eq!(keys(&mut tree.range(100..)), Vec::<i32>::new());
+
+    let mut range = tree.range(10..=40);
+    assert_eq!(range.next(), Some((&10, &"10".to_string())));
+    assert_eq!(range.next_back().map(|x| *x.0), Some(40));
+    assert_eq!(range.next().map(|x| *x.0), Some(20));
+    assert_eq!(range.next_back().map(|x| *x.0), Some(30));
+    assert_eq!(range.next(), None);
+    assert_eq!(range.next_back(), None);
+}
+
+#[test]
+fn test_tree_double_ended_iterator() {
+    let mut tree = RBTree::new();
+    assert!(tree.is_empty());
+    assert_eq!(tree.iter().next(), None);
+    for i in [5, 1, 9, 3, 7, 2, 8] {
+        tree.put(i, i);
+    }
+    assert_eq!(tree.len(), 7);
+    let mut iter = tree.iter();
+    assert_eq!(iter.len(), 7);
+    assert_eq!(iter.next().map(|x| *x.0), Some(1));
+    assert_eq!(iter.next_back().map(|x| *x.0), Some(9));
+    assert_eq!(iter.len(), 5);
+    let rest = iter.map(|x| *x.0).collect::<Vec<i32>>();
+    assert_eq!(rest, vec![2, 3, 5, 7, 8]);
+
+    tree.remove(&1);
+    tree.remove(&9);
+    tree.pop_first();
+    assert_eq!(tree.len(), 4);
+    let arr = tree.iter().rev().map(|x| *x.0).collect::<Vec<i32>>();
+    assert_eq!(arr, vec![8, 7, 5, 3]);
+}
+
+#[test]
+fn test_tree_iter_mut() {
+    let mut tree = RBTree::new();
+    for i in 0..20 {
+        tree.put(i, i);
+    }
+    for (k, v) in tree.iter_mut() {
+        *v += k;
+    }
+    for v in tree.values_mut().rev().take(5) {
+        *v = 0;
+    }
+    assert_eq!(tree.get(&3), Some(&6));
+    assert_eq!(tree.get(&15), Some(&0));
+    assert_eq!(tree.keys().copied().collect::<Vec<i32>>(), (0..20).collect::<Vec<_>>());
+    assert_eq!(tree.values().len(), 20);
+}
+
+#[test]
+fn test_tree_into_iter() {
+    let value = Rc::new(());
+    let mut tree = RBTree::new();
+    for i in 0..20 {
+        tree.put(i, value.clone());
+    }
+    let mut iter = tree.into_iter();
+    assert_eq!(iter.next().map(|x| x.0), Some(0));
+    assert_eq!(iter.next_back().map(|x| x.0), Some(19));
+    assert_eq!(iter.len(), 18);
+    drop(iter);
+    assert_eq!(Rc::strong_count(&value), 1);
+
+    let mut tree = RBTree::new();
+    for i in 0..20 {
+        tree.put(i, i * 2);
+    }
+    assert_eq!(tree.into_values().rev().take(3).collect::<Vec<i32>>(), vec![38, 36, 34]);
+}
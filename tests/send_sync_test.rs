@@ -0,0 +1,98 @@
+use std::sync::{mpsc, Arc, RwLock};
+use std::thread;
+use mutcrab::collection::list::LinkedList;
+use mutcrab::collection::map::{HashMap, Map, TreeHashMap};
+use mutcrab::collection::tree::RBTree;
+use mutcrab::concurrent::thread::ThreadPool;
+
+#[test]
+fn hashmap_move_into_pool_test() {
+    let pool = ThreadPool::new(2);
+    let (tx, rx) = mpsc::channel();
+    for part in 0..4 {
+        let mut map: HashMap<String, Vec<i32>> = HashMap::new();
+        let tx = tx.clone();
+        pool.execute(move || {
+            for i in 0..100 {
+                map.put(format!("{}-{}", part, i), vec![part, i]);
+            }
+            tx.send(map).unwrap();
+        });
+    }
+    drop(tx);
+
+    let mut total = 0;
+    for map in rx.iter().take(4) {
+        assert_eq!(map.len(), 100);
+        total += map.len();
+    }
+    assert_eq!(total, 400);
+}
+
+#[test]
+fn tree_hashmap_rwlock_test() {
+    let map: Arc<RwLock<TreeHashMap<String, i32>>> = Arc::new(RwLock::new(TreeHashMap::new()));
+    thread::scope(|s| {
+        for t in 0..4 {
+            let map = map.clone();
+            s.spawn(move || {
+                for i in 0..250 {
+                    map.write().unwrap().put(format!("{}", t * 250 + i), i);
+                }
+            });
+        }
+    });
+
+    thread::scope(|s| {
+        for t in 0..4 {
+            let map = &map;
+            s.spawn(move || {
+                let guard = map.read().unwrap();
+                for i in 0..250 {
+                    assert_eq!(guard.get(format!("{}", t * 250 + i).as_str()), Some(&i));
+                }
+            });
+        }
+    });
+    assert_eq!(map.read().unwrap().len(), 1000);
+}
+
+#[test]
+fn rbtree_across_threads_test() {
+    let mut tree = RBTree::new();
+    for i in 0..100 {
+        tree.put(i, i.to_string());
+    }
+    let tree = thread::spawn(move || {
+        tree.remove(&50);
+        tree
+    }).join().unwrap();
+    assert_eq!(tree.len(), 99);
+
+    let tree = Arc::new(tree);
+    let handles: Vec<_> = (0..2).map(|_| {
+        let tree = tree.clone();
+        thread::spawn(move || tree.iter().count())
+    }).collect();
+    for h in handles {
+        assert_eq!(h.join().unwrap(), 99);
+    }
+}
+
+#[test]
+fn linked_list_across_threads_test() {
+    let mut list = LinkedList::new();
+    for i in 0..10 {
+        list.add(i.to_string());
+    }
+    let list = thread::spawn(move || {
+        list.add("10".to_string());
+        list
+    }).join().unwrap();
+    assert_eq!(list.size(), 11);
+
+    let list = Arc::new(list);
+    let reader = list.clone();
+    let joined = thread::spawn(move || reader.iter().cloned().collect::<Vec<_>>().join(",")).join().unwrap();
+    assert_eq!(joined, "0,1,2,3,4,5,6,7,8,9,10");
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "serde")]
+
+use mutcrab::collection::graph::ImmutableGraph;
+use mutcrab::collection::list::{LinkedBlockingQueue, LinkedList};
+use mutcrab::collection::map::{HashMap, Map, TreeHashMap};
+use mutcrab::collection::tree::RBTree;
+
+#[test]
+fn hashmap_json_test() {
+    let mut map = HashMap::new();
+    for i in 0..100 {
+        map.put(i.to_string(), vec![i; 3]);
+    }
+    let json = serde_json::to_string(&map).unwrap();
+    let copy: HashMap<String, Vec<i32>> = serde_json::from_str(&json).unwrap();
+    assert_eq!(copy, map);
+
+    let tree_map: TreeHashMap<String, Vec<i32>> = serde_json::from_str(&json).unwrap();
+    assert_eq!(tree_map.len(), 100);
+    assert_eq!(tree_map.get("42"), Some(&vec![42; 3]));
+    let json = serde_json::to_string(&tree_map).unwrap();
+    assert_eq!(serde_json::from_str::<HashMap<String, Vec<i32>>>(&json).unwrap(), map);
+
+    assert!(serde_json::from_str::<HashMap<String, i32>>("[1, 2]").is_err());
+}
+
+#[test]
+fn rbtree_json_test() {
+    let mut tree = RBTree::new();
+    for i in [5, 3, 9, 1, 7] {
+        tree.put(i, i * 10);
+    }
+    // the keys are written in order
+    let json = serde_json::to_string(&tree).unwrap();
+    assert_eq!(json, r#"{"1":10,"3":30,"5":50,"7":70,"9":90}"#);
+    let copy: RBTree<i32, i32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(copy.iter().collect::<Vec<_>>(), tree.iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn list_json_test() {
+    let mut list = LinkedList::new();
+    for i in 0..5 {
+        list.add_last(i);
+    }
+    let json = serde_json::to_string(&list).unwrap();
+    assert_eq!(json, "[0,1,2,3,4]");
+    let copy: LinkedList<i32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(copy.iter().copied().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+
+    let queue = LinkedBlockingQueue::with_capacity(10);
+    for i in 0..5 {
+        queue.push(i.to_string());
+    }
+    assert_eq!(queue.poll(), Some("0".to_string()));
+    let json = serde_json::to_string(&queue).unwrap();
+    assert_eq!(json, r#"["1","2","3","4"]"#);
+    let copy: LinkedBlockingQueue<String> = serde_json::from_str(&json).unwrap();
+    assert_eq!(copy.len(), 4);
+    assert_eq!(copy.take(), "1");
+}
+
+#[test]
+fn graph_json_test() {
+    let json = r#"{"nodes":["a","b","c","d"],"edges":[[0,1],[1,2],[1,3]]}"#;
+    let graph: ImmutableGraph<String> = serde_json::from_str(json).unwrap();
+    assert_eq!(graph.size(), 4);
+    assert!(graph.contains_node(&"c".to_string()));
+    let copy = serde_json::to_string(&graph).unwrap();
+    let again: serde_json::Value = serde_json::from_str(&copy).unwrap();
+    assert_eq!(again["nodes"], serde_json::json!(["a", "b", "c", "d"]));
+    let mut edges: Vec<(u32, u32)> = serde_json::from_value(again["edges"].clone()).unwrap();
+    edges.sort();
+    assert_eq!(edges, vec![(0, 1), (1, 2), (1, 3)]);
+
+    assert!(serde_json::from_str::<ImmutableGraph<String>>(r#"{"nodes":["a","a"],"edges":[]}"#).is_err());
+    assert!(serde_json::from_str::<ImmutableGraph<String>>(r#"{"nodes":["a"],"edges":[[0,1]]}"#).is_err());
+}
@@ -0,0 +1,109 @@
+use mutcrab::collection::map::{Entry, HashMap, Map, TreeHashMap, TreeMap};
+
+#[test]
+fn tree_map_test() {
+    let mut map = TreeMap::<i32, &str>::of(3, "c");
+    map.put(1, "a");
+    map.put(2, "b");
+    assert_eq!(map.put(3, "cc"), Some("c"));
+    assert_eq!(map.size(), 3);
+    assert_eq!(map.get(&1), Some(&"a"));
+    assert_eq!(map.get(&4), None);
+    *map.get_mut(&2).unwrap() = "bb";
+    assert_eq!(map.remove(&2), Some("bb"));
+    assert_eq!(map.remove(&2), None);
+    assert!(map.contains_key(&3));
+    assert_eq!(map.len(), 2);
+}
+
+#[test]
+fn tree_map_entry_test() {
+    let mut map = TreeMap::<&str, i32>::new();
+    map.entry("a").or_insert(1);
+    assert_eq!(map.entry("a").take_insert(2), Some(1));
+    assert_eq!(*map.entry("b").or_insert(3), 3);
+    match map.entry("c") {
+        Entry::Occupied(_) => panic!("c is absent"),
+        Entry::Vacant(x) => assert_eq!(*x.key(), "c"),
+    }
+    assert_eq!(map.size(), 2);
+    assert_eq!(map.get(&"a"), Some(&2));
+    assert_eq!(map.get(&"b"), Some(&3));
+    match map.entry("a") {
+        Entry::Occupied(x) => assert_eq!(x.remove(), 2),
+        Entry::Vacant(_) => panic!("a is present"),
+    }
+    map.entry("b").and_modify(|x| *x += 1).or_default();
+    assert_eq!(map.get(&"b"), Some(&4));
+    assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&"b", &4)]);
+}
+
+#[test]
+fn tree_map_sorted_test() {
+    let mut map = TreeMap::<i32, i32>::new();
+    for i in [5, 3, 8, 1, 9, 2] {
+        map.put(i, i * 10);
+    }
+    let keys: Vec<i32> = map.iter().map(|(k, _)| *k).collect();
+    assert_eq!(keys, vec![1, 2, 3, 5, 8, 9]);
+    let keys: Vec<i32> = map.range(2..=5).map(|(k, _)| *k).collect();
+    assert_eq!(keys, vec![2, 3, 5]);
+    assert_eq!(map.first_key_value(), Some((&1, &10)));
+    assert_eq!(map.last_key_value(), Some((&9, &90)));
+
+    let mut keys = Vec::new();
+    map.foreach(|k, v| {
+        *v += 1;
+        keys.push(*k);
+    });
+    assert_eq!(keys, vec![1, 2, 3, 5, 8, 9]);
+    assert_eq!(map.get(&8), Some(&81));
+}
+
+fn count_words<M: Map<String, usize>>(map: &mut M, text: &str) {
+    for word in text.split_whitespace() {
+        *map.entry(word.to_string()).or_insert(0) += 1;
+    }
+}
+
+#[test]
+fn generic_map_test() {
+    let text = "b a c a b a";
+    let mut tree_map = TreeMap::new();
+    let mut hash_map = HashMap::new();
+    let mut tree_hash_map = TreeHashMap::new();
+    count_words(&mut tree_map, text);
+    count_words(&mut hash_map, text);
+    count_words(&mut tree_hash_map, text);
+    assert_eq!(tree_map.size(), 3);
+    assert_eq!(hash_map.size(), 3);
+    assert_eq!(tree_hash_map.size(), 3);
+    let words: Vec<(&str, usize)> = tree_map.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    assert_eq!(words, vec![("a", 3), ("b", 2), ("c", 1)]);
+    for (k, v) in &tree_map {
+        assert_eq!(hash_map.get(k), Some(v));
+        assert_eq!(tree_hash_map.get(k), Some(v));
+    }
+    drop_rare(&mut tree_map);
+    drop_rare(&mut hash_map);
+    assert_eq!(tree_map.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+    assert_eq!(hash_map.len(), 2);
+}
+
+fn drop_rare<M: Map<String, usize>>(map: &mut M) {
+    map.retain(|_, n| *n > 1);
+    assert!(map.contains_key("a"));
+    assert_eq!(map.remove("c"), None);
+}
+
+#[test]
+fn borrowed_lookup_test() {
+    let mut map = TreeMap::<String, i32>::new();
+    map.put("a".to_string(), 1);
+    map.put("b".to_string(), 2);
+    assert_eq!(map.get("a"), Some(&1));
+    assert!(map.contains_key("b"));
+    *map.get_mut("b").unwrap() += 1;
+    assert_eq!(map.remove("b"), Some(3));
+    assert_eq!(map.get("b"), None);
+}
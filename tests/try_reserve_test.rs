@@ -0,0 +1,133 @@
+use mutcrab::base::numbers::next_power_of_two;
+use mutcrab::collection::map::{FlatHashMap, HashMap, LinkedHashMap, Map, NodeAllocator, SlabAllocator, TreeHashMap, TreeMap, TryReserveError};
+
+#[test]
+fn next_power_of_two_test() {
+    assert_eq!(next_power_of_two(0), 1);
+    assert_eq!(next_power_of_two(17), 32);
+    // no longer clamped at 2^31
+    assert_eq!(next_power_of_two((1 << 31) + 1), 1 << 32);
+    assert_eq!(next_power_of_two((1 << 40) - 3), 1 << 40);
+}
+
+#[test]
+fn try_reserve_overflow_test() {
+    let mut map = HashMap::<u32, u32>::new();
+    for i in 0..100 {
+        map.put(i, i);
+    }
+    assert_eq!(map.try_reserve(usize::MAX), Err(TryReserveError::CapacityOverflow));
+    assert_eq!(map.try_reserve(usize::MAX / 2), Err(TryReserveError::CapacityOverflow));
+    // the failed calls leave the map as it was
+    assert_eq!(map.len(), 100);
+    assert!((0..100).all(|i| map.get(&i) == Some(&i)));
+    assert_eq!(map.try_reserve(1000), Ok(()));
+    assert!(map.capacity() >= 1100);
+    assert!(map.allocator().slots() >= 1100);
+    assert!(map.try_reserve(usize::MAX).unwrap_err().to_string().contains("capacity overflow"));
+
+    let mut tree_map = TreeHashMap::<u32, u32>::new();
+    tree_map.put(1, 1);
+    assert_eq!(tree_map.try_reserve(usize::MAX), Err(TryReserveError::CapacityOverflow));
+    assert_eq!(tree_map.try_reserve(500), Ok(()));
+    assert!(tree_map.capacity() >= 501);
+    assert_eq!(tree_map.get(&1), Some(&1));
+}
+
+fn check_try_reserve<M: Map<u32, u32>>(mut map: M) {
+    for i in 0..100 {
+        assert_eq!(map.try_put(i, i), Ok(None));
+    }
+    assert_eq!(map.try_reserve(usize::MAX), Err(TryReserveError::CapacityOverflow));
+    assert_eq!(map.try_put(7, 70), Ok(Some(7)));
+    assert_eq!(map.len(), 100);
+    assert_eq!(map.try_reserve(1000), Ok(()));
+    assert!((100..1100).all(|i| map.try_put(i, i) == Ok(None)));
+    assert_eq!(map.get(&7), Some(&70));
+}
+
+#[test]
+fn every_map_try_reserve_test() {
+    check_try_reserve(HashMap::new());
+    check_try_reserve(TreeHashMap::new());
+    check_try_reserve(FlatHashMap::new());
+    check_try_reserve(LinkedHashMap::new());
+    check_try_reserve(TreeMap::new());
+
+    let mut map = FlatHashMap::<u32, u32>::new();
+    map.put(1, 1);
+    assert_eq!(map.try_reserve(usize::MAX / 2), Err(TryReserveError::CapacityOverflow));
+    let mut map = LinkedHashMap::<u32, u32>::new();
+    map.put(1, 1);
+    assert_eq!(map.try_reserve(usize::MAX / 2), Err(TryReserveError::CapacityOverflow));
+    assert_eq!(map.get(&1), Some(&1));
+}
+
+#[test]
+#[should_panic(expected = "capacity overflow")]
+fn flat_reserve_overflow_test() {
+    let mut map = FlatHashMap::<u32, u32>::new();
+    map.put(1, 1);
+    map.reserve(usize::MAX);
+}
+
+#[test]
+fn try_put_test() {
+    let mut map = HashMap::new();
+    for i in 0..1000 {
+        assert_eq!(map.try_put(i, i), Ok(None));
+    }
+    assert_eq!(map.try_put(7, 70), Ok(Some(7)));
+    assert_eq!(map.len(), 1000);
+
+    let mut tree_map = TreeHashMap::new();
+    tree_map.set_incremental_rehash(true);
+    for i in 0..1000 {
+        assert_eq!(tree_map.try_put(i, i), Ok(None));
+    }
+    assert_eq!(tree_map.try_put(7, 70), Ok(Some(7)));
+    assert_eq!(tree_map.get(&7), Some(&70));
+    assert_eq!(tree_map.len(), 1000);
+}
+
+#[test]
+fn try_put_replace_test() {
+    let mut map = HashMap::new();
+    let mut i = 0;
+    while i == 0 || map.allocator().slots() > map.len() {
+        map.put(i, i);
+        i += 1;
+    }
+    // every slot is taken, replacing a value must not add a chunk
+    let slots = map.allocator().slots();
+    assert_eq!(map.try_put(0, 1), Ok(Some(0)));
+    assert_eq!(map.allocator().slots(), slots);
+
+    let mut tree_map = TreeHashMap::new();
+    let mut i = 0;
+    while i == 0 || tree_map.allocator().slots() > tree_map.len() {
+        tree_map.put(i, i);
+        i += 1;
+    }
+    let slots = tree_map.allocator().slots();
+    assert_eq!(tree_map.try_put(0, 1), Ok(Some(0)));
+    assert_eq!(tree_map.allocator().slots(), slots);
+    assert_eq!(tree_map.try_put(i, i), Ok(None));
+    assert!(tree_map.allocator().slots() > slots);
+}
+
+#[test]
+fn slab_try_reserve_test() {
+    let mut slab = SlabAllocator::<u64>::new();
+    assert_eq!(slab.try_reserve(5000), Ok(()));
+    let slots = slab.slots();
+    assert!(slots >= 5000);
+    let nodes: Vec<_> = (0..5000).map(|i| slab.alloc(i)).collect();
+    // the reserved slots are enough
+    assert_eq!(slab.slots(), slots);
+    for node in nodes {
+        unsafe { slab.free(node) };
+    }
+    assert!(slab.try_reserve(usize::MAX).is_err());
+    assert_eq!(slab.slots(), slots);
+}